@@ -0,0 +1,43 @@
+#!/bin/bash
+
+# Regenerates the weights of the given pallets (all the benchmarked ones by default) by running
+# their benchmarks. Run it from the root of the repository, on the reference hardware.
+
+set -eu
+
+declare -A WEIGHTS=(
+  [pallet_aleph]=./pallet/src/weights.rs
+)
+
+PALLETS=("$@")
+if [ ${#PALLETS[@]} -eq 0 ]; then
+  PALLETS=("${!WEIGHTS[@]}")
+fi
+
+cargo build --release --features runtime-benchmarks -p aleph-node
+
+# The benchmarks run on a development chain with a single authority.
+BASE_PATH=$(mktemp -d)
+trap 'rm -rf "$BASE_PATH"' EXIT
+./target/release/aleph-node bootstrap-chain \
+  --base-path "$BASE_PATH" \
+  --account-ids 5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY \
+  --chain-type dev > "$BASE_PATH/chainspec.json"
+
+for pallet in "${PALLETS[@]}"; do
+  if [ -z "${WEIGHTS[$pallet]:-}" ]; then
+    echo "$pallet has no benchmarks."
+    exit 1
+  fi
+  ./target/release/aleph-node benchmark \
+    --chain="$BASE_PATH/chainspec.json" \
+    --steps=50 \
+    --repeat=20 \
+    --pallet="$pallet" \
+    --extrinsic='*' \
+    --execution=wasm \
+    --wasm-execution=compiled \
+    --heap-pages=4096 \
+    --output="${WEIGHTS[$pallet]}" \
+    --template=./.maintain/frame-weight-template.hbs
+done
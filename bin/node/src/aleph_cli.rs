@@ -1,18 +1,122 @@
-use aleph_primitives::DEFAULT_UNIT_CREATION_DELAY;
-use finality_aleph::UnitCreationDelay;
+use finality_aleph::AlephTuning;
+use std::{fs, path::PathBuf};
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt, Clone)]
 pub struct AlephCli {
+    /// A TOML file with the tuning of the finality gadget, with the sections `consensus`,
+    /// `aggregator`, `data_store` and `justification`. The flags below take precedence over it.
+    #[structopt(long, parse(from_os_str))]
+    pub aleph_tuning: Option<PathBuf>,
+
+    /// How long to wait before creating the first unit in a session, in milliseconds.
+    #[structopt(long)]
+    pub first_unit_delay: Option<u64>,
+
+    /// The round from which the unit creation delay grows exponentially.
+    #[structopt(long)]
+    pub unit_creation_slowdown_start: Option<usize>,
+
+    /// How long to wait before broadcasting a unit for the first time, in milliseconds.
+    #[structopt(long)]
+    pub unit_broadcast_delay: Option<u64>,
+
+    /// How many times the delay grows with every following broadcast of the same unit.
+    #[structopt(long)]
+    pub unit_broadcast_slowdown_base: Option<f64>,
+
+    /// How often AlephBFT checks whether it should act, in milliseconds.
+    #[structopt(long)]
+    pub consensus_tick_interval: Option<u64>,
+
+    /// How long to wait before repeating requests for missing units, in milliseconds.
+    #[structopt(long)]
+    pub consensus_requests_interval: Option<u64>,
+
+    /// How long to wait before rebroadcasting a block signature for the first time, in
+    /// milliseconds.
     #[structopt(long)]
-    pub unit_creation_delay: Option<u64>,
+    pub rmc_delay: Option<u64>,
+
+    /// How many imported blocks the consensus data store remembers.
+    #[structopt(long)]
+    pub available_blocks_cache_capacity: Option<usize>,
+
+    /// How long the consensus data store waits for a block before requesting it, in milliseconds.
+    #[structopt(long)]
+    pub request_block_after: Option<u64>,
+
+    /// How long to wait for a justification before requesting one, in milliseconds.
+    #[structopt(long)]
+    pub justification_notification_timeout: Option<u64>,
+
+    /// How many verified justifications to remember.
+    #[structopt(long)]
+    pub verified_justifications_cache_capacity: Option<usize>,
+}
+
+fn set<T: Copy>(value: &mut T, flag: Option<T>) {
+    if let Some(flag) = flag {
+        *value = flag;
+    }
 }
 
 impl AlephCli {
-    pub fn unit_creation_delay(&self) -> UnitCreationDelay {
-        UnitCreationDelay(
-            self.unit_creation_delay
-                .unwrap_or(DEFAULT_UNIT_CREATION_DELAY),
-        )
+    /// The tuning of the finality gadget, read from the file and the flags, and validated.
+    pub fn tuning(&self) -> Result<AlephTuning, String> {
+        let mut tuning = match &self.aleph_tuning {
+            Some(path) => {
+                let raw = fs::read_to_string(path)
+                    .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+                toml::from_str(&raw)
+                    .map_err(|e| format!("Cannot parse {}: {}", path.display(), e))?
+            }
+            None => AlephTuning::default(),
+        };
+
+        let consensus = &mut tuning.consensus;
+        set(&mut consensus.first_unit_delay_ms, self.first_unit_delay);
+        set(
+            &mut consensus.unit_creation_slowdown_start,
+            self.unit_creation_slowdown_start,
+        );
+        set(
+            &mut consensus.unit_broadcast_delay_ms,
+            self.unit_broadcast_delay,
+        );
+        set(
+            &mut consensus.unit_broadcast_slowdown_base,
+            self.unit_broadcast_slowdown_base,
+        );
+        set(
+            &mut consensus.tick_interval_ms,
+            self.consensus_tick_interval,
+        );
+        set(
+            &mut consensus.requests_interval_ms,
+            self.consensus_requests_interval,
+        );
+        set(&mut tuning.aggregator.rmc_delay_ms, self.rmc_delay);
+        set(
+            &mut tuning.data_store.available_blocks_cache_capacity,
+            self.available_blocks_cache_capacity,
+        );
+        set(
+            &mut tuning.data_store.request_block_after_ms,
+            self.request_block_after,
+        );
+        set(
+            &mut tuning.justification.notification_timeout_ms,
+            self.justification_notification_timeout,
+        );
+        set(
+            &mut tuning.justification.verified_cache_capacity,
+            self.verified_justifications_cache_capacity,
+        );
+
+        tuning
+            .validate()
+            .map_err(|e| format!("Invalid Aleph tuning: {}", e))?;
+        Ok(tuning)
     }
 }
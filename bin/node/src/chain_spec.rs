@@ -1,12 +1,12 @@
 use aleph_primitives::{
-    AuthorityId as AlephId, ADDRESSES_ENCODING, DEFAULT_MILLISECS_PER_BLOCK,
-    DEFAULT_SESSION_PERIOD, TOKEN_DECIMALS,
+    AuthorityId as AlephId, FinalityVersion, ADDRESSES_ENCODING, DEFAULT_MILLISECS_PER_BLOCK,
+    DEFAULT_SESSION_PERIOD, DEFAULT_UNIT_CREATION_DELAY, TOKEN_DECIMALS,
 };
 use aleph_runtime::{
     AccountId, AlephConfig, AuraConfig, BalancesConfig, GenesisConfig, SessionConfig, SessionKeys,
     Signature, SudoConfig, SystemConfig, VestingConfig, WASM_BINARY,
 };
-use finality_aleph::{MillisecsPerBlock, SessionPeriod};
+use finality_aleph::{MillisecsPerBlock, SessionPeriod, UnitCreationDelay};
 use libp2p::PeerId;
 use sc_service::config::BasePath;
 use sc_service::ChainType;
@@ -130,6 +130,11 @@ pub struct ChainParams {
     #[structopt(long)]
     millisecs_per_block: Option<u64>,
 
+    /// Time interval (in milliseconds) between units created by AlephBFT, until it is changed on
+    /// chain. Default is 300 ms
+    #[structopt(long)]
+    unit_creation_delay: Option<u64>,
+
     /// Chain name. Default is "Aleph Zero Development"
     #[structopt(long, default_value = "Aleph Zero Development")]
     chain_name: String,
@@ -179,6 +184,13 @@ impl ChainParams {
         )
     }
 
+    pub fn unit_creation_delay(&self) -> UnitCreationDelay {
+        UnitCreationDelay(
+            self.unit_creation_delay
+                .unwrap_or(DEFAULT_UNIT_CREATION_DELAY),
+        )
+    }
+
     pub fn chain_name(&self) -> &str {
         &self.chain_name
     }
@@ -273,6 +285,7 @@ fn genesis(
 ) -> GenesisConfig {
     let session_period = chain_params.session_period();
     let millisecs_per_block = chain_params.millisecs_per_block();
+    let unit_creation_delay = chain_params.unit_creation_delay();
 
     let special_accounts = match faucet_account {
         Some(faucet_id) => vec![sudo_account.clone(), faucet_id],
@@ -312,6 +325,9 @@ fn genesis(
             authorities: vec![],
             session_period: session_period.0,
             millisecs_per_block: millisecs_per_block.0,
+            unit_creation_delay: unit_creation_delay.0,
+            // New chains start with the newest finality version right away.
+            finality_version: FinalityVersion::LATEST,
         },
         session: SessionConfig {
             keys: authorities
@@ -3,6 +3,7 @@ use crate::chain_spec::{
 };
 use aleph_primitives::AuthorityId as AlephId;
 use aleph_runtime::AccountId;
+use finality_aleph::BlsKeystore;
 use libp2p::identity::{ed25519 as libp2p_ed25519, PublicKey};
 use sc_cli::{Error, KeystoreParams};
 use sc_keystore::LocalKeystore;
@@ -63,11 +64,18 @@ fn p2p_key(chain_params: &ChainParams, account_id: &AccountId) -> SerializablePe
     }
 }
 
-fn open_keystore(
+/// Generates a BLS key, unless there already is one
+fn ensure_bls_key(keystore: &BlsKeystore) {
+    if keystore.keys().expect("Could not read BLS keys").is_empty() {
+        keystore.generate_new().expect("Could not create BLS key");
+    }
+}
+
+fn keystore_config(
     keystore_params: &KeystoreParams,
     chain_params: &ChainParams,
     account_id: &AccountId,
-) -> impl SyncCryptoStore {
+) -> KeystoreConfig {
     let chain_id = chain_params.chain_id();
     let base_path: BasePath = chain_params
         .base_path()
@@ -76,24 +84,45 @@ fn open_keystore(
         .into();
 
     let config_dir = base_path.config_dir(chain_id);
-    match keystore_params
+    keystore_params
         .keystore_config(&config_dir)
         .expect("keystore configuration should be available")
-    {
-        (_, KeystoreConfig::Path { path, password }) => {
+        .1
+}
+
+fn open_keystore(
+    keystore_params: &KeystoreParams,
+    chain_params: &ChainParams,
+    account_id: &AccountId,
+) -> impl SyncCryptoStore {
+    match keystore_config(keystore_params, chain_params, account_id) {
+        KeystoreConfig::Path { path, password } => {
             LocalKeystore::open(path, password).expect("Keystore open should succeed")
         }
         _ => unreachable!("keystore_config always returns path and password; qed"),
     }
 }
 
+fn open_bls_keystore(
+    keystore_params: &KeystoreParams,
+    chain_params: &ChainParams,
+    account_id: &AccountId,
+) -> BlsKeystore {
+    match keystore_config(keystore_params, chain_params, account_id) {
+        KeystoreConfig::Path { path, .. } => BlsKeystore::open(path),
+        _ => unreachable!("keystore_config always returns path and password; qed"),
+    }
+}
+
 fn authority_keys(
     keystore: &impl SyncCryptoStore,
+    bls_keystore: &BlsKeystore,
     chain_params: &ChainParams,
     account_id: &AccountId,
 ) -> AuthorityKeys {
     let aura_key = aura_key(keystore);
     let aleph_key = aleph_key(keystore);
+    ensure_bls_key(bls_keystore);
     let peer_id = p2p_key(chain_params, account_id);
 
     let account_id = account_id.clone();
@@ -129,7 +158,9 @@ impl BootstrapChainCmd {
             .iter()
             .map(|account_id| {
                 let keystore = open_keystore(&self.keystore_params, &self.chain_params, account_id);
-                authority_keys(&keystore, &self.chain_params, account_id)
+                let bls_keystore =
+                    open_bls_keystore(&self.keystore_params, &self.chain_params, account_id);
+                authority_keys(&keystore, &bls_keystore, &self.chain_params, account_id)
             })
             .collect();
 
@@ -170,8 +201,10 @@ impl BootstrapNodeCmd {
     pub fn run(&self) -> Result<(), Error> {
         let account_id = &self.account_id();
         let keystore = open_keystore(&self.keystore_params, &self.chain_params, account_id);
+        let bls_keystore = open_bls_keystore(&self.keystore_params, &self.chain_params, account_id);
 
-        let authority_keys = authority_keys(&keystore, &self.chain_params, account_id);
+        let authority_keys =
+            authority_keys(&keystore, &bls_keystore, &self.chain_params, account_id);
         let keys_json = serde_json::to_string_pretty(&authority_keys)
             .expect("serialization of authority keys should have succeeded");
         println!("{}", keys_json);
@@ -1,7 +1,8 @@
 use sc_cli::SubstrateCli;
 use sc_service::PartialComponents;
 
-use aleph_node::{new_full, new_partial, Cli, Subcommand};
+use aleph_node::{new_full, new_partial, Cli, Executor, Subcommand};
+use aleph_runtime::Block;
 
 fn main() -> sc_cli::Result<()> {
     let cli = Cli::from_args();
@@ -71,6 +72,16 @@ fn main() -> sc_cli::Result<()> {
                 Ok((cmd.run(client, backend), task_manager))
             })
         }
+        Some(Subcommand::Benchmark(cmd)) => {
+            if cfg!(feature = "runtime-benchmarks") {
+                let runner = cli.create_runner(cmd)?;
+                runner.sync_run(|config| cmd.run::<Block, Executor>(config))
+            } else {
+                Err("Benchmarking wasn't enabled when building the node. \
+                     You can enable it with `--features runtime-benchmarks`."
+                    .into())
+            }
+        }
         None => {
             let runner = cli.create_runner(&cli.run)?;
             let aleph_cli_config = cli.aleph;
@@ -7,7 +7,11 @@
 
 use std::sync::Arc;
 
+use aleph_primitives::AlephSessionApi;
 use aleph_runtime::{opaque::Block, AccountId, Balance, Index};
+use finality_aleph::JustificationStream;
+use sc_client_api::{AuxStore, BlockBackend};
+pub use sc_rpc::SubscriptionTaskExecutor;
 pub use sc_rpc_api::DenyUnsafe;
 use sc_transaction_pool_api::TransactionPool;
 use sp_api::ProvideRuntimeApi;
@@ -22,6 +26,10 @@ pub struct FullDeps<C, P> {
     pub pool: Arc<P>,
     /// Whether to deny unsafe calls
     pub deny_unsafe: DenyUnsafe,
+    /// Justifications of the blocks finalized by Aleph.
+    pub justification_stream: JustificationStream<Block>,
+    /// Executor driving the subscriptions.
+    pub subscription_executor: SubscriptionTaskExecutor,
 }
 
 /// Instantiate all full RPC extensions.
@@ -29,12 +37,15 @@ pub fn create_full<C, P>(deps: FullDeps<C, P>) -> jsonrpc_core::IoHandler<sc_rpc
 where
     C: ProvideRuntimeApi<Block>,
     C: HeaderBackend<Block> + HeaderMetadata<Block, Error = BlockChainError> + 'static,
+    C: BlockBackend<Block> + AuxStore,
     C: Send + Sync + 'static,
     C::Api: substrate_frame_rpc_system::AccountNonceApi<Block, AccountId, Index>,
     C::Api: pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance>,
     C::Api: BlockBuilder<Block>,
+    C::Api: AlephSessionApi<Block>,
     P: TransactionPool + 'static,
 {
+    use finality_aleph::rpc::{Aleph, AlephApi};
     use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApi};
     use substrate_frame_rpc_system::{FullSystem, SystemApi};
 
@@ -43,6 +54,8 @@ where
         client,
         pool,
         deny_unsafe,
+        justification_stream,
+        subscription_executor,
     } = deps;
 
     io.extend_with(SystemApi::to_delegate(FullSystem::new(
@@ -52,7 +65,13 @@ where
     )));
 
     io.extend_with(TransactionPaymentApi::to_delegate(TransactionPayment::new(
+        client.clone(),
+    )));
+
+    io.extend_with(AlephApi::to_delegate(Aleph::new(
         client,
+        justification_stream,
+        subscription_executor,
     )));
 
     io
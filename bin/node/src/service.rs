@@ -1,11 +1,11 @@
 //! Service and ServiceFactory implementation. Specialized wrapper over substrate service.
 
 use crate::aleph_cli::AlephCli;
-use aleph_primitives::AlephSessionApi;
 use aleph_runtime::{self, opaque::Block, RuntimeApi, MAX_BLOCK_SIZE};
 use finality_aleph::{
-    run_aleph_consensus, AlephBlockImport, AlephConfig, JustificationNotification, Metrics,
-    MillisecsPerBlock, SessionPeriod,
+    run_aleph_consensus, run_aleph_nonvalidator, AlephBlockImport, AlephConfig,
+    AlephWarpSyncProvider, BlsKeystore, JustificationNotification, JustificationStream, Metrics,
+    SessionSchedule,
 };
 use futures::channel::mpsc;
 use log::warn;
@@ -13,22 +13,35 @@ use sc_client_api::ExecutorProvider;
 use sc_consensus_aura::{ImportQueueParams, SlotProportion, StartAuraParams};
 use sc_executor::native_executor_instance;
 pub use sc_executor::NativeExecutor;
-use sc_service::{error::Error as ServiceError, Configuration, TFullClient, TaskManager};
+use sc_service::{
+    config::KeystoreConfig, error::Error as ServiceError, Configuration, TFullClient, TaskManager,
+};
 use sc_telemetry::{Telemetry, TelemetryWorker};
-use sp_api::ProvideRuntimeApi;
 use sp_consensus::SlotData;
 use sp_consensus_aura::sr25519::AuthorityPair as AuraPair;
 use sp_runtime::{
     generic::BlockId,
-    traits::{Block as BlockT, Header as HeaderT, Zero},
+    traits::{Block as BlockT, Header as HeaderT},
 };
 use std::sync::Arc;
 
+/// Host functions of the runtime, extended with the benchmarking ones only in nodes built for
+/// running benchmarks.
+#[cfg(not(feature = "runtime-benchmarks"))]
+type HostFunctions = aleph_justification::aleph_bls::HostFunctions;
+#[cfg(feature = "runtime-benchmarks")]
+type HostFunctions = (
+    frame_benchmarking::benchmarking::HostFunctions,
+    aleph_justification::aleph_bls::HostFunctions,
+    aleph_justification::aleph_bls_benchmarking::HostFunctions,
+);
+
 // Our native executor instance.
 native_executor_instance!(
     pub Executor,
     aleph_runtime::api::dispatch,
     aleph_runtime::native_version,
+    HostFunctions,
 );
 
 type FullClient = sc_service::TFullClient<Block, RuntimeApi, Executor>;
@@ -139,6 +152,13 @@ pub fn new_partial(
     })
 }
 
+/// Reads the schedule of session periods at the last finalized block.
+fn session_schedule(client: &FullClient) -> Result<SessionSchedule, ServiceError> {
+    let finalized = BlockId::Hash(client.chain_info().finalized_hash);
+    finality_aleph::session_schedule(client, &finalized)
+        .map_err(|e| ServiceError::Other(format!("Cannot read the session schedule: {:?}", e)))
+}
+
 /// Builds a new service for a full client.
 pub fn new_full(
     mut config: Configuration,
@@ -158,7 +178,7 @@ pub fn new_full(
     config
         .network
         .extra_sets
-        .push(finality_aleph::peers_set_config(None));
+        .extend(finality_aleph::peers_set_configs());
 
     let (network, system_rpc_tx, network_starter) =
         sc_service::build_network(sc_service::BuildNetworkParams {
@@ -169,39 +189,36 @@ pub fn new_full(
             import_queue,
             on_demand: None,
             block_announce_validator_builder: None,
-            warp_sync: None,
+            warp_sync: Some(Arc::new(AlephWarpSyncProvider::new(client.clone()))),
         })?;
 
-    let session_period = SessionPeriod(
-        client
-            .runtime_api()
-            .session_period(&BlockId::Number(Zero::zero()))
-            .unwrap(),
-    );
+    let session_schedule = session_schedule(&client)?;
 
-    let millisecs_per_block = MillisecsPerBlock(
-        client
-            .runtime_api()
-            .millisecs_per_block(&BlockId::Number(Zero::zero()))
-            .unwrap(),
-    );
+    let tuning = aleph_config.tuning().map_err(ServiceError::Other)?;
 
-    let unit_creation_delay = aleph_config.unit_creation_delay();
+    let bls_keystore = match &config.keystore {
+        KeystoreConfig::Path { path, .. } => BlsKeystore::open(path.clone()),
+        _ => BlsKeystore::in_memory(),
+    };
 
     let role = config.role.clone();
     let force_authoring = config.force_authoring;
     let backoff_authoring_blocks: Option<()> = None;
     let prometheus_registry = config.prometheus_registry().cloned();
 
+    let (justification_sender, justification_stream) = JustificationStream::channel();
+
     let rpc_extensions_builder = {
         let client = client.clone();
         let pool = transaction_pool.clone();
 
-        Box::new(move |deny_unsafe, _| {
+        Box::new(move |deny_unsafe, subscription_executor| {
             let deps = crate::rpc::FullDeps {
                 client: client.clone(),
                 pool: pool.clone(),
                 deny_unsafe,
+                justification_stream: justification_stream.clone(),
+                subscription_executor,
             };
 
             Ok(crate::rpc::create_full(deps))
@@ -272,22 +289,31 @@ pub fn new_full(
         task_manager
             .spawn_essential_handle()
             .spawn_blocking("aura", aura);
+    }
 
-        let aleph_config = AlephConfig {
-            network,
-            client,
-            select_chain,
-            session_period,
-            millisecs_per_block,
-            spawn_handle: task_manager.spawn_handle(),
-            keystore: keystore_container.keystore(),
-            justification_rx,
-            metrics,
-            unit_creation_delay,
-        };
+    // Every full node has to finalize the blocks it imports, so the ones that are not authorities
+    // run the justification handler alone.
+    let aleph_config = AlephConfig {
+        network,
+        client,
+        select_chain,
+        session_schedule,
+        spawn_handle: task_manager.spawn_handle(),
+        keystore: keystore_container.keystore(),
+        bls_keystore,
+        justification_rx,
+        justification_sender,
+        metrics,
+        tuning,
+    };
+    if role.is_authority() {
         task_manager
             .spawn_essential_handle()
             .spawn_blocking("aleph", run_aleph_consensus(aleph_config));
+    } else {
+        task_manager
+            .spawn_essential_handle()
+            .spawn_blocking("aleph", run_aleph_nonvalidator(aleph_config));
     }
 
     network_starter.start_network();
@@ -44,7 +44,10 @@ pub use frame_support::{
     StorageValue,
 };
 use frame_system::EnsureSignedBy;
-use primitives::{ApiError as AlephApiError, AuthorityId as AlephId};
+use primitives::{
+    AlephEquivocationProof, ApiError as AlephApiError, AuthorityId as AlephId, BlsKeyRegistration,
+    ConsensusParams, FinalityVersion, SessionSchedule,
+};
 
 pub use pallet_balances::Call as BalancesCall;
 pub use pallet_timestamp::Call as TimestampCall;
@@ -109,7 +112,7 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
     spec_name: create_runtime_str!("aleph-node"),
     impl_name: create_runtime_str!("aleph-node"),
     authoring_version: 1,
-    spec_version: 6,
+    spec_version: 13,
     impl_version: 1,
     apis: RUNTIME_API_VERSIONS,
     transaction_version: 3,
@@ -215,6 +218,8 @@ impl pallet_aura::Config for Runtime {
     type DisabledValidators = ();
 }
 
+/// Half of the block time, which makes the Aura slot duration equal to it. Nodes read the slot
+/// duration only at startup, so `pallet_aleph` does not let the block time change.
 pub struct MinimumPeriod;
 impl MinimumPeriod {
     pub fn get() -> u64 {
@@ -322,9 +327,39 @@ impl pallet_sudo::Config for Runtime {
     type Call = Call;
 }
 
+parameter_types! {
+    pub const MinCandidateBond: Balance = 1_000 * TOKEN;
+    pub const MinNominatorBond: Balance = 10 * TOKEN;
+    pub const MinCommitteeSize: u32 = 4;
+    pub const MaxCommitteeSize: u32 = 100;
+    pub const MaxCandidates: u32 = 1_000;
+    // A week of sessions with the default session period.
+    pub const SessionHistoryDepth: u32 = 672;
+}
+
 impl pallet_aleph::Config for Runtime {
     type AuthorityId = AlephId;
     type Event = Event;
+    type Currency = Balances;
+    type MinCandidateBond = MinCandidateBond;
+    type MinNominatorBond = MinNominatorBond;
+    type MinCommitteeSize = MinCommitteeSize;
+    type MaxCommitteeSize = MaxCommitteeSize;
+    type MaxCandidates = MaxCandidates;
+    type SessionHistoryDepth = SessionHistoryDepth;
+    type WeightInfo = pallet_aleph::weights::SubstrateWeight<Runtime>;
+}
+
+parameter_types! {
+    pub const RemoteHeadersToKeep: u32 = 1024;
+}
+
+impl pallet_aleph_light_client::Config for Runtime {
+    type Event = Event;
+    // The bridged chain runs the same runtime.
+    type RemoteHeader = Header;
+    type HeadersToKeep = RemoteHeadersToKeep;
+    type WeightInfo = pallet_aleph_light_client::weights::SubstrateWeight<Runtime>;
 }
 
 impl_opaque_keys! {
@@ -334,10 +369,6 @@ impl_opaque_keys! {
     }
 }
 
-parameter_types! {
-    pub const Offset: u32 = 0;
-}
-
 parameter_types! {
     pub const DisabledValidatorsThreshold: Perbill = Perbill::from_percent(30);
 }
@@ -356,26 +387,12 @@ impl<I: From<u64>> ::frame_support::traits::Get<I> for MillisecsPerBlock {
     }
 }
 
-pub struct SessionPeriod;
-
-impl SessionPeriod {
-    pub fn get() -> u32 {
-        Aleph::session_period()
-    }
-}
-
-impl<I: From<u32>> ::frame_support::traits::Get<I> for SessionPeriod {
-    fn get() -> I {
-        I::from(Self::get())
-    }
-}
-
 impl pallet_session::Config for Runtime {
     type Event = Event;
     type ValidatorId = <Self as frame_system::Config>::AccountId;
     type ValidatorIdOf = ConvertInto;
-    type ShouldEndSession = pallet_session::PeriodicSessions<SessionPeriod, Offset>;
-    type NextSessionRotation = pallet_session::PeriodicSessions<SessionPeriod, Offset>;
+    type ShouldEndSession = Aleph;
+    type NextSessionRotation = Aleph;
     type SessionManager = pallet_aleph::AlephSessionManager<Self>;
     type SessionHandler = <SessionKeys as OpaqueKeys>::KeyTypeIdProviders;
     type Keys = SessionKeys;
@@ -405,6 +422,7 @@ impl pallet_vesting::Config for Runtime {
 
 pub const MILLICENTS: Balance = 100_000_000;
 pub const CENTS: Balance = 1_000 * MILLICENTS; // 10^12 is one token, which for now is worth $0.1
+pub const TOKEN: Balance = 100 * CENTS;
 
 // at a fixed cost $0.01 per byte, the constants are selected so that
 // the base cost of starting a multisig action is $5
@@ -492,12 +510,13 @@ construct_runtime!(
         Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
         TransactionPayment: pallet_transaction_payment::{Pallet, Storage},
         Sudo: pallet_sudo::{Pallet, Call, Config<T>, Storage, Event<T>},
-        Aleph: pallet_aleph::{Pallet, Call, Config<T>, Storage, Event<T>},
+        Aleph: pallet_aleph::{Pallet, Call, Config<T>, Storage, Event<T>, ValidateUnsigned},
         Session: pallet_session::{Pallet, Call, Storage, Event, Config<T>},
         Scheduler: pallet_scheduler::{Pallet, Call, Storage, Event<T>},
         Treasury: pallet_treasury::{Pallet, Call, Storage, Config, Event<T>},
         Vesting: pallet_vesting::{Pallet, Call, Storage, Event<T>, Config<T>},
         Multisig: pallet_multisig::{Pallet, Call, Storage, Event<T>},
+        AlephLightClient: pallet_aleph_light_client::{Pallet, Call, Storage, Event<T>},
     }
 );
 
@@ -643,6 +662,22 @@ impl_runtime_apis! {
             Aleph::authorities()
         }
 
+        fn authorities_for_session(session_id: u32) -> Option<Vec<AlephId>> {
+            Aleph::session_authorities(session_id)
+        }
+
+        fn bls_keys_for_session(session_id: u32) -> Option<Vec<BlsKeyRegistration>> {
+            Aleph::session_bls_keys(session_id)
+        }
+
+        fn consensus_params(session_id: u32) -> ConsensusParams {
+            Aleph::consensus_params(session_id)
+        }
+
+        fn finality_version(session_id: u32) -> FinalityVersion {
+            Aleph::finality_version(session_id)
+        }
+
         fn session_period() -> u32 {
             Aleph::session_period()
         }
@@ -651,5 +686,49 @@ impl_runtime_apis! {
             Aleph::millisecs_per_block()
         }
 
+        fn session_schedule() -> SessionSchedule {
+            Aleph::session_schedule()
+        }
+
+        fn disabled_authorities() -> Vec<u32> {
+            Aleph::disabled_authorities()
+        }
+
+        fn submit_report_equivocation_unsigned_extrinsic(
+            equivocation_proof: AlephEquivocationProof<<Block as BlockT>::Header>,
+        ) -> Option<()> {
+            Aleph::submit_unsigned_equivocation_report(equivocation_proof)
+        }
+
+    }
+
+    #[cfg(feature = "runtime-benchmarks")]
+    impl frame_benchmarking::Benchmark<Block> for Runtime {
+        fn dispatch_benchmark(
+            config: frame_benchmarking::BenchmarkConfig
+        ) -> Result<Vec<frame_benchmarking::BenchmarkBatch>, sp_runtime::RuntimeString> {
+            use frame_benchmarking::{add_benchmark, BenchmarkBatch, Benchmarking, TrackedStorageKey};
+
+            let whitelist: Vec<TrackedStorageKey> = vec![
+                // Block Number
+                hex_literal::hex!("26aa394eea5630e07c48ae0c9558cef702a5c1b19ab7a04f536c519aca4983ac").to_vec().into(),
+                // Total Issuance
+                hex_literal::hex!("c2261276cc9d1f8598ea4b6a74b15c2f57c875e4cff74148e4628f264b974c80").to_vec().into(),
+                // Execution Phase
+                hex_literal::hex!("26aa394eea5630e07c48ae0c9558cef7ff553b5a9862a516939d82b3d3d8661a").to_vec().into(),
+                // Event Count
+                hex_literal::hex!("26aa394eea5630e07c48ae0c9558cef70a98fdbe9ce6c55837576c60c7af3850").to_vec().into(),
+                // System Events
+                hex_literal::hex!("26aa394eea5630e07c48ae0c9558cef780d41e5e16056765bc8461851072c9d7").to_vec().into(),
+            ];
+
+            let mut batches = Vec::<BenchmarkBatch>::new();
+            let params = (&config, &whitelist);
+
+            add_benchmark!(params, batches, pallet_aleph, Aleph);
+
+            if batches.is_empty() { return Err("Benchmark not found for this pallet.".into()) }
+            Ok(batches)
+        }
     }
 }
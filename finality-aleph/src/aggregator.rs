@@ -1,12 +1,9 @@
 use crate::{
-    crypto::Signature,
-    metrics::Checkpoint,
-    network::{Recipient, RmcNetwork},
-    Metrics,
+    equivocation::ObservedSignature, metrics::Checkpoint, new_network::DataNetwork, Metrics,
 };
 use aleph_bft::{
     rmc::{DoublingDelayScheduler, Message, ReliableMulticast},
-    MultiKeychain, Signable, SignatureSet,
+    Index, KeyBox, MultiKeychain, Recipient, Signable,
 };
 use codec::{Codec, Decode, Encode};
 use futures::{channel::mpsc, StreamExt};
@@ -31,34 +28,45 @@ impl<H: AsRef<[u8]> + Hash + Clone + Codec> Signable for SignableHash<H> {
     }
 }
 
-type RmcMessage<B> = Message<SignableHash<<B as Block>::Hash>, Signature, SignatureSet<Signature>>;
+/// The messages of an RMC signing block hashes with the keys of the keychain.
+pub(crate) type RmcMessage<B, MK> = Message<
+    SignableHash<<B as Block>::Hash>,
+    <MK as KeyBox>::Signature,
+    <MK as MultiKeychain>::PartialMultisignature,
+>;
+
 /// A wrapper around an RMC returning the signed hashes in the order of the [`ReliableMulticast::start_rmc`] calls.
-pub(crate) struct BlockSignatureAggregator<'a, B: Block, MK: MultiKeychain> {
-    messages_for_rmc: mpsc::UnboundedSender<RmcMessage<B>>,
-    messages_from_rmc: mpsc::UnboundedReceiver<RmcMessage<B>>,
+pub(crate) struct BlockSignatureAggregator<
+    'a,
+    B: Block,
+    N: DataNetwork<RmcMessage<B, MK>>,
+    MK: MultiKeychain,
+> {
+    messages_for_rmc: mpsc::UnboundedSender<RmcMessage<B, MK>>,
+    messages_from_rmc: mpsc::UnboundedReceiver<RmcMessage<B, MK>>,
     signatures: HashMap<B::Hash, MK::PartialMultisignature>,
     hash_queue: VecDeque<B::Hash>,
-    network: RmcNetwork<B>,
+    network: N,
     rmc: ReliableMulticast<'a, SignableHash<B::Hash>, MK>,
     last_hash_placed: bool,
     started_hashes: HashSet<B::Hash>,
     metrics: Option<Metrics<<B::Header as Header>::Hash>>,
+    signatures_tx: Option<mpsc::UnboundedSender<ObservedSignature<B, MK::Signature>>>,
 }
 
-impl<
-        'a,
-        B: Block,
-        MK: MultiKeychain<Signature = Signature, PartialMultisignature = SignatureSet<Signature>>,
-    > BlockSignatureAggregator<'a, B, MK>
+impl<'a, B: Block, N: DataNetwork<RmcMessage<B, MK>>, MK: MultiKeychain>
+    BlockSignatureAggregator<'a, B, N, MK>
 {
     pub(crate) fn new(
-        network: RmcNetwork<B>,
+        network: N,
         keychain: &'a MK,
         metrics: Option<Metrics<<B::Header as Header>::Hash>>,
+        signatures_tx: Option<mpsc::UnboundedSender<ObservedSignature<B, MK::Signature>>>,
+        rmc_delay: Duration,
     ) -> Self {
         let (messages_for_rmc, messages_from_network) = mpsc::unbounded();
         let (messages_for_network, messages_from_rmc) = mpsc::unbounded();
-        let scheduler = DoublingDelayScheduler::new(Duration::from_millis(500));
+        let scheduler = DoublingDelayScheduler::new(rmc_delay);
         let rmc = ReliableMulticast::new(
             messages_from_network,
             messages_for_network,
@@ -76,6 +84,25 @@ impl<
             last_hash_placed: false,
             started_hashes: HashSet::new(),
             metrics,
+            signatures_tx,
+        }
+    }
+
+    /// Passes single signatures received from the network on, so that they can be checked for
+    /// equivocations.
+    fn observe(&self, message: &RmcMessage<B, MK>) {
+        if let (Some(signatures_tx), Message::SignedHash(unchecked)) =
+            (&self.signatures_tx, message)
+        {
+            let indexed = unchecked.as_signable();
+            let observed = ObservedSignature {
+                hash: indexed.as_signable().hash,
+                index: indexed.index(),
+                signature: unchecked.signature(),
+            };
+            if signatures_tx.unbounded_send(observed).is_err() {
+                trace!(target: "afa", "The equivocation detector is no longer running.");
+            }
         }
     }
 
@@ -129,7 +156,9 @@ impl<
                     message_from_rmc = self.messages_from_rmc.next() => {
                         trace!(target: "afa", "Our rmc message {:?}.", message_from_rmc);
                         if let Some(message_from_rmc) = message_from_rmc {
-                            self.network.send(message_from_rmc, Recipient::All).expect("sending message from rmc failed")
+                            if self.network.send(message_from_rmc, Recipient::Everyone).is_err() {
+                                warn!(target: "afa", "failed sending a message from rmc to the network");
+                            }
                         } else {
                             warn!(target: "afa", "the channel of messages from rmc closed");
                         }
@@ -137,6 +166,7 @@ impl<
                     message_from_network = self.network.next() => {
                         if let Some(message_from_network) = message_from_network {
                             trace!(target: "afa", "Received message for rmc: {:?}", message_from_network);
+                            self.observe(&message_from_network);
                             self.messages_for_rmc.unbounded_send(message_from_network).expect("sending message to rmc failed");
                         } else {
                             warn!(target: "afa", "the network channel closed");
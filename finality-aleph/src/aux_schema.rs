@@ -0,0 +1,162 @@
+//! Schema of the gadget state kept in the aux storage of the client, so that it survives restarts.
+use crate::{AuthorityId, SessionId};
+use codec::{Decode, Encode};
+use sc_client_api::AuxStore;
+use sp_blockchain::{Error as ClientError, Result as ClientResult};
+
+const SESSION_AUTHORITIES_KEY: &[u8] = b"aleph_session_authorities";
+const PROGRESS_KEY: &[u8] = b"aleph_progress";
+
+/// How far the gadget got before it was stopped.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub(crate) struct Progress<H> {
+    /// The last session the gadget started.
+    pub(crate) last_started_session: SessionId,
+    /// The last block of that session for which the signature aggregation was started.
+    pub(crate) last_aggregated_hash: Option<H>,
+}
+
+fn session_authorities_key(session_id: SessionId) -> Vec<u8> {
+    (SESSION_AUTHORITIES_KEY, session_id).encode()
+}
+
+fn load_decode<S: AuxStore, T: Decode>(store: &S, key: &[u8]) -> ClientResult<Option<T>> {
+    match store.get_aux(key)? {
+        None => Ok(None),
+        Some(encoded) => T::decode(&mut &encoded[..])
+            .map(Some)
+            .map_err(|e| ClientError::Backend(format!("Aleph DB is corrupted: {}", e))),
+    }
+}
+
+pub(crate) fn load_session_authorities<S: AuxStore>(
+    store: &S,
+    session_id: SessionId,
+) -> ClientResult<Option<Vec<AuthorityId>>> {
+    load_decode(store, &session_authorities_key(session_id))
+}
+
+pub(crate) fn write_session_authorities<S: AuxStore>(
+    store: &S,
+    session_id: SessionId,
+    authorities: &[AuthorityId],
+) -> ClientResult<()> {
+    let key = session_authorities_key(session_id);
+    store.insert_aux(&[(&key[..], &authorities.encode()[..])], &[])
+}
+
+/// Removes the authorities of the sessions in `from..below`.
+pub(crate) fn prune_session_authorities<S: AuxStore>(
+    store: &S,
+    from: SessionId,
+    below: SessionId,
+) -> ClientResult<()> {
+    let keys: Vec<_> = (from.0..below.0)
+        .map(|session| session_authorities_key(SessionId(session)))
+        .collect();
+    let keys: Vec<&[u8]> = keys.iter().map(|key| &key[..]).collect();
+    store.insert_aux(&[], &keys)
+}
+
+pub(crate) fn load_progress<S: AuxStore, H: Decode>(
+    store: &S,
+) -> ClientResult<Option<Progress<H>>> {
+    load_decode(store, PROGRESS_KEY)
+}
+
+pub(crate) fn write_progress<S: AuxStore, H: Encode>(
+    store: &S,
+    progress: &Progress<H>,
+) -> ClientResult<()> {
+    store.insert_aux(&[(PROGRESS_KEY, &progress.encode()[..])], &[])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aleph_primitives::AuthorityPair;
+    use parking_lot::Mutex;
+    use sp_core::{Pair, H256};
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct MemoryAuxStore(Mutex<HashMap<Vec<u8>, Vec<u8>>>);
+
+    impl AuxStore for MemoryAuxStore {
+        fn insert_aux<
+            'a,
+            'b: 'a,
+            'c: 'a,
+            I: IntoIterator<Item = &'a (&'c [u8], &'c [u8])>,
+            D: IntoIterator<Item = &'a &'b [u8]>,
+        >(
+            &self,
+            insert: I,
+            delete: D,
+        ) -> ClientResult<()> {
+            let mut storage = self.0.lock();
+            for (key, value) in insert {
+                storage.insert(key.to_vec(), value.to_vec());
+            }
+            for key in delete {
+                storage.remove(*key);
+            }
+            Ok(())
+        }
+
+        fn get_aux(&self, key: &[u8]) -> ClientResult<Option<Vec<u8>>> {
+            Ok(self.0.lock().get(key).cloned())
+        }
+    }
+
+    fn authorities(seeds: &[u8]) -> Vec<AuthorityId> {
+        seeds
+            .iter()
+            .map(|seed| AuthorityPair::from_seed(&[*seed; 32]).public())
+            .collect()
+    }
+
+    #[test]
+    fn stores_and_prunes_session_authorities() {
+        let store = MemoryAuxStore::default();
+        for session in 0..5 {
+            write_session_authorities(&store, SessionId(session), &authorities(&[session as u8]))
+                .unwrap();
+        }
+
+        prune_session_authorities(&store, SessionId(0), SessionId(3)).unwrap();
+
+        assert_eq!(
+            load_session_authorities(&store, SessionId(2)).unwrap(),
+            None
+        );
+        assert_eq!(
+            load_session_authorities(&store, SessionId(3)).unwrap(),
+            Some(authorities(&[3]))
+        );
+    }
+
+    #[test]
+    fn stores_progress() {
+        let store = MemoryAuxStore::default();
+        assert_eq!(load_progress::<_, H256>(&store).unwrap(), None);
+
+        let progress = Progress {
+            last_started_session: SessionId(7),
+            last_aggregated_hash: Some(H256::repeat_byte(1)),
+        };
+        write_progress(&store, &progress).unwrap();
+
+        assert_eq!(load_progress(&store).unwrap(), Some(progress));
+    }
+
+    #[test]
+    fn fails_on_corrupted_data() {
+        let store = MemoryAuxStore::default();
+        store
+            .insert_aux(&[(PROGRESS_KEY, &[1u8][..])], &[])
+            .unwrap();
+
+        assert!(load_progress::<_, H256>(&store).is_err());
+    }
+}
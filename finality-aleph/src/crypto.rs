@@ -1,17 +1,37 @@
 use aleph_bft::{
     KeyBox as AlephKeyBox, MultiKeychain, NodeCount, NodeIndex, PartialMultisignature, SignatureSet,
 };
-use aleph_primitives::{AuthorityId, AuthoritySignature, KEY_TYPE};
+use aleph_justification::{
+    bls::{self, POSSESSION_DST, SIGNATURE_DST},
+    AggregatedSignature, AlephJustification, AuthorityVerifier as JustificationVerifier, SignerSet,
+};
+pub use aleph_justification::{SigningDomain, SigningPurpose};
+use aleph_primitives::{
+    AuthorityId, AuthoritySignature, BlsKeyRegistration, BlsPublic, BlsSignature, FinalityVersion,
+    SessionId, BLS_KEY_TYPE, BLS_PUBLIC_SIZE, KEY_TYPE,
+};
+use blst::min_pk::SecretKey;
 use codec::{Decode, Encode};
+use log::debug;
+use parking_lot::Mutex;
+use rand::RngCore;
 use sp_core::crypto::KeyTypeId;
 use sp_keystore::{CryptoStore, Error as KeystoreError};
-use sp_runtime::RuntimeAppPublic;
-use std::{convert::TryInto, sync::Arc};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    convert::TryInto,
+    fs::{self, File},
+    io,
+    path::PathBuf,
+    sync::Arc,
+};
 
 #[derive(Debug)]
 pub enum Error {
     KeyMissing,
     Keystore(KeystoreError),
+    Io(io::Error),
     Conversion,
 }
 
@@ -24,6 +44,24 @@ impl From<AuthoritySignature> for Signature {
     }
 }
 
+impl From<Signature> for AuthoritySignature {
+    fn from(signature: Signature) -> AuthoritySignature {
+        signature.0
+    }
+}
+
+/// The message tagged with the domain, or the bare message outside of any domain.
+fn tagged<'a>(domain: &Option<SigningDomain>, msg: &'a [u8]) -> Cow<'a, [u8]> {
+    match domain {
+        Some(domain) => Cow::Owned(domain.tag(msg)),
+        None => Cow::Borrowed(msg),
+    }
+}
+
+/// The message signed to check whether the keystore can sign with the authority key. The
+/// signature is never sent anywhere.
+const KEY_CHECK_MESSAGE: &[u8] = b"aleph-key-check";
+
 /// Ties an authority identification and a cryptography keystore together for use in
 /// signing that requires an authority.
 #[derive(Clone)]
@@ -31,6 +69,7 @@ pub struct AuthorityPen {
     key_type_id: KeyTypeId,
     authority_id: AuthorityId,
     keystore: Arc<dyn CryptoStore>,
+    domain: Option<SigningDomain>,
 }
 
 impl AuthorityPen {
@@ -44,7 +83,7 @@ impl AuthorityPen {
     ) -> Result<Self, Error> {
         // Check whether this signing setup works
         let _: AuthoritySignature = keystore
-            .sign_with(KEY_TYPE, &authority_id.clone().into(), b"test")
+            .sign_with(KEY_TYPE, &authority_id.clone().into(), KEY_CHECK_MESSAGE)
             .await
             .map_err(Error::Keystore)?
             .ok_or(Error::KeyMissing)?
@@ -54,14 +93,25 @@ impl AuthorityPen {
             key_type_id: KEY_TYPE,
             authority_id,
             keystore,
+            domain: None,
         })
     }
 
+    /// The same pen, signing messages tagged with the given domain, or bare messages if there is
+    /// none.
+    pub fn in_domain(&self, domain: Option<SigningDomain>) -> Self {
+        AuthorityPen {
+            domain,
+            ..self.clone()
+        }
+    }
+
     /// Cryptographically signs the message.
     pub async fn sign(&self, msg: &[u8]) -> Signature {
+        let msg = tagged(&self.domain, msg);
         Signature(
             self.keystore
-                .sign_with(self.key_type_id, &self.authority_id.clone().into(), msg)
+                .sign_with(self.key_type_id, &self.authority_id.clone().into(), &msg)
                 .await
                 .expect("the keystore works")
                 .expect("we have the required key")
@@ -71,44 +121,294 @@ impl AuthorityPen {
     }
 }
 
+/// Keeps the BLS secret keys of the node.
+///
+/// The keystore of the node cannot sign with BLS keys, but it can store them: `author_insertKey`
+/// with the `BLS_KEY_TYPE`, the hex-encoded secret key and the public key writes the secret key to
+/// a file in the keystore directory, named after the key type and the public key. The keys are
+/// read from there, and new ones are written in the same way.
+#[derive(Clone)]
+pub struct BlsKeystore {
+    path: Option<PathBuf>,
+    keys: Arc<Mutex<HashMap<BlsPublic, SecretKey>>>,
+}
+
+impl BlsKeystore {
+    /// A keystore keeping the keys in the given keystore directory.
+    pub fn open(path: PathBuf) -> Self {
+        BlsKeystore {
+            path: Some(path),
+            keys: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// A keystore keeping the keys in memory only.
+    pub fn in_memory() -> Self {
+        BlsKeystore {
+            path: None,
+            keys: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn key_file_prefix() -> String {
+        hex::encode(BLS_KEY_TYPE.0)
+    }
+
+    fn key_file(&self, public: &BlsPublic) -> Option<PathBuf> {
+        self.path
+            .as_ref()
+            .map(|path| path.join(Self::key_file_prefix() + &hex::encode(public.0)))
+    }
+
+    /// Generates a new random key and stores it.
+    pub fn generate_new(&self) -> Result<BlsPublic, Error> {
+        let mut seed = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut seed);
+        let secret = SecretKey::key_gen(&seed, &[]).map_err(|_| Error::Conversion)?;
+        let public = BlsPublic(secret.sk_to_pk().compress());
+        if let (Some(path), Some(file)) = (&self.path, self.key_file(&public)) {
+            fs::create_dir_all(path).map_err(Error::Io)?;
+            let encoded = format!("0x{}", hex::encode(secret.to_bytes()));
+            let writer = File::create(&file).map_err(Error::Io)?;
+            serde_json::to_writer(&writer, &encoded).map_err(|e| Error::Io(e.into()))?;
+            #[cfg(target_family = "unix")]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                writer
+                    .set_permissions(fs::Permissions::from_mode(0o600))
+                    .map_err(Error::Io)?;
+            }
+        }
+        self.keys.lock().insert(public, secret);
+        Ok(public)
+    }
+
+    /// The public keys of all the stored keys.
+    pub fn keys(&self) -> Result<Vec<BlsPublic>, Error> {
+        let mut keys: Vec<_> = self.keys.lock().keys().cloned().collect();
+        if let Some(path) = &self.path {
+            if !path.exists() {
+                return Ok(keys);
+            }
+            let prefix = Self::key_file_prefix();
+            for entry in fs::read_dir(path).map_err(Error::Io)? {
+                let name = entry.map_err(Error::Io)?.file_name();
+                let public = match name.to_str().and_then(|name| name.strip_prefix(&prefix)) {
+                    Some(public) => public,
+                    None => continue,
+                };
+                let mut key = [0u8; BLS_PUBLIC_SIZE];
+                if hex::decode_to_slice(public, &mut key).is_ok() && !keys.contains(&BlsPublic(key))
+                {
+                    keys.push(BlsPublic(key));
+                }
+            }
+        }
+        Ok(keys)
+    }
+
+    /// The secret key matching the public key, if it is stored.
+    fn secret(&self, public: &BlsPublic) -> Result<SecretKey, Error> {
+        if let Some(secret) = self.keys.lock().get(public) {
+            return Ok(secret.clone());
+        }
+        let file = match self.key_file(public) {
+            Some(file) if file.exists() => file,
+            _ => return Err(Error::KeyMissing),
+        };
+        let encoded: String = serde_json::from_reader(File::open(&file).map_err(Error::Io)?)
+            .map_err(|e| Error::Io(e.into()))?;
+        let bytes = hex::decode(encoded.trim_start_matches("0x")).map_err(|_| Error::Conversion)?;
+        let secret = SecretKey::from_bytes(&bytes).map_err(|_| Error::Conversion)?;
+        if secret.sk_to_pk().compress() != public.0 {
+            return Err(Error::Conversion);
+        }
+        self.keys.lock().insert(*public, secret.clone());
+        Ok(secret)
+    }
+}
+
+/// Signs messages with the BLS key of an authority.
+#[derive(Clone)]
+pub struct BlsPen {
+    secret: SecretKey,
+    domain: Option<SigningDomain>,
+}
+
+impl BlsPen {
+    /// Creates a pen signing with the BLS key with the given public key, if it is stored in the
+    /// keystore.
+    pub fn new(public: &BlsPublic, keystore: &BlsKeystore) -> Result<Self, Error> {
+        Ok(BlsPen {
+            secret: keystore.secret(public)?,
+            domain: None,
+        })
+    }
+
+    /// The same pen, signing messages tagged with the given domain, or bare messages if there is
+    /// none.
+    pub fn in_domain(&self, domain: Option<SigningDomain>) -> Self {
+        BlsPen {
+            domain,
+            ..self.clone()
+        }
+    }
+
+    pub fn public(&self) -> BlsPublic {
+        BlsPublic(self.secret.sk_to_pk().compress())
+    }
+
+    /// Cryptographically signs the message.
+    pub fn sign(&self, msg: &[u8]) -> BlsSignature {
+        let msg = tagged(&self.domain, msg);
+        BlsSignature(self.secret.sign(&msg, SIGNATURE_DST, &[]).compress())
+    }
+
+    /// The public key together with the proof that we know the secret key.
+    pub fn registration(&self) -> BlsKeyRegistration {
+        let key = self.public();
+        BlsKeyRegistration {
+            key,
+            proof_of_possession: BlsSignature(
+                self.secret.sign(&key.0, POSSESSION_DST, &[]).compress(),
+            ),
+        }
+    }
+}
+
+/// Returns the registration of the BLS key of the authority together with the authority
+/// signature required by `pallet_aleph::register_bls_key`. The nonce has to be greater than the
+/// one of the previous registration of the authority.
+pub async fn bls_key_registration(
+    authority_id: AuthorityId,
+    bls_key: &BlsPublic,
+    nonce: u32,
+    keystore: Arc<dyn CryptoStore>,
+    bls_keystore: &BlsKeystore,
+) -> Result<(BlsKeyRegistration, AuthoritySignature), Error> {
+    let registration = BlsPen::new(bls_key, bls_keystore)?.registration();
+    let payload = registration.payload(&authority_id, nonce);
+    let signature = AuthorityPen::new(authority_id, keystore)
+        .await?
+        .sign(&payload)
+        .await;
+    Ok((registration, signature.into()))
+}
+
+/// Returns the BLS keys of a session, provided all the proofs of possession are correct.
+/// Aggregated signatures cannot be trusted otherwise, as a single rogue key can forge them.
+pub fn checked_bls_keys(registrations: Vec<BlsKeyRegistration>) -> Option<Vec<BlsPublic>> {
+    registrations
+        .into_iter()
+        .map(|registration| bls::verify_possession(&registration).then(|| registration.key))
+        .collect()
+}
+
 /// Holds the public authority keys for a session allowing for verification of messages from that
 /// session.
 #[derive(Clone)]
 pub struct AuthorityVerifier {
-    authorities: Vec<AuthorityId>,
+    verifier: JustificationVerifier,
+    domain: Option<SigningDomain>,
 }
 
 impl AuthorityVerifier {
     /// Constructs a new authority verifier from a set of public keys.
     pub fn new(authorities: Vec<AuthorityId>) -> Self {
-        AuthorityVerifier { authorities }
+        AuthorityVerifier {
+            verifier: JustificationVerifier::new(authorities),
+            domain: None,
+        }
+    }
+
+    /// Makes the verifier accept BLS signatures made with the given keys, which have to be
+    /// checked with `checked_bls_keys` first.
+    pub fn with_bls_keys(self, bls_keys: Vec<BlsPublic>) -> Self {
+        AuthorityVerifier {
+            verifier: self.verifier.with_bls_keys(bls_keys),
+            ..self
+        }
+    }
+
+    /// Makes the verifier accept the justifications of the given session of the chain with the
+    /// given genesis hash, in the format of the finality version run in the session.
+    pub fn for_session(
+        self,
+        genesis_hash: Vec<u8>,
+        session_id: SessionId,
+        version: FinalityVersion,
+    ) -> Self {
+        AuthorityVerifier {
+            verifier: self.verifier.for_session(genesis_hash, session_id, version),
+            ..self
+        }
+    }
+
+    /// The same verifier, expecting signatures of messages tagged with the given domain, or of
+    /// bare messages if there is none. Justifications are verified the same way in any domain.
+    pub fn in_domain(&self, domain: Option<SigningDomain>) -> Self {
+        AuthorityVerifier {
+            domain,
+            ..self.clone()
+        }
+    }
+
+    /// Whether the verifier knows the BLS keys of all the authorities.
+    pub fn has_bls_keys(&self) -> bool {
+        self.verifier.bls_key(0).is_some()
     }
 
     /// Verifies whether the message is correctly signed with the signature assumed to be made by a
     /// node of the given index.
     pub fn verify(&self, msg: &[u8], sgn: &Signature, index: NodeIndex) -> bool {
-        match self.authorities.get(index.0) {
-            Some(authority) => authority.verify(&msg.to_vec(), &sgn.0),
-            None => false,
-        }
+        self.verifier
+            .verify(&tagged(&self.domain, msg), &sgn.0, index.0)
     }
 
     pub fn node_count(&self) -> NodeCount {
-        self.authorities.len().into()
-    }
-
-    fn threshold(&self) -> usize {
-        2 * self.node_count().0 / 3 + 1
+        self.verifier.node_count().into()
     }
 
     /// Verifies whether the given signature set is a correct and complete multisignature of the
     /// message. Completeness requires more than 2/3 of all authorities.
     pub fn is_complete(&self, msg: &[u8], partial: &SignatureSet<Signature>) -> bool {
         let signature_count = partial.iter().count();
-        if signature_count < self.threshold() {
+        if signature_count < self.verifier.threshold() {
             return false;
         }
-        partial.iter().all(|(i, sgn)| self.verify(msg, sgn, i))
+        let msg = tagged(&self.domain, msg);
+        match self
+            .verifier
+            .verify_all(&msg, partial.iter().map(|(i, sgn)| (i.0, &sgn.0)))
+        {
+            Ok(()) => true,
+            Err(invalid) => {
+                debug!(target: "afa", "Incorrect signatures of nodes {:?} in a multisignature", invalid);
+                false
+            }
+        }
+    }
+
+    /// Verifies whether the message is correctly signed with the BLS signature assumed to be made
+    /// by a node of the given index.
+    pub fn verify_bls(&self, msg: &[u8], sgn: &BlsSignature, index: NodeIndex) -> bool {
+        match self.verifier.bls_key(index.0) {
+            Some(key) => bls::verify(&tagged(&self.domain, msg), sgn, key),
+            None => false,
+        }
+    }
+
+    /// Verifies whether the aggregated signature is a correct and complete multisignature of the
+    /// message.
+    pub fn is_complete_aggregated(&self, msg: &[u8], partial: &AggregatedSignature) -> bool {
+        self.verifier
+            .is_complete_aggregated(&tagged(&self.domain, msg), partial)
+    }
+
+    /// Verifies whether the justification is complete for the block with the given encoded hash.
+    /// Only justifications of the session of the verifier are accepted.
+    pub fn verify_justification(&self, hash: &[u8], justification: &AlephJustification) -> bool {
+        self.verifier.verify_justification(hash, justification)
     }
 }
 
@@ -135,6 +435,16 @@ impl KeyBox {
             authority_verifier,
         }
     }
+
+    /// The same keybox, signing and verifying messages tagged with the given domain, or bare
+    /// messages if there is none.
+    pub fn in_domain(&self, domain: Option<SigningDomain>) -> Self {
+        KeyBox {
+            id: self.id,
+            authority_pen: self.authority_pen.in_domain(domain.clone()),
+            authority_verifier: self.authority_verifier.in_domain(domain),
+        }
+    }
 }
 
 impl aleph_bft::Index for KeyBox {
@@ -178,22 +488,116 @@ impl MultiKeychain for KeyBox {
     }
 }
 
-/// Old format of signatures, needed for backwards compatibility.
+/// A BLS signature aggregated from the signatures of some nodes.
 #[derive(PartialEq, Eq, Clone, Debug, Decode, Encode)]
-pub(crate) struct SignatureV1 {
-    pub(crate) _id: NodeIndex,
-    pub(crate) sgn: AuthoritySignature,
+pub struct BlsMultisignature(AggregatedSignature);
+
+impl BlsMultisignature {
+    fn new(signature: &BlsSignature, index: NodeIndex, node_count: NodeCount) -> Self {
+        let mut signers = SignerSet::with_size(node_count.0);
+        signers.insert(index.0);
+        BlsMultisignature(AggregatedSignature {
+            signers,
+            signature: *signature,
+        })
+    }
+}
+
+impl From<BlsMultisignature> for AggregatedSignature {
+    fn from(multisignature: BlsMultisignature) -> AggregatedSignature {
+        multisignature.0
+    }
+}
+
+impl PartialMultisignature for BlsMultisignature {
+    type Signature = BlsSignature;
+
+    fn add_signature(self, signature: &BlsSignature, index: NodeIndex) -> Self {
+        let BlsMultisignature(mut aggregated) = self;
+        // Like in the signature set, signatures of nodes outside the committee are ignored.
+        if index.0 >= aggregated.signers.size() || aggregated.signers.contains(index.0) {
+            return BlsMultisignature(aggregated);
+        }
+        if let Some(signature) = bls::aggregate(&aggregated.signature, signature) {
+            aggregated.signers.insert(index.0);
+            aggregated.signature = signature;
+        }
+        BlsMultisignature(aggregated)
+    }
+}
+
+/// BlsKeyBox combines a BlsPen and an AuthorityVerifier knowing the BLS keys of the session into
+/// one object implementing the AlephBFT MultiKeychain trait with aggregated signatures.
+#[derive(Clone)]
+pub struct BlsKeyBox {
+    id: NodeIndex,
+    bls_pen: BlsPen,
+    authority_verifier: AuthorityVerifier,
 }
 
-impl From<SignatureV1> for Signature {
-    fn from(sig_v1: SignatureV1) -> Signature {
-        Signature(sig_v1.sgn)
+impl BlsKeyBox {
+    /// Constructs a new keybox from a BLS signing contraption and a verifier, with the specified
+    /// node index.
+    pub fn new(id: NodeIndex, authority_verifier: AuthorityVerifier, bls_pen: BlsPen) -> Self {
+        BlsKeyBox {
+            id,
+            bls_pen,
+            authority_verifier,
+        }
+    }
+
+    /// The same keybox, signing and verifying messages tagged with the given domain, or bare
+    /// messages if there is none.
+    pub fn in_domain(&self, domain: Option<SigningDomain>) -> Self {
+        BlsKeyBox {
+            id: self.id,
+            bls_pen: self.bls_pen.in_domain(domain.clone()),
+            authority_verifier: self.authority_verifier.in_domain(domain),
+        }
+    }
+}
+
+impl aleph_bft::Index for BlsKeyBox {
+    fn index(&self) -> NodeIndex {
+        self.id
+    }
+}
+
+#[async_trait::async_trait]
+impl AlephKeyBox for BlsKeyBox {
+    type Signature = BlsSignature;
+
+    fn node_count(&self) -> NodeCount {
+        self.authority_verifier.node_count()
+    }
+
+    async fn sign(&self, msg: &[u8]) -> BlsSignature {
+        self.bls_pen.sign(msg)
+    }
+
+    fn verify(&self, msg: &[u8], sgn: &BlsSignature, index: NodeIndex) -> bool {
+        self.authority_verifier.verify_bls(msg, sgn, index)
+    }
+}
+
+impl MultiKeychain for BlsKeyBox {
+    type PartialMultisignature = BlsMultisignature;
+
+    fn from_signature(&self, signature: &BlsSignature, index: NodeIndex) -> BlsMultisignature {
+        BlsMultisignature::new(signature, index, self.node_count())
+    }
+
+    fn is_complete(&self, msg: &[u8], partial: &BlsMultisignature) -> bool {
+        self.authority_verifier
+            .is_complete_aggregated(msg, &partial.0)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use aleph_bft::Index;
+    use aleph_primitives::SessionId;
     use sp_keystore::{testing::KeyStore, CryptoStore};
 
     async fn generate_keys(names: &[String]) -> (Vec<AuthorityPen>, AuthorityVerifier) {
@@ -269,4 +673,156 @@ mod tests {
             assert!(!verifier.verify(not_msg, &signature, NodeIndex(i)));
         }
     }
+
+    fn domain(purpose: SigningPurpose, session: u32) -> Option<SigningDomain> {
+        Some(SigningDomain::new(
+            purpose,
+            b"genesis".to_vec(),
+            SessionId(session),
+        ))
+    }
+
+    #[tokio::test]
+    async fn does_not_accept_signatures_from_other_domains() {
+        let (pens, verifier) = prepare_test().await;
+        let msg = b"test";
+        let unit_domain = domain(SigningPurpose::Unit, 1);
+        let other_domains = vec![
+            None,
+            domain(SigningPurpose::Authentication, 1),
+            domain(SigningPurpose::Unit, 2),
+        ];
+        for (i, pen) in pens.into_iter().enumerate() {
+            let signature = pen.in_domain(unit_domain.clone()).sign(msg).await;
+            assert!(verifier
+                .in_domain(unit_domain.clone())
+                .verify(msg, &signature, NodeIndex(i)));
+            for other_domain in &other_domains {
+                assert!(!verifier.in_domain(other_domain.clone()).verify(
+                    msg,
+                    &signature,
+                    NodeIndex(i)
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn reads_bls_keys_from_keystore_directory() {
+        let directory = tempfile::tempdir().unwrap();
+        let bls_keystore = BlsKeystore::open(directory.path().to_path_buf());
+        let generated = bls_keystore.generate_new().unwrap();
+
+        let reopened = BlsKeystore::open(directory.path().to_path_buf());
+        assert_eq!(reopened.keys().unwrap(), vec![generated]);
+        let pen = BlsPen::new(&generated, &reopened).unwrap();
+        assert_eq!(pen.public(), generated);
+        assert!(checked_bls_keys(vec![pen.registration()]).is_some());
+    }
+
+    #[test]
+    fn reads_bls_keys_inserted_like_other_keystore_keys() {
+        let directory = tempfile::tempdir().unwrap();
+        let secret = SecretKey::key_gen(&[7; 32], &[]).unwrap();
+        let public = BlsPublic(secret.sk_to_pk().compress());
+        // The way `author_insertKey` stores keys of types the keystore does not know.
+        let file = directory
+            .path()
+            .join(hex::encode(BLS_KEY_TYPE.0) + &hex::encode(public.0));
+        serde_json::to_writer(
+            File::create(file).unwrap(),
+            &format!("0x{}", hex::encode(secret.to_bytes())),
+        )
+        .unwrap();
+
+        let bls_keystore = BlsKeystore::open(directory.path().to_path_buf());
+        assert_eq!(bls_keystore.keys().unwrap(), vec![public]);
+        assert_eq!(
+            BlsPen::new(&public, &bls_keystore).unwrap().public(),
+            public
+        );
+        let other = BlsPublic(
+            SecretKey::key_gen(&[8; 32], &[])
+                .unwrap()
+                .sk_to_pk()
+                .compress(),
+        );
+        assert!(matches!(
+            BlsPen::new(&other, &bls_keystore),
+            Err(Error::KeyMissing)
+        ));
+    }
+
+    async fn prepare_bls_test() -> Vec<BlsKeyBox> {
+        let key_store = Arc::new(KeyStore::new());
+        let mut authority_ids = Vec::new();
+        for name in &["//Alice", "//Bob", "//Charlie", "//Dave"] {
+            let pk = key_store
+                .ed25519_generate_new(KEY_TYPE, Some(name))
+                .await
+                .unwrap();
+            authority_ids.push(AuthorityId::from(pk));
+        }
+        let bls_keystore = BlsKeystore::in_memory();
+        let mut pens = Vec::new();
+        for _ in &authority_ids {
+            let bls_key = bls_keystore.generate_new().unwrap();
+            pens.push(BlsPen::new(&bls_key, &bls_keystore).expect("The key was just generated"));
+        }
+        let bls_keys = checked_bls_keys(pens.iter().map(BlsPen::registration).collect())
+            .expect("The proofs of possession are correct");
+        let verifier = AuthorityVerifier::new(authority_ids).with_bls_keys(bls_keys);
+        pens.into_iter()
+            .enumerate()
+            .map(|(i, pen)| BlsKeyBox::new(NodeIndex(i), verifier.clone(), pen))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn aggregates_bls_signatures_into_complete_multisignatures() {
+        let keyboxes = prepare_bls_test().await;
+        let msg = b"test";
+        let mut multisignature =
+            keyboxes[0].from_signature(&keyboxes[0].sign(msg).await, NodeIndex(0));
+        for keybox in &keyboxes[1..3] {
+            assert!(!keybox.is_complete(msg, &multisignature));
+            let signature = keybox.sign(msg).await;
+            assert!(keyboxes[0].verify(msg, &signature, keybox.index()));
+            multisignature = multisignature.add_signature(&signature, keybox.index());
+        }
+
+        assert!(keyboxes[0].is_complete(msg, &multisignature));
+        assert!(!keyboxes[0].is_complete(b"not test", &multisignature));
+    }
+
+    #[tokio::test]
+    async fn aggregates_bls_signatures_in_domains() {
+        let keyboxes: Vec<_> = prepare_bls_test()
+            .await
+            .iter()
+            .map(|keybox| keybox.in_domain(domain(SigningPurpose::Finality, 1)))
+            .collect();
+        let msg = b"test";
+        let mut multisignature =
+            keyboxes[0].from_signature(&keyboxes[0].sign(msg).await, NodeIndex(0));
+        for keybox in &keyboxes[1..3] {
+            multisignature = multisignature.add_signature(&keybox.sign(msg).await, keybox.index());
+        }
+
+        assert!(keyboxes[0].is_complete(msg, &multisignature));
+        let bare = keyboxes[0].in_domain(None);
+        assert!(!bare.is_complete(msg, &multisignature));
+    }
+
+    #[tokio::test]
+    async fn does_not_accept_bls_keys_without_proofs_of_possession() {
+        let keyboxes = prepare_bls_test().await;
+        let mut registrations: Vec<_> = keyboxes
+            .iter()
+            .map(|keybox| keybox.bls_pen.registration())
+            .collect();
+        registrations[1].proof_of_possession = keyboxes[1].bls_pen.sign(&registrations[1].key.0);
+
+        assert!(checked_bls_keys(registrations).is_none());
+    }
 }
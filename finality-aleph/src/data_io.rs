@@ -1,4 +1,4 @@
-use crate::{metrics::Checkpoint, network, Metrics};
+use crate::{metrics::Checkpoint, new_network, Metrics};
 use async_trait::async_trait;
 use codec::{Decode, Encode};
 use futures::channel::{
@@ -96,7 +96,7 @@ where
     B: BlockT,
     C: crate::ClientForAleph<B, BE> + Send + Sync + 'static,
     BE: Backend<B> + 'static,
-    RB: network::RequestBlocks<B> + 'static,
+    RB: new_network::RequestBlocks<B> + 'static,
     Message: AlephNetworkMessage<B> + std::fmt::Debug,
 {
     next_message_id: MessageId,
@@ -117,7 +117,7 @@ where
     B: BlockT,
     C: crate::ClientForAleph<B, BE> + Send + Sync + 'static,
     BE: Backend<B> + 'static,
-    RB: network::RequestBlocks<B> + 'static,
+    RB: new_network::RequestBlocks<B> + 'static,
     Message: AlephNetworkMessage<B> + std::fmt::Debug,
 {
     pub(crate) fn new(
@@ -0,0 +1,423 @@
+//! Detection and reporting of authorities signing two different blocks of the same height in one
+//! session.
+use crate::{
+    crypto::{AuthorityVerifier, Signature, SigningDomain, SigningPurpose},
+    justification::{
+        decode_justification, AlephJustification, AlephSignature, JustificationNotification,
+    },
+    party::get_session_authorities,
+    session_api, session_id_from_block_num, AuthorityId, NodeIndex, SessionId, SessionMap,
+    SessionSchedule,
+};
+use aleph_primitives::{AlephEquivocationProof, AlephSessionApi, ALEPH_ENGINE_ID};
+use codec::Encode;
+use futures::{channel::mpsc, StreamExt};
+use log::{debug, error, info, warn};
+use parking_lot::Mutex;
+use sc_client_api::{AuxStore, Backend, BlockBackend};
+use sp_api::{BlockId, NumberFor, ProvideRuntimeApi};
+use sp_blockchain::HeaderBackend;
+use sp_runtime::traits::{Block, Header};
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    sync::Arc,
+};
+
+type EquivocationProof<B> = AlephEquivocationProof<<B as Block>::Header>;
+
+/// A signature of a block hash made by a committee member, as received from the network.
+pub(crate) struct ObservedSignature<B: Block, S = Signature> {
+    pub(crate) hash: B::Hash,
+    pub(crate) index: NodeIndex,
+    pub(crate) signature: S,
+}
+
+/// Remembers the blocks signed by the members of a single session committee and produces a proof
+/// whenever a member signs two different blocks of the same height. Signatures are checked in the
+/// finality domain of the session, if it signs in domains.
+pub(crate) struct EquivocationDetector<B: Block> {
+    session_id: SessionId,
+    authorities: Vec<AuthorityId>,
+    verifier: AuthorityVerifier,
+    signed: HashMap<(NodeIndex, NumberFor<B>), (B::Header, Signature)>,
+    reported: HashSet<NodeIndex>,
+}
+
+impl<B: Block> EquivocationDetector<B> {
+    pub(crate) fn new(
+        session_id: SessionId,
+        authorities: Vec<AuthorityId>,
+        domain: Option<SigningDomain>,
+    ) -> Self {
+        EquivocationDetector {
+            session_id,
+            verifier: AuthorityVerifier::new(authorities.clone()).in_domain(domain),
+            authorities,
+            signed: HashMap::new(),
+            reported: HashSet::new(),
+        }
+    }
+
+    /// Records the signature of `header` made by the member of the given index. Returns a proof if
+    /// the member has already signed a different block of the same height. Every member is
+    /// reported at most once.
+    pub(crate) fn on_signature(
+        &mut self,
+        header: B::Header,
+        index: NodeIndex,
+        signature: Signature,
+    ) -> Option<EquivocationProof<B>> {
+        if self.reported.contains(&index)
+            || !self
+                .verifier
+                .verify(&header.hash().encode(), &signature, index)
+        {
+            return None;
+        }
+        let (signed_header, signed_signature) = match self.signed.entry((index, *header.number())) {
+            Entry::Occupied(entry) => entry.get().clone(),
+            Entry::Vacant(entry) => {
+                entry.insert((header, signature));
+                return None;
+            }
+        };
+        if signed_header.hash() == header.hash() {
+            return None;
+        }
+        self.reported.insert(index);
+        Some(AlephEquivocationProof {
+            session: self.session_id.0,
+            offender: self.authorities[index.0].clone(),
+            first_header: signed_header,
+            first_signature: signed_signature.into(),
+            second_header: header,
+            second_signature: signature.into(),
+        })
+    }
+
+    /// Records all the signatures from the justification of `header`.
+    pub(crate) fn on_justification(
+        &mut self,
+        header: &B::Header,
+        justification: &AlephJustification,
+    ) -> Vec<EquivocationProof<B>> {
+        // Aggregated signatures cannot be split to find the equivocating signers. Signatures made
+        // outside of the domain of the detector do not verify and are skipped.
+        let signatures = match justification {
+            AlephJustification::V2(signatures)
+            | AlephJustification::V3 {
+                signature: AlephSignature::Signatures(signatures),
+                ..
+            }
+            | AlephJustification::V4 {
+                signature: AlephSignature::Signatures(signatures),
+                ..
+            } => signatures,
+            AlephJustification::V3 {
+                signature: AlephSignature::Aggregated(_),
+                ..
+            }
+            | AlephJustification::V4 {
+                signature: AlephSignature::Aggregated(_),
+                ..
+            } => return Vec::new(),
+        };
+        signatures
+            .iter()
+            .filter_map(|(index, signature)| {
+                self.on_signature(header.clone(), NodeIndex(index), signature.clone().into())
+            })
+            .collect()
+    }
+}
+
+/// Passes the signatures observed during a session to the detector and forwards the resulting
+/// proofs to the reporter. Ends when the aggregator of the session is dropped.
+pub(crate) async fn run_equivocation_detector<B, C>(
+    client: Arc<C>,
+    mut detector: EquivocationDetector<B>,
+    mut signatures_rx: mpsc::UnboundedReceiver<ObservedSignature<B>>,
+    proofs_tx: mpsc::UnboundedSender<EquivocationProof<B>>,
+) where
+    B: Block,
+    C: HeaderBackend<B>,
+{
+    while let Some(ObservedSignature {
+        hash,
+        index,
+        signature,
+    }) = signatures_rx.next().await
+    {
+        // We can only prove equivocations concerning blocks we know the headers of.
+        let header = match client.header(BlockId::Hash(hash)) {
+            Ok(Some(header)) => header,
+            _ => continue,
+        };
+        if let Some(proof) = detector.on_signature(header, index, signature) {
+            if let Err(e) = proofs_tx.unbounded_send(proof) {
+                warn!(target: "afa", "Failed to pass an equivocation proof to the reporter {:?}", e);
+                return;
+            }
+        }
+    }
+}
+
+/// Submits the equivocation proofs to the runtime and looks for equivocations in justifications
+/// conflicting with the already finalized blocks.
+pub(crate) async fn run_equivocation_reporter<B, C, BE>(
+    client: Arc<C>,
+    session_authorities: Arc<Mutex<SessionMap>>,
+    session_schedule: Arc<Mutex<SessionSchedule>>,
+    mut conflicting_justification_rx: mpsc::UnboundedReceiver<JustificationNotification<B>>,
+    mut proofs_rx: mpsc::UnboundedReceiver<EquivocationProof<B>>,
+) where
+    B: Block,
+    C: crate::ClientForAleph<B, BE> + Send + Sync + 'static,
+    C::Api: AlephSessionApi<B>,
+    BE: Backend<B> + 'static,
+{
+    loop {
+        tokio::select! {
+            maybe_proof = proofs_rx.next() => {
+                if let Some(proof) = maybe_proof {
+                    report_equivocation(client.as_ref(), proof);
+                } else {
+                    debug!(target: "afa", "The stream of equivocation proofs has ended. Terminating.");
+                    return;
+                }
+            }
+            maybe_notification = conflicting_justification_rx.next() => {
+                if let Some(notification) = maybe_notification {
+                    let session_schedule = session_schedule.lock().clone();
+                    for proof in proofs_from_conflicting_justification(
+                        client.as_ref(),
+                        &session_authorities,
+                        &session_schedule,
+                        notification,
+                    ) {
+                        report_equivocation(client.as_ref(), proof);
+                    }
+                } else {
+                    debug!(target: "afa", "The stream of conflicting justifications has ended. Terminating.");
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Compares the signatures in a justification of a block conflicting with a finalized one with
+/// the signatures in the justification of the finalized block. Every authority that signed both
+/// blocks equivocated.
+fn proofs_from_conflicting_justification<B, C>(
+    client: &C,
+    session_authorities: &Mutex<SessionMap>,
+    session_schedule: &SessionSchedule,
+    notification: JustificationNotification<B>,
+) -> Vec<EquivocationProof<B>>
+where
+    B: Block,
+    C: HeaderBackend<B> + BlockBackend<B> + ProvideRuntimeApi<B> + AuxStore,
+    C::Api: AlephSessionApi<B>,
+{
+    let JustificationNotification {
+        justification,
+        hash,
+        ..
+    } = notification;
+    let conflicting_header = match client.header(BlockId::Hash(hash)) {
+        Ok(Some(header)) => header,
+        _ => {
+            debug!(target: "afa", "Cannot look for equivocations, block {:?} is unknown.", hash);
+            return Vec::new();
+        }
+    };
+    let number = *conflicting_header.number();
+    let session_id = session_id_from_block_num::<B>(number, session_schedule);
+    let authorities = match get_session_authorities(session_authorities, client, session_id) {
+        Some(authorities) => authorities,
+        None => {
+            debug!(target: "afa", "Cannot look for equivocations, authorities of session {:?} are unknown.", session_id);
+            return Vec::new();
+        }
+    };
+    let finalized_header = match client.header(BlockId::Number(number)) {
+        Ok(Some(header)) => header,
+        _ => return Vec::new(),
+    };
+    // Blocks finalized implicitly, as ancestors of other blocks, have no justifications.
+    let finalized_justification = match client
+        .justifications(&BlockId::Number(number))
+        .ok()
+        .flatten()
+        .and_then(|justifications| justifications.into_justification(ALEPH_ENGINE_ID))
+        .and_then(|justification| decode_justification(&justification).ok())
+    {
+        Some(justification) => justification,
+        None => {
+            debug!(target: "afa", "Cannot look for equivocations, no justification of finalized block {:?}.", number);
+            return Vec::new();
+        }
+    };
+
+    // The finalized justification was accepted, so it is in the format of the session, which
+    // tells whether the session signs in domains.
+    let domain = match finalized_justification {
+        AlephJustification::V4 { .. } => Some(SigningDomain::new(
+            SigningPurpose::Finality,
+            client.info().genesis_hash.encode(),
+            session_id,
+        )),
+        AlephJustification::V2(_) | AlephJustification::V3 { .. } => None,
+    };
+    let mut detector = EquivocationDetector::<B>::new(session_id, authorities, domain);
+    detector.on_justification(&finalized_header, &finalized_justification);
+    detector.on_justification(&conflicting_header, &justification)
+}
+
+fn report_equivocation<B, C>(client: &C, proof: EquivocationProof<B>)
+where
+    B: Block,
+    C: HeaderBackend<B> + ProvideRuntimeApi<B>,
+    C::Api: AlephSessionApi<B>,
+{
+    let best_block = BlockId::Hash(client.info().best_hash);
+    match session_api::accepts_equivocation_reports(client, &best_block) {
+        Ok(true) => {}
+        Ok(false) => {
+            debug!(target: "afa", "Not reporting equivocation of {:?}, the runtime does not accept reports yet.", proof.offender);
+            return;
+        }
+        Err(e) => {
+            error!(target: "afa", "Error when checking whether equivocation reports are accepted {:?}", e);
+            return;
+        }
+    }
+    info!(target: "afa", "Reporting equivocation of {:?} in session {:?}", proof.offender, proof.session);
+    match client
+        .runtime_api()
+        .submit_report_equivocation_unsigned_extrinsic(&best_block, proof)
+    {
+        Ok(Some(())) => {}
+        Ok(None) => {
+            warn!(target: "afa", "The equivocation report was not accepted by the transaction pool.")
+        }
+        Err(e) => error!(target: "afa", "Error when reporting an equivocation {:?}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        crypto::AuthorityPen,
+        testing::mocks::{create_block, TBlock},
+    };
+    use aleph_primitives::KEY_TYPE;
+    use sp_keystore::{testing::KeyStore, CryptoStore};
+
+    async fn prepare_test_in_domain(
+        domain: Option<SigningDomain>,
+    ) -> (Vec<AuthorityPen>, EquivocationDetector<TBlock>) {
+        let key_store = Arc::new(KeyStore::new());
+        let mut authorities = Vec::new();
+        for name in &["//Alice", "//Bob", "//Charlie"] {
+            let pk = key_store
+                .ed25519_generate_new(KEY_TYPE, Some(name))
+                .await
+                .unwrap();
+            authorities.push(AuthorityId::from(pk));
+        }
+        let mut pens = Vec::new();
+        for authority_id in authorities.clone() {
+            pens.push(
+                AuthorityPen::new(authority_id, key_store.clone())
+                    .await
+                    .unwrap()
+                    .in_domain(domain.clone()),
+            );
+        }
+        (
+            pens,
+            EquivocationDetector::new(SessionId(1), authorities, domain),
+        )
+    }
+
+    async fn prepare_test() -> (Vec<AuthorityPen>, EquivocationDetector<TBlock>) {
+        prepare_test_in_domain(None).await
+    }
+
+    #[tokio::test]
+    async fn detects_signatures_of_different_blocks_of_the_same_height() {
+        let (pens, mut detector) = prepare_test().await;
+        let first = create_block([1u8; 32].into(), 5).header;
+        let second = create_block([2u8; 32].into(), 5).header;
+
+        let first_signature = pens[1].sign(&first.hash().encode()).await;
+        let second_signature = pens[1].sign(&second.hash().encode()).await;
+        assert!(detector
+            .on_signature(first.clone(), NodeIndex(1), first_signature.clone())
+            .is_none());
+        assert!(detector
+            .on_signature(first.clone(), NodeIndex(1), first_signature)
+            .is_none());
+        let proof = detector
+            .on_signature(second.clone(), NodeIndex(1), second_signature.clone())
+            .expect("the member signed two different blocks");
+
+        assert!(proof.is_valid(None));
+        assert_eq!(proof.session, 1);
+        assert_eq!(proof.first_header, first);
+        assert_eq!(proof.second_header, second);
+        // Every member is reported only once.
+        assert!(detector
+            .on_signature(second, NodeIndex(1), second_signature)
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn ignores_different_heights_and_invalid_signatures() {
+        let (pens, mut detector) = prepare_test().await;
+        let first = create_block([1u8; 32].into(), 5).header;
+        let second = create_block([2u8; 32].into(), 6).header;
+        let third = create_block([3u8; 32].into(), 5).header;
+
+        let signature = pens[0].sign(&first.hash().encode()).await;
+        assert!(detector
+            .on_signature(first, NodeIndex(0), signature)
+            .is_none());
+        let signature = pens[0].sign(&second.hash().encode()).await;
+        assert!(detector
+            .on_signature(second, NodeIndex(0), signature)
+            .is_none());
+        let signature = pens[2].sign(&third.hash().encode()).await;
+        assert!(detector
+            .on_signature(third, NodeIndex(0), signature)
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn detects_equivocations_in_signing_domains() {
+        let domain =
+            SigningDomain::new(SigningPurpose::Finality, b"genesis".to_vec(), SessionId(1));
+        let (pens, mut detector) = prepare_test_in_domain(Some(domain.clone())).await;
+        let first = create_block([1u8; 32].into(), 5).header;
+        let second = create_block([2u8; 32].into(), 5).header;
+
+        let signature = pens[1].sign(&first.hash().encode()).await;
+        assert!(detector
+            .on_signature(first, NodeIndex(1), signature)
+            .is_none());
+        let bare_signature = pens[1].in_domain(None).sign(&second.hash().encode()).await;
+        assert!(detector
+            .on_signature(second.clone(), NodeIndex(1), bare_signature)
+            .is_none());
+        let signature = pens[1].sign(&second.hash().encode()).await;
+        let proof = detector
+            .on_signature(second, NodeIndex(1), signature)
+            .expect("the member signed two different blocks");
+
+        assert!(proof.is_valid(Some(&domain)));
+        assert!(!proof.is_valid(None));
+    }
+}
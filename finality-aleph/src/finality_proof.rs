@@ -0,0 +1,311 @@
+//! Proofs of finality of arbitrary finalized blocks. Only some blocks are finalized with a
+//! justification, the rest are finalized as their ancestors. A block is proven by the nearest
+//! later justification in the same session together with the headers connecting the two, and the
+//! session ends leading from a trusted session to the one of the justification.
+use crate::{
+    justification::AlephJustification,
+    session_api,
+    warp_sync::{
+        session_end_proof, stored_justification, verify_session_ends, Error, SessionEndProof,
+        TrustedSession,
+    },
+    SessionId,
+};
+use aleph_primitives::AlephSessionApi;
+use codec::{Decode, Encode};
+use sc_client_api::BlockBackend;
+use sp_api::{BlockId, NumberFor, ProvideRuntimeApi};
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{
+    traits::{Block, Header},
+    SaturatedConversion,
+};
+use std::{marker::PhantomData, sync::Arc};
+
+/// A proof that a block is finalized.
+#[derive(Clone, Encode, Decode, Debug)]
+pub struct FinalityProof<B: Block> {
+    /// The ends of the sessions from the trusted one up to the session preceding the justified
+    /// block, each announcing the authorities, the finality version and possibly the BLS keys of
+    /// the next session.
+    pub session_ends: Vec<SessionEndProof<B>>,
+    /// The headers from the child of the proven block up to the justified block. Empty if the
+    /// proven block is justified itself.
+    pub headers: Vec<B::Header>,
+    /// The justification of the last of the headers, or of the proven block if there are none.
+    pub justification: AlephJustification,
+}
+
+/// Verifies that `proof` proves the finality of the block with the given hash in the chain with
+/// the given genesis hash, starting with the trusted session. Returns the session of the
+/// justification.
+pub fn verify_finality_proof<B: Block>(
+    hash: B::Hash,
+    proof: &FinalityProof<B>,
+    genesis_hash: B::Hash,
+    trusted: TrustedSession,
+) -> Result<TrustedSession, Error> {
+    let trusted = verify_session_ends(&proof.session_ends, genesis_hash, trusted)?;
+    let mut justified_hash = hash;
+    for header in &proof.headers {
+        if *header.parent_hash() != justified_hash {
+            return Err(Error::InvalidProof(format!(
+                "header {:?} is not a child of {:?}",
+                header.hash(),
+                justified_hash
+            )));
+        }
+        justified_hash = header.hash();
+    }
+    if !trusted
+        .verifier(genesis_hash)
+        .verify_justification(&justified_hash.encode(), &proof.justification)
+    {
+        return Err(Error::InvalidProof(format!(
+            "bad justification of block {:?} in session {:?}",
+            justified_hash, trusted.session
+        )));
+    }
+    Ok(trusted)
+}
+
+/// Builds finality proofs of the blocks finalized by the client.
+pub struct FinalityProofProvider<B, C> {
+    client: Arc<C>,
+    _phantom: PhantomData<B>,
+}
+
+impl<B, C> FinalityProofProvider<B, C> {
+    pub fn new(client: Arc<C>) -> Self {
+        FinalityProofProvider {
+            client,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<B, C> FinalityProofProvider<B, C>
+where
+    B: Block,
+    C: HeaderBackend<B> + BlockBackend<B> + ProvideRuntimeApi<B>,
+    C::Api: AlephSessionApi<B>,
+{
+    /// Proves the finality of the block with the given hash to someone who trusts the authorities
+    /// of `trusted_session`.
+    pub fn prove_finality(
+        &self,
+        hash: B::Hash,
+        trusted_session: SessionId,
+    ) -> Result<FinalityProof<B>, Error> {
+        let client = self.client.as_ref();
+        let info = client.info();
+        let number = *client
+            .header(BlockId::Hash(hash))?
+            .ok_or_else(|| Error::UnknownBlock(format!("{:?}", hash)))?
+            .number();
+        if number > info.finalized_number || client.hash(number)? != Some(hash) {
+            return Err(Error::InvalidProof(format!(
+                "block {:?} is not finalized",
+                hash
+            )));
+        }
+        let schedule = session_api::session_schedule(client, &BlockId::Hash(info.finalized_hash))?;
+        let session = schedule.session_of_block(number.saturated_into());
+        if trusted_session.0 > session {
+            return Err(Error::InvalidProof(format!(
+                "block {:?} precedes the trusted session {:?}",
+                hash, trusted_session
+            )));
+        }
+
+        let session_ends = (trusted_session.0..session)
+            .map(|session| {
+                session_end_proof(client, schedule.last_block_of_session(session).into())
+            })
+            .collect::<Result<_, _>>()?;
+
+        // The last block of every session is justified, so we never leave the session.
+        let last_candidate: NumberFor<B> = schedule.last_block_of_session(session).into();
+        let last_candidate = last_candidate.min(info.finalized_number);
+        let mut headers = Vec::new();
+        let mut current = number;
+        let justification = loop {
+            if let Some(justification) = stored_justification(client, current)? {
+                break justification;
+            }
+            if current >= last_candidate {
+                return Err(Error::MissingJustification(format!(
+                    "any block of session {:?} from {:?}",
+                    session, number
+                )));
+            }
+            current += 1u32.into();
+            headers.push(
+                client
+                    .header(BlockId::Number(current))?
+                    .ok_or_else(|| Error::UnknownBlock(format!("{:?}", current)))?,
+            );
+        };
+
+        Ok(FinalityProof {
+            session_ends,
+            headers,
+            justification,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AuthorityId;
+    use crate::{
+        crypto::AuthorityPen,
+        testing::mocks::{create_block, TBlock, THeader},
+    };
+    use aleph_justification::{AlephSignature, SignatureSet};
+    use aleph_primitives::{ConsensusLog, FinalityVersion, ALEPH_ENGINE_ID, KEY_TYPE};
+    use sp_keystore::{testing::KeyStore, CryptoStore};
+    use sp_runtime::DigestItem;
+
+    async fn generate_keys(names: &[&str]) -> (Vec<AuthorityPen>, Vec<AuthorityId>) {
+        let key_store = Arc::new(KeyStore::new());
+        let mut pens = Vec::new();
+        let mut authorities = Vec::new();
+        for name in names {
+            let pk = key_store
+                .ed25519_generate_new(KEY_TYPE, Some(name))
+                .await
+                .unwrap();
+            authorities.push(AuthorityId::from(pk));
+            pens.push(
+                AuthorityPen::new(AuthorityId::from(pk), key_store.clone())
+                    .await
+                    .unwrap(),
+            );
+        }
+        (pens, authorities)
+    }
+
+    async fn justify(
+        header: &THeader,
+        session: u32,
+        signers: &[AuthorityPen],
+    ) -> AlephJustification {
+        let message = header.hash().encode();
+        let mut signature = SignatureSet::with_size(signers.len());
+        for (index, pen) in signers.iter().enumerate() {
+            signature = signature.add_signature(&pen.sign(&message).await.into(), index);
+        }
+        AlephJustification::new(SessionId(session), AlephSignature::Signatures(signature))
+    }
+
+    fn trusted(authorities: Vec<AuthorityId>) -> TrustedSession {
+        TrustedSession {
+            session: SessionId(0),
+            finality_version: FinalityVersion::LEGACY,
+            authorities,
+            bls_keys: None,
+        }
+    }
+
+    /// A proof of block 10 of session 1 justified by its grandchild, trusting session 0.
+    async fn prepare_test() -> (THeader, FinalityProof<TBlock>, Vec<AuthorityId>) {
+        let (first_pens, first) = generate_keys(&["//Alice", "//Bob", "//Charlie"]).await;
+        let (second_pens, second) = generate_keys(&["//Dave", "//Eve", "//Ferdie"]).await;
+
+        let mut session_end = create_block([5u8; 32].into(), 5).header;
+        session_end.digest.push(DigestItem::Consensus(
+            ALEPH_ENGINE_ID,
+            ConsensusLog::NextSessionAuthorities {
+                session: 1,
+                authorities: second,
+            }
+            .encode(),
+        ));
+        session_end.digest.push(DigestItem::Consensus(
+            ALEPH_ENGINE_ID,
+            ConsensusLog::<AuthorityId>::NextSessionFinalityVersion {
+                session: 1,
+                version: FinalityVersion::LEGACY,
+            }
+            .encode(),
+        ));
+        let session_end = SessionEndProof {
+            justification: justify(&session_end, 0, &first_pens).await,
+            header: session_end,
+        };
+
+        let proven = create_block([9u8; 32].into(), 10).header;
+        let child = create_block(proven.hash(), 11).header;
+        let grandchild = create_block(child.hash(), 12).header;
+        let proof = FinalityProof {
+            session_ends: vec![session_end],
+            justification: justify(&grandchild, 1, &second_pens).await,
+            headers: vec![child, grandchild],
+        };
+        (proven, proof, first)
+    }
+
+    #[tokio::test]
+    async fn accepts_valid_proof() {
+        let (proven, proof, authorities) = prepare_test().await;
+
+        let trusted = verify_finality_proof(
+            proven.hash(),
+            &proof,
+            Default::default(),
+            trusted(authorities),
+        )
+        .unwrap();
+
+        assert_eq!(trusted.session, SessionId(1));
+    }
+
+    #[tokio::test]
+    async fn rejects_broken_header_chain() {
+        let (proven, mut proof, authorities) = prepare_test().await;
+        proof.headers.remove(0);
+
+        assert!(verify_finality_proof(
+            proven.hash(),
+            &proof,
+            Default::default(),
+            trusted(authorities)
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_proof_from_untrusted_authorities() {
+        let (proven, proof, _) = prepare_test().await;
+        let (_, untrusted) = generate_keys(&["//Dave", "//Eve", "//Ferdie"]).await;
+
+        assert!(verify_finality_proof(
+            proven.hash(),
+            &proof,
+            Default::default(),
+            trusted(untrusted)
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_justification_claiming_other_session() {
+        let (proven, mut proof, authorities) = prepare_test().await;
+        proof.justification = match proof.justification {
+            AlephJustification::V3 { signature, .. } => {
+                AlephJustification::new(SessionId(2), signature)
+            }
+            justification => justification,
+        };
+
+        assert!(verify_finality_proof(
+            proven.hash(),
+            &proof,
+            Default::default(),
+            trusted(authorities)
+        )
+        .is_err());
+    }
+}
@@ -1,21 +1,25 @@
 use crate::{
-    justification::{
-        backwards_compatible_decode, JustificationDecoding, JustificationNotification,
-    },
+    justification::{decode_justification, JustificationNotification},
     metrics::{Checkpoint, Metrics},
+    session_api, AuthorityId, SessionId,
 };
-use aleph_primitives::ALEPH_ENGINE_ID;
+use aleph_primitives::{
+    AlephSessionApi, BlsKeyRegistration, ConsensusLog, FinalityVersion, SessionSchedule,
+    ALEPH_ENGINE_ID,
+};
+use codec::Decode;
 use futures::channel::mpsc::{TrySendError, UnboundedSender};
 use log::{debug, warn};
 use sc_client_api::backend::Backend;
 use sc_consensus::{
     BlockCheckParams, BlockImport, BlockImportParams, ImportResult, JustificationImport,
 };
-use sp_api::TransactionFor;
-use sp_consensus::Error as ConsensusError;
+use sp_api::{BlockId, TransactionFor};
+use sp_blockchain::HeaderBackend;
+use sp_consensus::{BlockOrigin, Error as ConsensusError};
 use sp_runtime::{
     traits::{Block as BlockT, Header, NumberFor},
-    Justification,
+    Justification, SaturatedConversion,
 };
 use std::{collections::HashMap, marker::PhantomData, sync::Arc, time::Instant};
 
@@ -41,6 +45,101 @@ where
     Decode,
 }
 
+/// Where a block lies within its session.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct SessionPosition {
+    first: bool,
+    last: bool,
+}
+
+impl SessionPosition {
+    fn of_block(schedule: &SessionSchedule, number: u32) -> Self {
+        let session = schedule.session_of_block(number);
+        SessionPosition {
+            first: number > 0 && schedule.first_block_of_session(session) == number,
+            last: schedule.last_block_of_session(session) == number,
+        }
+    }
+}
+
+/// Decodes the Aleph logs in the digest of `header`.
+fn aleph_logs<B: BlockT>(
+    header: &B::Header,
+) -> Result<Vec<ConsensusLog<AuthorityId>>, ConsensusError> {
+    header
+        .digest()
+        .logs()
+        .iter()
+        .filter_map(|log| log.as_consensus())
+        .filter(|(engine_id, _)| engine_id == &ALEPH_ENGINE_ID)
+        .map(|(_, mut data)| {
+            ConsensusLog::<AuthorityId>::decode(&mut data).map_err(|_| {
+                ConsensusError::ClientImport("Could not decode Aleph consensus log.".into())
+            })
+        })
+        .collect()
+}
+
+/// Finds the single announcement picked by `pick` among the Aleph logs of `header`. Fails if any
+/// log cannot be decoded or there is more than one such announcement.
+fn find_announcement<B, T, F>(
+    header: &B::Header,
+    what: &str,
+    pick: F,
+) -> Result<Option<T>, ConsensusError>
+where
+    B: BlockT,
+    F: Fn(ConsensusLog<AuthorityId>) -> Option<T>,
+{
+    let mut announcements = aleph_logs::<B>(header)?.into_iter().filter_map(pick);
+    let announcement = announcements.next();
+    if announcements.next().is_some() {
+        return Err(ConsensusError::ClientImport(format!(
+            "Multiple announcements of the next session {}.",
+            what
+        )));
+    }
+    Ok(announcement)
+}
+
+/// Finds the announcement of the next session authorities in the digest of `header`. Fails if the
+/// announcement cannot be decoded or there is more than one.
+pub(crate) fn find_next_session_authorities<B: BlockT>(
+    header: &B::Header,
+) -> Result<Option<(SessionId, Vec<AuthorityId>)>, ConsensusError> {
+    find_announcement::<B, _, _>(header, "authorities", |log| match log {
+        ConsensusLog::NextSessionAuthorities {
+            session,
+            authorities,
+        } => Some((SessionId(session), authorities)),
+        _ => None,
+    })
+}
+
+/// Finds the announcement of the next session finality version in the digest of `header`. Fails
+/// if the announcement cannot be decoded or there is more than one.
+pub(crate) fn find_next_session_finality_version<B: BlockT>(
+    header: &B::Header,
+) -> Result<Option<(SessionId, FinalityVersion)>, ConsensusError> {
+    find_announcement::<B, _, _>(header, "finality version", |log| match log {
+        ConsensusLog::NextSessionFinalityVersion { session, version } => {
+            Some((SessionId(session), version))
+        }
+        _ => None,
+    })
+}
+
+/// Finds the announcement of the next session BLS keys in the digest of `header`. Fails if the
+/// announcement cannot be decoded or there is more than one.
+pub(crate) fn find_next_session_bls_keys<B: BlockT>(
+    header: &B::Header,
+) -> Result<Option<(SessionId, Vec<BlsKeyRegistration>)>, ConsensusError> {
+    find_announcement::<B, _, _>(header, "BLS keys", |log| match log {
+        ConsensusLog::NextSessionBlsKeys { session, keys } => Some((SessionId(session), keys)),
+        _ => None,
+    })
+}
+
 impl<Block, Be, I> AlephBlockImport<Block, Be, I>
 where
     Block: BlockT,
@@ -60,29 +159,41 @@ where
         }
     }
 
+    /// Decodes the justification and passes it to the justification handler. Justifications
+    /// claiming a session other than the one of the block are rejected right away, if we know the
+    /// session schedule.
     fn send_justification(
         &mut self,
         hash: Block::Hash,
         number: NumberFor<Block>,
         justification: Justification,
-    ) -> Result<(), SendJustificationError<Block>> {
+    ) -> Result<(), SendJustificationError<Block>>
+    where
+        I::Api: AlephSessionApi<Block>,
+    {
         debug!(target: "afa", "Importing justification for block {:?}", number);
         if justification.0 != ALEPH_ENGINE_ID {
             return Err(SendJustificationError::Consensus(Box::new(
                 ConsensusError::ClientImport("Aleph can import only Aleph justifications.".into()),
             )));
         }
-        let justification_raw = justification.1;
-        let aleph_justification = match backwards_compatible_decode(justification_raw) {
-            JustificationDecoding::V1(just) => {
-                debug!(target: "afa", "Justification for block {:?} decoded correctly as V1", number);
-                just.into()
-            }
-            JustificationDecoding::V2(just) => just,
-            JustificationDecoding::Err => {
-                return Err(SendJustificationError::Decode);
+        let aleph_justification =
+            decode_justification(&justification.1).map_err(|_| SendJustificationError::Decode)?;
+        debug!(target: "afa", "Justification for block {:?} decoded correctly as V{:?}", number, aleph_justification.version());
+        if let (Some(justification_session), Some(schedule)) = (
+            aleph_justification.session_id(),
+            self.session_schedule_at(hash),
+        ) {
+            let session = SessionId(schedule.session_of_block(number.saturated_into()));
+            if justification_session != session {
+                return Err(SendJustificationError::Consensus(Box::new(
+                    ConsensusError::ClientImport(format!(
+                        "Justification claims session {:?} for a block of session {:?}.",
+                        justification_session, session
+                    )),
+                )));
             }
-        };
+        }
 
         self.justification_tx
             .unbounded_send(JustificationNotification {
@@ -92,6 +203,75 @@ where
             })
             .map_err(SendJustificationError::Send)
     }
+
+    fn session_schedule_at(&self, hash: Block::Hash) -> Option<SessionSchedule>
+    where
+        I::Api: AlephSessionApi<Block>,
+    {
+        session_api::session_schedule(self.inner.as_ref(), &BlockId::Hash(hash))
+            .map_err(|e| debug!(target: "afa", "Cannot read the session schedule at {:?}: {:?}", hash, e))
+            .ok()
+    }
+
+    /// Checks that the authorities announced in the digest are not empty and come with the
+    /// finality version of the same session, and possibly with one BLS key each, and that the
+    /// announcement is made in the first or in the last block of the session preceding the
+    /// announced one.
+    fn verify_next_session_authorities(&self, header: &Block::Header) -> Result<(), ConsensusError>
+    where
+        I::Api: AlephSessionApi<Block>,
+    {
+        let announced_version = find_next_session_finality_version::<Block>(header)?;
+        let announced_bls_keys = find_next_session_bls_keys::<Block>(header)?;
+        let (session, authorities) = match find_next_session_authorities::<Block>(header)? {
+            Some(announcement) => announcement,
+            None if announced_version.is_none() && announced_bls_keys.is_none() => return Ok(()),
+            None => {
+                return Err(ConsensusError::ClientImport(
+                    "Announced a finality version or BLS keys without the authorities.".into(),
+                ))
+            }
+        };
+        if announced_version.map(|(version_session, _)| version_session) != Some(session) {
+            return Err(ConsensusError::ClientImport(format!(
+                "Announced the authorities of session {:?} without its finality version.",
+                session
+            )));
+        }
+        match announced_bls_keys {
+            Some((keys_session, keys))
+                if keys_session != session || keys.len() != authorities.len() =>
+            {
+                return Err(ConsensusError::ClientImport(format!(
+                    "Announced BLS keys not matching the authorities of session {:?}.",
+                    session
+                )))
+            }
+            _ => {}
+        }
+        if authorities.is_empty() {
+            return Err(ConsensusError::ClientImport(
+                "Announced an empty set of authorities.".into(),
+            ));
+        }
+        let number: u32 = (*header.number()).saturated_into();
+        // Nodes that do not have the state of the parent, e.g. ones importing just the headers,
+        // cannot check the position of the announcement and have to rely on justifications.
+        let schedule = match self.session_schedule_at(*header.parent_hash()) {
+            Some(schedule) => schedule,
+            None => return Ok(()),
+        };
+        let position = SessionPosition::of_block(&schedule, number);
+        if session.0 != schedule.session_of_block(number) + 1 || !(position.first || position.last)
+        {
+            return Err(ConsensusError::ClientImport(format!(
+                "Authorities of session {:?} announced in block {:?}, which neither starts nor ends the preceding session.",
+                session, number
+            )));
+        }
+        debug!(target: "afa", "Block {:?} announces the authorities of session {:?}", number, session);
+        Ok(())
+    }
 }
 
 impl<Block, Be, I> Clone for AlephBlockImport<Block, Be, I>
@@ -116,6 +296,7 @@ where
     Block: BlockT,
     Be: Backend<Block>,
     I: crate::ClientForAleph<Block, Be> + Send,
+    I::Api: AlephSessionApi<Block>,
     for<'a> &'a I:
         BlockImport<Block, Error = ConsensusError, Transaction = TransactionFor<I, Block>>,
     TransactionFor<I, Block>: Send + 'static,
@@ -141,20 +322,46 @@ where
             m.report_block(post_hash, Instant::now(), Checkpoint::Importing);
         };
 
+        self.verify_next_session_authorities(&block.header)?;
+
+        let position = self
+            .session_schedule_at(*block.header.parent_hash())
+            .map(|schedule| SessionPosition::of_block(&schedule, number.saturated_into()))
+            .unwrap_or_default();
+        let initial_sync = block.origin == BlockOrigin::NetworkInitialSync;
+        // While syncing, a new session is entered only once the last block of the previous one is
+        // finalized, i.e. its justification was verified against the authorities we already trust.
+        if initial_sync
+            && position.first
+            && self.inner.info().finalized_number + 1u32.into() < number
+        {
+            return Err(ConsensusError::ClientImport(format!(
+                "Block {:?} starts a new session, but the last block of the previous session is not finalized yet.",
+                number
+            )));
+        }
+
         let justifications = block.justifications.take();
 
         debug!(target: "afa", "Importing block {:?} {:?} {:?}", number, block.header.hash(), block.post_hash());
         let import_result = self.inner.import_block(block, cache).await;
 
-        let imported_aux = match import_result {
+        let mut imported_aux = match import_result {
             Ok(ImportResult::Imported(aux)) => aux,
             Ok(r) => return Ok(r),
             Err(e) => return Err(e),
         };
 
-        if let Some(justification) =
-            justifications.and_then(|just| just.into_justification(ALEPH_ENGINE_ID))
-        {
+        let justification =
+            justifications.and_then(|just| just.into_justification(ALEPH_ENGINE_ID));
+        // A justification received along a block may turn out to be invalid, so while syncing we
+        // always ask the peers for the justification of a session-ending block.
+        if initial_sync && position.last {
+            debug!(target: "afa", "Requesting justification of the last block {:?} of a session", number);
+            imported_aux.needs_justification = true;
+        }
+
+        if let Some(justification) = justification {
             debug!(target: "afa", "Got justification along imported block {:?}", number);
 
             if let Err(e) =
@@ -178,12 +385,27 @@ where
     Block: BlockT,
     Be: Backend<Block>,
     I: crate::ClientForAleph<Block, Be>,
+    I::Api: AlephSessionApi<Block>,
 {
     type Error = ConsensusError;
 
+    /// Requests the justification of the first session-ending block above the finalized one, if we
+    /// already have it. Without it no block of the next session can be imported during sync.
     async fn on_start(&mut self) -> Vec<(Block::Hash, NumberFor<Block>)> {
         debug!(target: "afa", "On start called");
-        Vec::new()
+        let info = self.inner.info();
+        let schedule = match self.session_schedule_at(info.finalized_hash) {
+            Some(schedule) => schedule,
+            None => return Vec::new(),
+        };
+        let next: u32 = (info.finalized_number + 1u32.into()).saturated_into();
+        let last_block: NumberFor<Block> = schedule
+            .last_block_of_session(schedule.session_of_block(next))
+            .into();
+        match self.inner.hash(last_block) {
+            Ok(Some(hash)) => vec![(hash, last_block)],
+            _ => Vec::new(),
+        }
     }
 
     async fn import_justification(
@@ -206,3 +428,173 @@ where
             })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::mocks::{create_block, TBlock};
+    use aleph_primitives::{
+        AuthorityPair, BlsPublic, BlsSignature, BLS_PUBLIC_SIZE, BLS_SIGNATURE_SIZE,
+    };
+    use codec::Encode;
+    use sp_core::Pair;
+    use sp_runtime::DigestItem;
+
+    fn announcement(session: u32) -> DigestItem<<TBlock as BlockT>::Hash> {
+        let authorities = vec![AuthorityPair::from_seed(&[1u8; 32]).public()];
+        DigestItem::Consensus(
+            ALEPH_ENGINE_ID,
+            ConsensusLog::NextSessionAuthorities {
+                session,
+                authorities,
+            }
+            .encode(),
+        )
+    }
+
+    fn version_announcement(session: u32) -> DigestItem<<TBlock as BlockT>::Hash> {
+        DigestItem::Consensus(
+            ALEPH_ENGINE_ID,
+            ConsensusLog::<AuthorityId>::NextSessionFinalityVersion {
+                session,
+                version: FinalityVersion::DOMAIN_SEPARATED,
+            }
+            .encode(),
+        )
+    }
+
+    #[test]
+    fn finds_announced_finality_version() {
+        let mut header = create_block([1u8; 32].into(), 10).header;
+        header.digest.push(announcement(3));
+        assert_eq!(
+            find_next_session_finality_version::<TBlock>(&header).unwrap(),
+            None
+        );
+
+        header.digest.push(version_announcement(3));
+        assert_eq!(
+            find_next_session_finality_version::<TBlock>(&header).unwrap(),
+            Some((SessionId(3), FinalityVersion::DOMAIN_SEPARATED))
+        );
+        assert!(find_next_session_authorities::<TBlock>(&header)
+            .unwrap()
+            .is_some());
+
+        header.digest.push(version_announcement(3));
+        assert!(find_next_session_finality_version::<TBlock>(&header).is_err());
+    }
+
+    #[test]
+    fn finds_announced_bls_keys() {
+        let mut header = create_block([1u8; 32].into(), 10).header;
+        header.digest.push(announcement(3));
+        header.digest.push(version_announcement(3));
+        assert_eq!(find_next_session_bls_keys::<TBlock>(&header).unwrap(), None);
+
+        let keys = vec![BlsKeyRegistration {
+            key: BlsPublic([1; BLS_PUBLIC_SIZE]),
+            proof_of_possession: BlsSignature([2; BLS_SIGNATURE_SIZE]),
+        }];
+        header.digest.push(DigestItem::Consensus(
+            ALEPH_ENGINE_ID,
+            ConsensusLog::<AuthorityId>::NextSessionBlsKeys {
+                session: 3,
+                keys: keys.clone(),
+            }
+            .encode(),
+        ));
+        assert_eq!(
+            find_next_session_bls_keys::<TBlock>(&header).unwrap(),
+            Some((SessionId(3), keys))
+        );
+    }
+
+    #[test]
+    fn finds_announced_authorities() {
+        let mut header = create_block([1u8; 32].into(), 10).header;
+        assert_eq!(
+            find_next_session_authorities::<TBlock>(&header).unwrap(),
+            None
+        );
+
+        header.digest.push(announcement(3));
+        let (session, authorities) = find_next_session_authorities::<TBlock>(&header)
+            .unwrap()
+            .expect("the announcement is in the digest");
+        assert_eq!(session, SessionId(3));
+        assert_eq!(authorities.len(), 1);
+    }
+
+    #[test]
+    fn recognizes_session_boundaries() {
+        let schedule = SessionSchedule::new(10, 1000)
+            .with_change(2, 5, 1000)
+            .unwrap();
+        let position = |number| SessionPosition::of_block(&schedule, number);
+
+        assert_eq!(
+            position(0),
+            SessionPosition {
+                first: false,
+                last: false
+            }
+        );
+        assert_eq!(
+            position(9),
+            SessionPosition {
+                first: false,
+                last: true
+            }
+        );
+        assert_eq!(
+            position(10),
+            SessionPosition {
+                first: true,
+                last: false
+            }
+        );
+        assert_eq!(
+            position(19),
+            SessionPosition {
+                first: false,
+                last: true
+            }
+        );
+        assert_eq!(
+            position(20),
+            SessionPosition {
+                first: true,
+                last: false
+            }
+        );
+        assert_eq!(
+            position(24),
+            SessionPosition {
+                first: false,
+                last: true
+            }
+        );
+        assert_eq!(
+            position(25),
+            SessionPosition {
+                first: true,
+                last: false
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_and_duplicated_announcements() {
+        let mut header = create_block([1u8; 32].into(), 10).header;
+        header
+            .digest
+            .push(DigestItem::Consensus(ALEPH_ENGINE_ID, vec![7, 7, 7]));
+        assert!(find_next_session_authorities::<TBlock>(&header).is_err());
+
+        let mut header = create_block([1u8; 32].into(), 10).header;
+        header.digest.push(announcement(3));
+        header.digest.push(announcement(3));
+        assert!(find_next_session_authorities::<TBlock>(&header).is_err());
+    }
+}
@@ -1,27 +1,83 @@
 use crate::{
-    crypto::{Signature, SignatureV1},
+    crypto::{BlsMultisignature, Signature},
     finalization::BlockFinalizer,
     metrics::Checkpoint,
-    network, Metrics, SessionId,
+    new_network, FinalityVersion, Metrics, SessionId,
 };
-use aleph_bft::{PartialMultisignature, SignatureSet};
+use aleph_bft::SignatureSet;
+pub use aleph_justification::AlephJustification;
+pub(crate) use aleph_justification::{decode_justification, AlephSignature};
 use aleph_primitives::ALEPH_ENGINE_ID;
-use codec::{Decode, DecodeAll, Encode};
+use codec::Encode;
 use futures::{channel::mpsc, Stream, StreamExt};
 use futures_timer::Delay;
 use log::{debug, error, warn};
+use lru::LruCache;
+use parking_lot::Mutex;
 use sc_client_api::HeaderBackend;
 use sp_api::{BlockId, BlockT, NumberFor};
+use sp_core::blake2_256;
 use sp_runtime::traits::Header;
 use std::marker::PhantomData;
 use std::time::Instant;
 use std::{sync::Arc, time::Duration};
 use tokio::time::timeout;
 
-/// A proof of block finality, currently in the form of a sufficiently long list of signatures.
-#[derive(Clone, Encode, Decode, Debug, PartialEq)]
-pub struct AlephJustification {
-    pub(crate) signature: SignatureSet<Signature>,
+/// A justification of a block of the session, in the format matching the finality version of
+/// the session.
+fn versioned_justification(
+    session_id: SessionId,
+    version: FinalityVersion,
+    signature: AlephSignature,
+) -> AlephJustification {
+    if version.signs_in_domains() {
+        AlephJustification::domain_separated(session_id, signature)
+    } else {
+        AlephJustification::new(session_id, signature)
+    }
+}
+
+/// Turns a multisignature produced by AlephBFT into a justification of a block of the session.
+pub(crate) fn justification_from_multisignature(
+    multisignature: SignatureSet<Signature>,
+    session_id: SessionId,
+    version: FinalityVersion,
+) -> AlephJustification {
+    let size = multisignature.size().0;
+    let signature = multisignature.into_iter().fold(
+        aleph_justification::SignatureSet::with_size(size),
+        |sig_set, (index, signature)| sig_set.add_signature(&signature.into(), index.0),
+    );
+    versioned_justification(session_id, version, AlephSignature::Signatures(signature))
+}
+
+/// Multisignatures produced by the aggregator, which become justifications of the signed blocks.
+pub(crate) trait IntoJustification {
+    fn into_justification(
+        self,
+        session_id: SessionId,
+        version: FinalityVersion,
+    ) -> AlephJustification;
+}
+
+impl IntoJustification for SignatureSet<Signature> {
+    fn into_justification(
+        self,
+        session_id: SessionId,
+        version: FinalityVersion,
+    ) -> AlephJustification {
+        justification_from_multisignature(self, session_id, version)
+    }
+}
+
+impl IntoJustification for BlsMultisignature {
+    fn into_justification(
+        self,
+        session_id: SessionId,
+        version: FinalityVersion,
+    ) -> AlephJustification {
+        versioned_justification(session_id, version, AlephSignature::Aggregated(self.into()))
+    }
 }
 
 pub(crate) trait Verifier<B: BlockT> {
@@ -71,6 +127,78 @@ pub struct JustificationNotification<Block: BlockT> {
     pub number: NumberFor<Block>,
 }
 
+/// A justification of a block that has just been finalized with it.
+#[derive(Clone)]
+pub struct FinalizedJustification<Block: BlockT> {
+    /// The justification itself.
+    pub justification: AlephJustification,
+    /// The hash of the finalized block.
+    pub hash: Block::Hash,
+    /// The number of the finalized block.
+    pub number: NumberFor<Block>,
+    /// The session the finalized block belongs to.
+    pub session: SessionId,
+}
+
+/// How many justifications may wait for a subscriber before it is considered lagging and its
+/// subscription is dropped.
+pub const SUBSCRIPTION_BUFFER: usize = 64;
+
+type Subscribers<B> = Arc<Mutex<Vec<mpsc::Sender<FinalizedJustification<B>>>>>;
+
+/// Broadcasts the justifications of finalized blocks to all the subscribers of the corresponding
+/// `JustificationStream`. Subscribers that do not keep up are dropped, so a slow one cannot make
+/// the node buffer justifications without bound.
+#[derive(Clone)]
+pub struct JustificationSender<B: BlockT> {
+    subscribers: Subscribers<B>,
+}
+
+impl<B: BlockT> JustificationSender<B> {
+    pub(crate) fn notify(&self, justification: FinalizedJustification<B>) {
+        let mut subscribers = self.subscribers.lock();
+        *subscribers = subscribers
+            .drain(..)
+            .filter_map(|mut subscriber| match subscriber.try_send(justification.clone()) {
+                Ok(()) => Some(subscriber),
+                Err(e) if e.is_full() => {
+                    warn!(target: "afa", "Dropping a justification subscriber lagging behind by more than {} justifications.", SUBSCRIPTION_BUFFER);
+                    None
+                }
+                Err(_) => None,
+            })
+            .collect();
+    }
+}
+
+/// A handle for subscribing to the justifications of finalized blocks.
+#[derive(Clone)]
+pub struct JustificationStream<B: BlockT> {
+    subscribers: Subscribers<B>,
+}
+
+impl<B: BlockT> JustificationStream<B> {
+    /// Creates a connected sender and stream.
+    pub fn channel() -> (JustificationSender<B>, Self) {
+        let subscribers = Arc::new(Mutex::new(Vec::new()));
+        (
+            JustificationSender {
+                subscribers: subscribers.clone(),
+            },
+            JustificationStream { subscribers },
+        )
+    }
+
+    /// Returns a receiver of all the justifications of blocks finalized from now on. Dropping the
+    /// receiver ends the subscription, and so does falling more than `SUBSCRIPTION_BUFFER`
+    /// justifications behind, in which case the receiver ends after the buffered ones.
+    pub fn subscribe(&self) -> mpsc::Receiver<FinalizedJustification<B>> {
+        let (tx, rx) = mpsc::channel(SUBSCRIPTION_BUFFER);
+        self.subscribers.lock().push(tx);
+        rx
+    }
+}
+
 pub(crate) struct JustificationHandlerConfig<B: BlockT, D: JustificationRequestDelay> {
     pub(crate) justification_request_delay: D,
     pub(crate) metrics: Option<Metrics<<B::Header as Header>::Hash>>,
@@ -78,13 +206,21 @@ pub(crate) struct JustificationHandlerConfig<B: BlockT, D: JustificationRequestD
     pub(crate) verifier_timeout: Duration,
     /// How long should we wait for any notification.
     pub(crate) notification_timeout: Duration,
+    /// Where to send justifications of blocks conflicting with already finalized ones.
+    pub(crate) conflicting_justification_tx:
+        Option<mpsc::UnboundedSender<JustificationNotification<B>>>,
+    /// Where to announce the justifications of finalized blocks.
+    pub(crate) justification_sender: Option<JustificationSender<B>>,
+    /// How many already verified justifications to remember, so that the same justification
+    /// arriving again does not have its signatures checked anew. Has to be positive.
+    pub(crate) verified_cache_capacity: usize,
 }
 
 pub(crate) struct JustificationHandler<B, V, RB, C, D, SI, F>
 where
     B: BlockT,
     V: Verifier<B>,
-    RB: network::RequestBlocks<B> + 'static,
+    RB: new_network::RequestBlocks<B> + 'static,
     C: HeaderBackend<B> + Send + Sync + 'static,
     D: JustificationRequestDelay,
     SI: SessionInfoProvider<B, V>,
@@ -95,6 +231,7 @@ where
     client: Arc<C>,
     finalizer: F,
     config: JustificationHandlerConfig<B, D>,
+    verified: LruCache<(B::Hash, [u8; 32]), ()>,
     phantom: PhantomData<V>,
 }
 
@@ -102,7 +239,7 @@ impl<B, V, RB, C, D, SI, F> JustificationHandler<B, V, RB, C, D, SI, F>
 where
     B: BlockT,
     V: Verifier<B>,
-    RB: network::RequestBlocks<B> + 'static,
+    RB: new_network::RequestBlocks<B> + 'static,
     C: HeaderBackend<B> + Send + Sync + 'static,
     D: JustificationRequestDelay,
     SI: SessionInfoProvider<B, V>,
@@ -115,38 +252,80 @@ where
         finalizer: F,
         config: JustificationHandlerConfig<B, D>,
     ) -> Self {
+        let verified = LruCache::new(config.verified_cache_capacity);
         Self {
             session_info_provider,
             block_requester,
             client,
             finalizer,
             config,
+            verified,
             phantom: PhantomData,
         }
     }
 
+    /// Forwards the justification if it concerns a block conflicting with an already finalized
+    /// one, as such justifications might prove equivocations.
+    fn forward_if_conflicting(&self, notification: JustificationNotification<B>) {
+        let conflicting_justification_tx = match &self.config.conflicting_justification_tx {
+            Some(tx) => tx,
+            None => return,
+        };
+        match self.client.hash(notification.number) {
+            Ok(Some(finalized_hash)) if finalized_hash != notification.hash => {
+                debug!(target: "afa", "Justification for block {:?} {:?} conflicts with finalized {:?}", notification.number, notification.hash, finalized_hash);
+                if let Err(e) = conflicting_justification_tx.unbounded_send(notification) {
+                    warn!(target: "afa", "Failed to forward a conflicting justification {:?}", e);
+                }
+            }
+            _ => {}
+        }
+    }
+
     fn handle_justification_notification(
         &mut self,
         notification: JustificationNotification<B>,
         verifier: V,
+        session: SessionId,
         last_finalized: NumberFor<B>,
         stop_h: NumberFor<B>,
     ) {
+        if notification.number <= last_finalized {
+            debug!(target: "afa", "Not finalizing block {:?}. Last finalized {:?}", notification.number, last_finalized);
+            self.forward_if_conflicting(notification);
+            return;
+        }
+
         let JustificationNotification {
             justification,
             number,
             hash,
         } = notification;
 
-        if number <= last_finalized || number > stop_h {
+        if number > stop_h {
             debug!(target: "afa", "Not finalizing block {:?}. Last finalized {:?}, stop_h {:?}", number, last_finalized, stop_h);
             return;
         };
 
-        if !(verifier.verify(&justification, hash)) {
-            warn!(target: "afa", "Error when verifying justification for block {:?} {:?}", number, hash);
-            return;
-        };
+        // Older justifications do not say which session they belong to, for them the signatures
+        // alone have to do.
+        if let Some(justification_session) = justification.session_id() {
+            if justification_session != session {
+                warn!(target: "afa", "Justification for block {:?} {:?} claims session {:?} instead of {:?}", number, hash, justification_session, session);
+                return;
+            }
+        }
+
+        // The same justification often comes both from the aggregator and from import, and is
+        // resent if the block could not be finalized yet, so we remember which ones were correct.
+        let key = (hash, blake2_256(&justification.encode()));
+        if self.verified.get(&key).is_none() {
+            if !(verifier.verify(&justification, hash)) {
+                warn!(target: "afa", "Error when verifying justification for block {:?} {:?}", number, hash);
+                return;
+            };
+            self.verified.put(key, ());
+        }
 
         debug!(target: "afa", "Finalizing block {:?} {:?}", number, hash);
         let finalization_res = self.finalizer.finalize_block(
@@ -161,6 +340,14 @@ where
                 if let Some(metrics) = &self.config.metrics {
                     metrics.report_block(hash, Instant::now(), Checkpoint::Finalized);
                 }
+                if let Some(sender) = &self.config.justification_sender {
+                    sender.notify(FinalizedJustification {
+                        justification,
+                        hash,
+                        number,
+                        session,
+                    });
+                }
             }
             Err(e) => {
                 error!(target: "afa", "Fail in finalization of {:?} {:?} -- {:?}", number, hash, e);
@@ -213,6 +400,7 @@ where
                     self.handle_justification_notification(
                         notification,
                         verifier,
+                        current_session,
                         last_finalized_number,
                         stop_h,
                     );
@@ -239,41 +427,3 @@ fn wrap_channel_with_logging<B: BlockT>(
             None
         })))
 }
-
-/// Old format of justifications, needed for backwards compatibility.
-#[derive(Clone, Encode, Decode, Debug, PartialEq)]
-pub(crate) struct AlephJustificationV1 {
-    pub(crate) signature: SignatureSet<SignatureV1>,
-}
-
-impl From<AlephJustificationV1> for AlephJustification {
-    fn from(just_v1: AlephJustificationV1) -> AlephJustification {
-        let size = just_v1.signature.size();
-        let just_drop_id: SignatureSet<Signature> = just_v1
-            .signature
-            .into_iter()
-            .fold(SignatureSet::with_size(size), |sig_set, (id, sgn)| {
-                sig_set.add_signature(&sgn.into(), id)
-            });
-        AlephJustification {
-            signature: just_drop_id,
-        }
-    }
-}
-
-#[derive(Clone, Debug, PartialEq)]
-pub(crate) enum JustificationDecoding {
-    V1(AlephJustificationV1),
-    V2(AlephJustification),
-    Err,
-}
-
-pub(crate) fn backwards_compatible_decode(justification_raw: Vec<u8>) -> JustificationDecoding {
-    if let Ok(justification) = AlephJustification::decode_all(&justification_raw) {
-        JustificationDecoding::V2(justification)
-    } else if let Ok(justification) = AlephJustificationV1::decode_all(&justification_raw) {
-        JustificationDecoding::V1(justification)
-    } else {
-        JustificationDecoding::Err
-    }
-}
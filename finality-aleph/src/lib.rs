@@ -3,7 +3,10 @@ use codec::{Decode, Encode};
 pub use aleph_bft::default_config as default_aleph_config;
 use aleph_bft::{NodeCount, NodeIndex, TaskHandle};
 use futures::{channel::oneshot, Future, TryFutureExt};
-use sc_client_api::{backend::Backend, BlockchainEvents, Finalizer, LockImportRun, TransactionFor};
+use sc_client_api::{
+    backend::Backend, AuxStore, BlockBackend, BlockchainEvents, Finalizer, LockImportRun,
+    TransactionFor,
+};
 use sc_consensus::BlockImport;
 use sc_service::SpawnTaskHandle;
 use sp_api::{NumberFor, ProvideRuntimeApi};
@@ -16,8 +19,11 @@ use sp_runtime::{
 };
 use std::{collections::HashMap, fmt::Debug, sync::Arc};
 mod aggregator;
+mod aux_schema;
 mod crypto;
 mod data_io;
+mod equivocation;
+mod finality_proof;
 mod finalization;
 mod hash;
 mod import;
@@ -26,11 +32,52 @@ pub mod metrics;
 mod network;
 mod new_network;
 mod party;
+pub mod rpc;
+mod session_api;
 #[cfg(test)]
 pub mod testing;
+mod tuning;
+mod warp_sync;
 
+pub use crypto::{bls_key_registration, BlsKeystore, Error as CryptoError};
+pub use finality_proof::{verify_finality_proof, FinalityProof, FinalityProofProvider};
 pub use import::AlephBlockImport;
-pub use justification::JustificationNotification;
+pub use justification::{
+    FinalizedJustification, JustificationNotification, JustificationSender, JustificationStream,
+    SUBSCRIPTION_BUFFER,
+};
+pub use session_api::session_schedule;
+pub use tuning::{
+    AggregatorTuning, AlephTuning, ConsensusTuning, DataStoreTuning, JustificationTuning,
+    TuningError,
+};
+pub use warp_sync::{AlephWarpSyncProvider, Error as ProofError, SessionEndProof, TrustedSession};
+
+/// Configurations of all the network protocols used by the node: the generic and validator
+/// protocols of the network, and the legacy protocols of the finality versions that still use the
+/// legacy network, as the version changes between sessions.
+pub fn peers_set_configs() -> Vec<sc_network::config::NonDefaultSetConfig> {
+    // The legacy protocols are only used by the finality versions older than
+    // `FinalityVersion::NEW_NETWORK` (version 2). Once every chain run by the node has switched to
+    // that version, they can be dropped here together with `network.rs` and
+    // `new_network/compatibility.rs`.
+    FinalityVersion::known()
+        .filter(|version| version.uses_legacy_network())
+        .map(|version| {
+            let mut config = peers_set_config(None);
+            config.notifications_protocol = network::protocol_name(version);
+            config
+        })
+        .chain(
+            [
+                new_network::Protocol::Generic,
+                new_network::Protocol::Validator,
+            ]
+            .iter()
+            .map(|protocol| peers_set_config(Some(protocol.clone()))),
+        )
+        .collect()
+}
 
 #[derive(Clone, Debug, Encode, Decode)]
 enum Error {
@@ -66,9 +113,6 @@ pub fn peers_set_config(protocol: Option<new_network::Protocol>) -> sc_network::
     config
 }
 
-#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, Encode, Decode)]
-pub struct SessionId(pub u32);
-
 #[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, Encode, Decode)]
 pub struct SessionPeriod(pub u32);
 
@@ -79,8 +123,10 @@ pub struct MillisecsPerBlock(pub u64);
 pub struct UnitCreationDelay(pub u64);
 
 pub use crate::metrics::Metrics;
-use crate::party::{run_consensus_party, AlephParams};
-pub use aleph_primitives::{AuthorityId, AuthorityPair, AuthoritySignature};
+use crate::party::{run_consensus_party, run_nonvalidator_node, AlephParams};
+pub use aleph_primitives::{
+    AuthorityId, AuthorityPair, AuthoritySignature, FinalityVersion, SessionId, SessionSchedule,
+};
 use futures::channel::mpsc;
 use sp_runtime::traits::Header;
 
@@ -92,6 +138,8 @@ pub trait ClientForAleph<B, BE>:
     + HeaderBackend<B>
     + HeaderMetadata<B, Error = sp_blockchain::Error>
     + BlockchainEvents<B>
+    + BlockBackend<B>
+    + AuxStore
 where
     BE: Backend<B>,
     B: Block,
@@ -108,6 +156,8 @@ where
         + HeaderBackend<B>
         + HeaderMetadata<B, Error = sp_blockchain::Error>
         + BlockchainEvents<B>
+        + BlockBackend<B>
+        + AuxStore
         + BlockImport<B, Transaction = TransactionFor<BE, B>, Error = sp_consensus::Error>,
 {
 }
@@ -146,13 +196,16 @@ pub type SessionMap = HashMap<SessionId, Vec<AuthorityId>>;
 
 pub fn last_block_of_session<B: Block>(
     session_id: SessionId,
-    period: SessionPeriod,
+    schedule: &SessionSchedule,
 ) -> NumberFor<B> {
-    ((session_id.0 + 1) * period.0 - 1).into()
+    schedule.last_block_of_session(session_id.0).into()
 }
 
-pub fn session_id_from_block_num<B: Block>(num: NumberFor<B>, period: SessionPeriod) -> SessionId {
-    SessionId(num.saturated_into::<u32>() / period.0)
+pub fn session_id_from_block_num<B: Block>(
+    num: NumberFor<B>,
+    schedule: &SessionSchedule,
+) -> SessionId {
+    SessionId(schedule.session_of_block(num.saturated_into()))
 }
 
 pub struct AlephConfig<B: Block, N, C, SC> {
@@ -161,11 +214,12 @@ pub struct AlephConfig<B: Block, N, C, SC> {
     pub select_chain: SC,
     pub spawn_handle: SpawnTaskHandle,
     pub keystore: Arc<dyn CryptoStore>,
+    pub bls_keystore: BlsKeystore,
     pub justification_rx: mpsc::UnboundedReceiver<JustificationNotification<B>>,
+    pub justification_sender: JustificationSender<B>,
     pub metrics: Option<Metrics<<B::Header as Header>::Hash>>,
-    pub session_period: SessionPeriod,
-    pub millisecs_per_block: MillisecsPerBlock,
-    pub unit_creation_delay: UnitCreationDelay,
+    pub session_schedule: SessionSchedule,
+    pub tuning: AlephTuning,
 }
 
 pub fn run_aleph_consensus<B: Block, BE, C, N, SC>(
@@ -173,10 +227,28 @@ pub fn run_aleph_consensus<B: Block, BE, C, N, SC>(
 ) -> impl Future<Output = ()>
 where
     BE: Backend<B> + 'static,
-    N: network::Network<B> + network::RequestBlocks<B> + 'static,
+    N: network::Network<B>
+        + new_network::Network
+        + new_network::NetworkIdentity
+        + new_network::RequestBlocks<B>
+        + 'static,
     C: ClientForAleph<B, BE> + Send + Sync + 'static,
     C::Api: aleph_primitives::AlephSessionApi<B>,
     SC: SelectChain<B> + 'static,
 {
     run_consensus_party(AlephParams { config })
 }
+
+/// Runs the part of the finality gadget needed on full nodes that are not authorities: it
+/// finalizes the imported blocks with their justifications and publishes the justifications.
+pub fn run_aleph_nonvalidator<B: Block, BE, C, N, SC>(
+    config: AlephConfig<B, N, C, SC>,
+) -> impl Future<Output = ()>
+where
+    BE: Backend<B> + 'static,
+    N: new_network::RequestBlocks<B> + 'static,
+    C: ClientForAleph<B, BE> + Send + Sync + 'static,
+    C::Api: aleph_primitives::AlephSessionApi<B>,
+{
+    run_nonvalidator_node(AlephParams { config })
+}
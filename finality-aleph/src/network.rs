@@ -1,62 +1,38 @@
-use aleph_bft::{Index, KeyBox as _, NodeIndex, SignatureSet};
+use aleph_bft::{Index, KeyBox as _, NodeIndex};
 use codec::{Codec, Decode, Encode};
-use futures::{channel::mpsc, stream::Stream, FutureExt, StreamExt};
+use futures::{channel::mpsc, stream::Stream, StreamExt};
 use parking_lot::Mutex;
-use sc_network::{multiaddr, Event, ExHashT, NetworkService, PeerId as ScPeerId, ReputationChange};
+use sc_network::{multiaddr, Event, ExHashT, NetworkService, ReputationChange};
 use sp_runtime::traits::Block as BlockT;
-use std::{
-    borrow::Cow, collections::HashMap, hash::Hash, iter, marker::PhantomData, pin::Pin, sync::Arc,
-};
+use std::{borrow::Cow, collections::HashMap, iter, marker::PhantomData, pin::Pin, sync::Arc};
 
-use log::{debug, error, info, trace, warn};
+use log::{debug, error, info, trace};
 use std::time::Duration;
 
 use crate::{
     crypto::{KeyBox, Signature},
-    data_io::AlephDataFor,
-    Error, Hasher, SessionId,
+    Error, FinalityVersion, SessionId,
 };
-use sp_api::NumberFor;
-use std::{fmt::Debug, future::Future};
-
-pub use crate::new_network::AlephNetworkData;
-use crate::new_network::RmcNetworkData;
-
-#[derive(PartialEq, Eq, Copy, Clone, Debug, Hash)]
-pub struct PeerId(pub(crate) ScPeerId);
-
-impl From<PeerId> for ScPeerId {
-    fn from(wrapper: PeerId) -> Self {
-        wrapper.0
-    }
-}
 
-impl From<ScPeerId> for PeerId {
-    fn from(id: ScPeerId) -> Self {
-        PeerId(id)
-    }
-}
-
-impl Encode for PeerId {
-    fn using_encoded<R, F: FnOnce(&[u8]) -> R>(&self, f: F) -> R {
-        self.0.to_bytes().using_encoded(f)
-    }
-}
-
-impl Decode for PeerId {
-    fn decode<I: codec::Input>(value: &mut I) -> Result<Self, codec::Error> {
-        let bytes = Vec::<u8>::decode(value)?;
-        ScPeerId::from_bytes(&bytes)
-            .map_err(|_| "PeerId not encoded with to_bytes".into())
-            .map(|pid| pid.into())
-    }
-}
+use crate::new_network::{AlephNetworkData, PeerId, RmcBlsNetworkData, RmcNetworkData};
 
 /// Name of the network protocol used by Aleph Zero. This is how messages
 /// are subscribed to ensure that we are gossiping and communicating with our
 /// own network.
 pub(crate) const ALEPH_PROTOCOL_NAME: &str = "/cardinals/aleph/1";
 
+/// Name of the network protocol used by sessions signing in domains, so that nodes running
+/// different finality versions never exchange messages.
+pub(crate) const ALEPH_DOMAINS_PROTOCOL_NAME: &str = "/cardinals/aleph_domains/1";
+
+/// Name of the network protocol used by sessions running the given finality version.
+pub(crate) fn protocol_name(version: FinalityVersion) -> Cow<'static, str> {
+    match version.signs_in_domains() {
+        true => Cow::Borrowed(ALEPH_DOMAINS_PROTOCOL_NAME),
+        false => Cow::Borrowed(ALEPH_PROTOCOL_NAME),
+    }
+}
+
 /// Abstraction over a network.
 pub trait Network<B: BlockT>: Clone + Send + Sync + 'static {
     /// Returns a stream of events representing what happens on the network.
@@ -85,28 +61,6 @@ pub trait Network<B: BlockT>: Clone + Send + Sync + 'static {
     fn peer_id(&self) -> PeerId;
 }
 
-pub trait RequestBlocks<B: BlockT>: Clone + Send + Sync + 'static {
-    /// Request the justification for the given block
-    fn request_justification(&self, hash: &B::Hash, number: NumberFor<B>);
-
-    /// Request the given block -- this is supposed to be used only for "old forks".
-    fn request_stale_block(&self, hash: B::Hash, number: NumberFor<B>);
-}
-
-impl<B: BlockT, H: ExHashT> RequestBlocks<B> for Arc<NetworkService<B, H>> {
-    fn request_justification(&self, hash: &B::Hash, number: NumberFor<B>) {
-        NetworkService::request_justification(self, hash, number)
-    }
-
-    fn request_stale_block(&self, hash: B::Hash, number: NumberFor<B>) {
-        // The below comment is adapted from substrate:
-        // Notifies the sync service to try and sync the given block from the given peers. If the given vector
-        // of peers is empty (as in our case) then the underlying implementation should make a best effort to fetch
-        // the block from any peers it is connected to.
-        NetworkService::set_sync_fork_request(self, Vec::new(), hash, number)
-    }
-}
-
 impl<B: BlockT, H: ExHashT> Network<B> for Arc<NetworkService<B, H>> {
     fn event_stream(&self) -> Pin<Box<dyn Stream<Item = Event> + Send>> {
         Box::pin(NetworkService::event_stream(self, "network-gossip"))
@@ -317,17 +271,6 @@ enum SessionCommand<D: Clone + Encode + Decode> {
     Control(ControlCommand),
 }
 
-impl<D: Clone + Codec> SessionCommand<D> {
-    fn map<E: Clone + Codec, F: FnOnce(D) -> E>(self, f: F) -> SessionCommand<E> {
-        use SessionCommand::*;
-        match self {
-            Meta(message, recipient) => Meta(message, recipient),
-            Data(session_id, data, recipient) => Data(session_id, f(data), recipient),
-            Control(cc) => Control(cc),
-        }
-    }
-}
-
 pub(crate) struct SessionManager<D: Clone + Codec> {
     peer_id: PeerId,
     sessions: Arc<Mutex<HashMap<SessionId, SessionData<D>>>>,
@@ -676,18 +619,35 @@ where
     }
 }
 
+/// The data of a session, in the format of the legacy network.
 #[derive(Clone, Encode, Decode, Debug)]
 pub(crate) enum NetworkData<B: BlockT> {
     Aleph(AlephNetworkData<B>),
     Rmc(RmcNetworkData<B>),
+    RmcBls(RmcBlsNetworkData<B>),
 }
 
-pub(crate) struct DataNetwork<D: Clone + Codec> {
+/// Sends data to the members of a session.
+#[derive(Clone)]
+pub(crate) struct DataSender<D: Clone + Codec> {
     session_id: SessionId,
-    data_from_consensus_network: mpsc::UnboundedReceiver<D>,
     commands_for_consensus_network: mpsc::UnboundedSender<SessionCommand<D>>,
 }
 
+impl<D: Clone + Codec> DataSender<D> {
+    pub(crate) fn send(&self, data: D, recipient: Recipient<NodeIndex>) -> Result<(), Error> {
+        let sc = SessionCommand::Data(self.session_id, data, recipient);
+        self.commands_for_consensus_network
+            .unbounded_send(sc)
+            .map_err(|_| Error::SendData)
+    }
+}
+
+pub(crate) struct DataNetwork<D: Clone + Codec> {
+    sender: DataSender<D>,
+    data_from_consensus_network: mpsc::UnboundedReceiver<D>,
+}
+
 impl<D: Clone + Codec> DataNetwork<D> {
     fn new(
         session_id: SessionId,
@@ -695,132 +655,27 @@ impl<D: Clone + Codec> DataNetwork<D> {
         commands_for_consensus_network: mpsc::UnboundedSender<SessionCommand<D>>,
     ) -> Self {
         DataNetwork {
-            session_id,
+            sender: DataSender {
+                session_id,
+                commands_for_consensus_network,
+            },
             data_from_consensus_network,
-            commands_for_consensus_network,
         }
     }
 
+    // Sessions only use the parts, see `into_parts`.
+    #[cfg(test)]
     pub(crate) fn send(&self, data: D, recipient: Recipient<NodeIndex>) -> Result<(), Error> {
-        let sc = SessionCommand::Data(self.session_id, data, recipient);
-        self.commands_for_consensus_network
-            .unbounded_send(sc)
-            .map_err(|_| Error::SendData)
+        self.sender.send(data, recipient)
     }
 
+    #[cfg(test)]
     pub(crate) async fn next(&mut self) -> Option<D> {
         self.data_from_consensus_network.next().await
     }
-}
-
-pub(crate) struct AlephNetwork<B: BlockT> {
-    inner: DataNetwork<AlephNetworkData<B>>,
-}
-
-impl<B: BlockT> AlephNetwork<B> {
-    pub(crate) fn new(inner: DataNetwork<AlephNetworkData<B>>) -> Self {
-        AlephNetwork { inner }
-    }
-}
-
-#[async_trait::async_trait]
-impl<B: BlockT> aleph_bft::Network<Hasher, AlephDataFor<B>, Signature, SignatureSet<Signature>>
-    for AlephNetwork<B>
-{
-    fn send(&self, data: AlephNetworkData<B>, recipient: aleph_bft::Recipient) {
-        let recipient = recipient.into();
-        if self.inner.send(data, recipient).is_err() {
-            error!(target: "afa", "error sending a message to {:?}", recipient);
-        }
-    }
-
-    async fn next_event(&mut self) -> Option<AlephNetworkData<B>> {
-        self.inner.next().await
-    }
-}
-
-pub(crate) struct RmcNetwork<B: BlockT> {
-    inner: DataNetwork<RmcNetworkData<B>>,
-}
 
-impl<B: BlockT> RmcNetwork<B> {
-    pub(crate) fn new(inner: DataNetwork<RmcNetworkData<B>>) -> Self {
-        RmcNetwork { inner }
-    }
-    pub(crate) fn send(
-        &self,
-        data: RmcNetworkData<B>,
-        recipient: Recipient<NodeIndex>,
-    ) -> Result<(), Error> {
-        self.inner.send(data, recipient)
+    /// Separates the sending and the receiving end, so that they can be used independently.
+    pub(crate) fn into_parts(self) -> (DataSender<D>, mpsc::UnboundedReceiver<D>) {
+        (self.sender, self.data_from_consensus_network)
     }
-    pub(crate) async fn next(&mut self) -> Option<RmcNetworkData<B>> {
-        self.inner.next().await
-    }
-}
-
-pub(crate) fn split_network<B: BlockT>(
-    data_network: DataNetwork<NetworkData<B>>,
-    data_store_tx: mpsc::UnboundedSender<AlephNetworkData<B>>,
-    data_store_rx: mpsc::UnboundedReceiver<AlephNetworkData<B>>,
-) -> (AlephNetwork<B>, RmcNetwork<B>, impl Future<Output = ()>) {
-    let (rmc_data_tx, rmc_data_rx) = mpsc::unbounded();
-    let (aleph_cmd_tx, aleph_cmd_rx) = mpsc::unbounded();
-    let (rmc_cmd_tx, rmc_cmd_rx) = mpsc::unbounded();
-    let aleph_network = AlephNetwork::new(DataNetwork::new(
-        data_network.session_id,
-        data_store_rx,
-        aleph_cmd_tx,
-    ));
-    let rmc_network = RmcNetwork::new(DataNetwork::new(
-        data_network.session_id,
-        rmc_data_rx,
-        rmc_cmd_tx,
-    ));
-    let session_id = data_network.session_id;
-    let mut data_from_consensus_network = data_network.data_from_consensus_network;
-    let forward_data = async move {
-        loop {
-            match data_from_consensus_network.next().await {
-                None => break,
-                Some(NetworkData::Aleph(data)) => {
-                    trace!(target: "afa", "Forwarding a message to DataStore {:?} {:?}", session_id, data);
-                    if let Err(e) = data_store_tx.unbounded_send(data) {
-                        debug!(target: "afa", "unable to send data for {:?} to DataStore {}", session_id, e);
-                    }
-                }
-                Some(NetworkData::Rmc(data)) => {
-                    trace!(target: "afa", "Forwarding a message to rmc {:?} {:?}", session_id, data);
-                    if let Err(e) = rmc_data_tx.unbounded_send(data) {
-                        debug!(target: "afa", "unable to send data for {:?} to rmc network {}", session_id, e);
-                    }
-                }
-            }
-        }
-    };
-    let cmd_tx = data_network.commands_for_consensus_network;
-    let forward_aleph_cmd = {
-        let cmd_tx = cmd_tx.clone();
-        aleph_cmd_rx
-            .map(|cmd| Ok(cmd.map(NetworkData::Aleph)))
-            .forward(cmd_tx)
-            .map(|res| {
-                if let Err(e) = res {
-                    warn!(target: "afa", "error forwarding aleph commands: {}", e);
-                }
-            })
-    };
-    let forward_rmc_cmd = {
-        rmc_cmd_rx
-            .map(|cmd| Ok(cmd.map(NetworkData::Rmc)))
-            .forward(cmd_tx)
-            .map(|res| {
-                if let Err(e) = res {
-                    warn!(target: "afa", "error forwarding rmc commands: {}", e);
-                }
-            })
-    };
-    let forwards = futures::future::join3(forward_data, forward_aleph_cmd, forward_rmc_cmd)
-        .map(|((), (), ())| ());
-    (aleph_network, rmc_network, forwards)
 }
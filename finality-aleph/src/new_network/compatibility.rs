@@ -1,74 +1,126 @@
-// All this should be removed after the old network is no longer in use.
-// In particular I use the above to avoid worrying about the code duplication below.
-// It cannot easily be avoided, because it's kinda hard to make AlephNetwork and RmcNetwork
-// implement appropriate DataNetworks.
+// Only needed by the finality versions older than `FinalityVersion::NEW_NETWORK` (version 2),
+// which still talk over the legacy network. Delete this module together with `network.rs` and the
+// legacy protocols in `peers_set_configs` once every chain run by the node has switched to that
+// version.
 use crate::{
-    network::{AlephNetwork, RmcNetwork},
-    new_network::{AlephNetworkData, DataNetwork, RmcNetworkData, SendError},
+    network::{
+        DataNetwork as LegacyDataNetwork, DataSender as LegacyDataSender,
+        NetworkData as LegacyNetworkData,
+    },
+    new_network::{
+        ComponentNetwork, ReceiverComponent, SendError, SenderComponent, SessionData, Split,
+    },
 };
-use aleph_bft::{Network, Recipient};
+use aleph_bft::Recipient;
+use futures::channel::mpsc;
 use sp_runtime::traits::Block;
+use std::sync::Arc;
+use tokio::sync::Mutex;
 
-pub struct SplicedAlephNetwork<B: Block, DN: DataNetwork<AlephNetworkData<B>>> {
-    new_aleph_network: DN,
-    old_aleph_network: AlephNetwork<B>,
+impl<B: Block> From<LegacyNetworkData<B>> for SessionData<B> {
+    fn from(data: LegacyNetworkData<B>) -> Self {
+        match data {
+            LegacyNetworkData::Aleph(data) => Split::Left(data),
+            LegacyNetworkData::Rmc(data) => Split::Right(Split::Left(data)),
+            LegacyNetworkData::RmcBls(data) => Split::Right(Split::Right(data)),
+        }
+    }
 }
 
-impl<B: Block, DN: DataNetwork<AlephNetworkData<B>>> SplicedAlephNetwork<B, DN> {
-    pub(crate) fn new(new_aleph_network: DN, old_aleph_network: AlephNetwork<B>) -> Self {
-        SplicedAlephNetwork {
-            new_aleph_network,
-            old_aleph_network,
+impl<B: Block> From<SessionData<B>> for LegacyNetworkData<B> {
+    fn from(data: SessionData<B>) -> Self {
+        match data {
+            Split::Left(data) => LegacyNetworkData::Aleph(data),
+            Split::Right(Split::Left(data)) => LegacyNetworkData::Rmc(data),
+            Split::Right(Split::Right(data)) => LegacyNetworkData::RmcBls(data),
         }
     }
 }
 
-#[async_trait::async_trait]
-impl<B: Block, DN: DataNetwork<AlephNetworkData<B>>> DataNetwork<AlephNetworkData<B>>
-    for SplicedAlephNetwork<B, DN>
-{
-    fn send(&self, data: AlephNetworkData<B>, recipient: Recipient) -> Result<(), SendError> {
-        let _ = self.old_aleph_network.send(data.clone(), recipient.clone());
-        self.new_aleph_network.send(data, recipient)
-    }
+/// Sends data both using the new network and, if present, the legacy one.
+#[derive(Clone)]
+struct SplicedSender<B: Block, S: SenderComponent<SessionData<B>>> {
+    new_sender: S,
+    legacy_sender: Option<LegacyDataSender<LegacyNetworkData<B>>>,
+}
 
-    async fn next(&mut self) -> Option<AlephNetworkData<B>> {
-        tokio::select! {
-            data = self.old_aleph_network.next_event() => data,
-            data = self.new_aleph_network.next() => data,
+impl<B: Block, S: SenderComponent<SessionData<B>>> SenderComponent<SessionData<B>>
+    for SplicedSender<B, S>
+{
+    fn send(&self, data: SessionData<B>, recipient: Recipient) -> Result<(), SendError> {
+        if let Some(legacy_sender) = &self.legacy_sender {
+            // Peers still on the legacy network might only be reachable through it, but the new
+            // network is the one that has to work.
+            let _ = legacy_sender.send(data.clone().into(), recipient.clone().into());
         }
+        self.new_sender.send(data, recipient)
     }
 }
 
-pub struct SplicedRmcNetwork<B: Block, DN: DataNetwork<RmcNetworkData<B>>> {
-    new_rmc_network: DN,
-    old_rmc_network: RmcNetwork<B>,
+/// Receives data from the new network and, if present, the legacy one.
+struct SplicedReceiver<B: Block, R: ReceiverComponent<SessionData<B>>> {
+    new_receiver: Arc<Mutex<R>>,
+    legacy_receiver: Option<mpsc::UnboundedReceiver<LegacyNetworkData<B>>>,
 }
 
-impl<B: Block, DN: DataNetwork<RmcNetworkData<B>>> SplicedRmcNetwork<B, DN> {
-    pub(crate) fn new(new_rmc_network: DN, old_rmc_network: RmcNetwork<B>) -> Self {
-        SplicedRmcNetwork {
-            new_rmc_network,
-            old_rmc_network,
+#[async_trait::async_trait]
+impl<B: Block, R: ReceiverComponent<SessionData<B>>> ReceiverComponent<SessionData<B>>
+    for SplicedReceiver<B, R>
+{
+    async fn next(&mut self) -> Option<SessionData<B>> {
+        let mut new_receiver = self.new_receiver.lock().await;
+        match &mut self.legacy_receiver {
+            Some(legacy_receiver) => tokio::select! {
+                data = legacy_receiver.next() => data.map(|data| data.into()),
+                data = new_receiver.next() => data,
+            },
+            None => new_receiver.next().await,
         }
     }
 }
 
-#[async_trait::async_trait]
-impl<B: Block, DN: DataNetwork<RmcNetworkData<B>>> DataNetwork<RmcNetworkData<B>>
-    for SplicedRmcNetwork<B, DN>
+struct SplicedNetwork<
+    B: Block,
+    S: SenderComponent<SessionData<B>>,
+    R: ReceiverComponent<SessionData<B>>,
+> {
+    sender: SplicedSender<B, S>,
+    receiver: Arc<Mutex<SplicedReceiver<B, R>>>,
+}
+
+impl<B: Block, S: SenderComponent<SessionData<B>>, R: ReceiverComponent<SessionData<B>>>
+    ComponentNetwork<SessionData<B>> for SplicedNetwork<B, S, R>
 {
-    fn send(&self, data: RmcNetworkData<B>, recipient: Recipient) -> Result<(), SendError> {
-        let _ = self
-            .old_rmc_network
-            .send(data.clone(), recipient.clone().into());
-        self.new_rmc_network.send(data, recipient)
+    type S = SplicedSender<B, S>;
+    type R = SplicedReceiver<B, R>;
+    fn sender(&self) -> &Self::S {
+        &self.sender
     }
+    fn receiver(&self) -> Arc<Mutex<Self::R>> {
+        self.receiver.clone()
+    }
+}
 
-    async fn next(&mut self) -> Option<RmcNetworkData<B>> {
-        tokio::select! {
-            data = self.old_rmc_network.next() => data,
-            data = self.new_rmc_network.next() => data,
-        }
+/// Combine the network of a session with the legacy one, if the session still uses it. Data is
+/// sent using both of them and received from whichever delivers it, so the session works with
+/// peers that did not upgrade yet.
+pub(crate) fn splice<B: Block, CN: ComponentNetwork<SessionData<B>>>(
+    new_network: CN,
+    legacy_network: Option<LegacyDataNetwork<LegacyNetworkData<B>>>,
+) -> impl ComponentNetwork<SessionData<B>> {
+    let (legacy_sender, legacy_receiver) = match legacy_network.map(|network| network.into_parts())
+    {
+        Some((sender, receiver)) => (Some(sender), Some(receiver)),
+        None => (None, None),
+    };
+    SplicedNetwork {
+        sender: SplicedSender {
+            new_sender: new_network.sender().clone(),
+            legacy_sender,
+        },
+        receiver: Arc::new(Mutex::new(SplicedReceiver {
+            new_receiver: new_network.receiver(),
+            legacy_receiver,
+        })),
     }
 }
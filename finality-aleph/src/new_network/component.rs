@@ -1,6 +1,6 @@
 use crate::new_network::{Data, DataNetwork, SendError};
 use aleph_bft::Recipient;
-use futures::channel::mpsc;
+use futures::{channel::mpsc, StreamExt};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
@@ -36,6 +36,32 @@ impl<D: Data, CN: Network<D>> DataNetwork<D> for CN {
 #[async_trait::async_trait]
 impl<D: Data> Receiver<D> for mpsc::UnboundedReceiver<D> {
     async fn next(&mut self) -> Option<D> {
-        self.next().await
+        StreamExt::next(self).await
+    }
+}
+
+/// A network built from a sender and a receiving channel.
+pub struct SimpleNetwork<D: Data, S: Sender<D>> {
+    sender: S,
+    receiver: Arc<Mutex<mpsc::UnboundedReceiver<D>>>,
+}
+
+impl<D: Data, S: Sender<D>> SimpleNetwork<D, S> {
+    pub fn new(sender: S, receiver: mpsc::UnboundedReceiver<D>) -> Self {
+        SimpleNetwork {
+            sender,
+            receiver: Arc::new(Mutex::new(receiver)),
+        }
+    }
+}
+
+impl<D: Data, S: Sender<D>> Network<D> for SimpleNetwork<D, S> {
+    type S = S;
+    type R = mpsc::UnboundedReceiver<D>;
+    fn sender(&self) -> &Self::S {
+        &self.sender
+    }
+    fn receiver(&self) -> Arc<Mutex<Self::R>> {
+        self.receiver.clone()
     }
 }
@@ -17,7 +17,7 @@ use connections::Connections;
 use discovery::{Discovery, DiscoveryMessage};
 use session::{Handler as SessionHandler, HandlerError as SessionHandlerError};
 
-pub use service::SessionCommand;
+pub use service::{Service, SessionCommand, IO};
 
 /// A wrapper for the Substrate multiaddress to allow encoding & decoding.
 #[derive(Clone, Debug, PartialEq)]
@@ -372,6 +372,22 @@ pub enum Error {
 }
 
 impl<D: Data> IO<D> {
+    pub fn new(
+        commands_for_network: mpsc::UnboundedSender<ConnectionCommand>,
+        messages_for_network: mpsc::UnboundedSender<(NetworkData<D>, DataCommand)>,
+        commands_from_user: mpsc::UnboundedReceiver<SessionCommand<D>>,
+        messages_from_user: mpsc::UnboundedReceiver<(D, SessionId, Recipient)>,
+        messages_from_network: mpsc::UnboundedReceiver<NetworkData<D>>,
+    ) -> IO<D> {
+        IO {
+            commands_for_network,
+            messages_for_network,
+            commands_from_user,
+            messages_from_user,
+            messages_from_network,
+        }
+    }
+
     fn send_data(&self, to_send: (NetworkData<D>, DataCommand)) -> Result<(), Error> {
         self.messages_for_network
             .unbounded_send(to_send)
@@ -1,5 +1,3 @@
-// Everything here is dead code, but I don't want to create one enormous PR.
-#![allow(dead_code)]
 use aleph_bft::Recipient;
 use async_trait::async_trait;
 use codec::{Codec, Decode, Encode};
@@ -20,13 +18,23 @@ mod session;
 mod split;
 mod substrate;
 
-use component::{
+use manager::SessionCommand;
+
+pub use aleph::{NetworkData as AlephNetworkData, NetworkWrapper as AlephNetworkWrapper};
+pub use component::{
     Network as ComponentNetwork, Receiver as ReceiverComponent, Sender as SenderComponent,
+    SimpleNetwork,
 };
-use manager::SessionCommand;
+pub use manager::{Service as ConnectionManager, IO as ConnectionIO};
+pub use rmc::{BlsNetworkData as RmcBlsNetworkData, NetworkData as RmcNetworkData};
+pub use service::{Service, IO};
+pub use session::{Manager as SessionManager, ManagerError, Network as SessionNetwork};
+pub use split::{split, Split};
 
-pub use aleph::NetworkData as AlephNetworkData;
-pub use rmc::NetworkData as RmcNetworkData;
+/// The data sent within a single session, consisting of AlephBFT data and the data of both
+/// signature aggregators.
+pub type SessionData<B> =
+    Split<AlephNetworkData<B>, Split<RmcNetworkData<B>, RmcBlsNetworkData<B>>>;
 
 #[derive(PartialEq, Eq, Copy, Clone, Debug, Hash)]
 pub struct PeerId(pub(crate) ScPeerId);
@@ -157,6 +165,6 @@ pub trait DataNetwork<D: Data>: Send + Sync {
     async fn next(&mut self) -> Option<D>;
 }
 
-// This should be removed after compatibility with the old network is no longer needed.
+// Delete once every chain has switched to `FinalityVersion::NEW_NETWORK`, see the module.
 mod compatibility;
 pub use compatibility::*;
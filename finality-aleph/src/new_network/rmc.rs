@@ -1,6 +1,13 @@
-use crate::{aggregator::SignableHash, crypto::Signature};
+use crate::{
+    aggregator::SignableHash,
+    crypto::{BlsMultisignature, Signature},
+};
 use aleph_bft::{rmc::Message, SignatureSet};
+use aleph_primitives::BlsSignature;
 use sp_runtime::traits::Block;
 
 pub type NetworkData<B> =
     Message<SignableHash<<B as Block>::Hash>, Signature, SignatureSet<Signature>>;
+
+pub type BlsNetworkData<B> =
+    Message<SignableHash<<B as Block>::Hash>, BlsSignature, BlsMultisignature>;
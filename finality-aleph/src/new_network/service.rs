@@ -11,7 +11,14 @@ use std::{
     iter,
 };
 
-struct Service<N: Network, D: Data> {
+/// A service managing all the direct interaction with the underlying network implementation. It
+/// handles:
+/// 1. Incoming network events
+///   1. Messages are forwarded to the user.
+///   2. Various forms of (dis)connecting, keeping track of all currently connected nodes.
+/// 2. Commands from the network manager, modifying the reserved peer set.
+/// 3. Outgoing messages, sending them out, using 1.2. to broadcast.
+pub struct Service<N: Network, D: Data> {
     network: N,
     messages_from_user: mpsc::UnboundedReceiver<(D, DataCommand)>,
     messages_for_user: mpsc::UnboundedSender<D>,
@@ -20,12 +27,27 @@ struct Service<N: Network, D: Data> {
     to_send: VecDeque<(D, PeerId, Protocol)>,
 }
 
+/// Input/output channels for the network service.
 pub struct IO<D: Data> {
     messages_from_user: mpsc::UnboundedReceiver<(D, DataCommand)>,
     messages_for_user: mpsc::UnboundedSender<D>,
     commands_from_manager: mpsc::UnboundedReceiver<ConnectionCommand>,
 }
 
+impl<D: Data> IO<D> {
+    pub fn new(
+        messages_from_user: mpsc::UnboundedReceiver<(D, DataCommand)>,
+        messages_for_user: mpsc::UnboundedSender<D>,
+        commands_from_manager: mpsc::UnboundedReceiver<ConnectionCommand>,
+    ) -> IO<D> {
+        IO {
+            messages_from_user,
+            messages_for_user,
+            commands_from_manager,
+        }
+    }
+}
+
 impl<N: Network, D: Data> Service<N, D> {
     pub fn new(network: N, io: IO<D>) -> Service<N, D> {
         let IO {
@@ -1,28 +1,45 @@
 use crate::{
-    aggregator::BlockSignatureAggregator,
-    crypto::{AuthorityPen, AuthorityVerifier, KeyBox},
+    aggregator::{BlockSignatureAggregator, RmcMessage},
+    aux_schema::{self, Progress},
+    crypto::{
+        checked_bls_keys, AuthorityPen, AuthorityVerifier, BlsKeyBox, BlsKeystore, BlsPen, KeyBox,
+        SigningDomain, SigningPurpose,
+    },
     data_io::{
         reduce_header_to_num, refresh_best_chain, AlephData, AlephDataFor, DataProvider, DataStore,
     },
     default_aleph_config,
+    equivocation::{run_equivocation_detector, run_equivocation_reporter, EquivocationDetector},
     finalization::should_finalize,
+    import::{
+        find_next_session_authorities, find_next_session_bls_keys,
+        find_next_session_finality_version,
+    },
     justification::{
-        AlephJustification, JustificationHandler, JustificationNotification,
+        AlephJustification, IntoJustification, JustificationHandler, JustificationNotification,
         JustificationRequestDelay, SessionInfo, SessionInfoProvider,
     },
     last_block_of_session,
     metrics::Checkpoint,
     network,
     network::{
-        split_network, AlephNetworkData, ConsensusNetwork, DataNetwork, NetworkData, SessionManager,
+        ConsensusNetwork, DataNetwork as LegacyDataNetwork, NetworkData as LegacyNetworkData,
+        SessionManager as LegacySessionManager,
     },
-    session_id_from_block_num, AuthorityId, Future, Metrics, MillisecsPerBlock, NodeIndex,
-    SessionId, SessionMap, SessionPeriod, UnitCreationDelay,
+    new_network,
+    new_network::{
+        splice, split, AlephNetworkData, AlephNetworkWrapper, ComponentNetwork, ConnectionIO,
+        ConnectionManager, DataNetwork, SessionData, SessionManager, SessionNetwork, SimpleNetwork,
+    },
+    session_api, session_id_from_block_num, AlephTuning, AuthorityId, ConsensusTuning,
+    FinalityVersion, Future, Metrics, NodeIndex, SessionId, SessionMap, SessionSchedule,
 };
 use sp_keystore::CryptoStore;
 
-use aleph_bft::{DelayConfig, SpawnHandle};
-use aleph_primitives::{AlephSessionApi, KEY_TYPE};
+use aleph_bft::{DelayConfig, MultiKeychain, SpawnHandle};
+use aleph_primitives::{
+    AlephEquivocationProof, AlephSessionApi, BlsPublic, ConsensusParams, KEY_TYPE,
+};
 use futures_timer::Delay;
 
 use futures::{
@@ -37,8 +54,8 @@ use crate::finalization::{AlephFinalizer, BlockFinalizer};
 use crate::justification::{JustificationHandlerConfig, Verifier};
 use codec::Encode;
 use parking_lot::Mutex;
-use sc_client_api::{Backend, HeaderBackend};
-use sp_api::{BlockId, NumberFor};
+use sc_client_api::{AuxStore, Backend, BlockchainEvents, HeaderBackend};
+use sp_api::{BlockId, NumberFor, ProvideRuntimeApi};
 use sp_consensus::SelectChain;
 use sp_runtime::{
     traits::{Block, Header},
@@ -54,34 +71,66 @@ use std::{
     time::Duration,
 };
 
+/// The checked BLS keys of the sessions signing with them.
+type SessionBlsKeys = HashMap<SessionId, Vec<BlsPublic>>;
+/// The finality versions of the sessions, deciding the format of their justifications.
+type SessionFinalityVersions = HashMap<SessionId, FinalityVersion>;
+
 pub struct AlephParams<B: Block, N, C, SC> {
     pub config: crate::AlephConfig<B, N, C, SC>,
 }
 
-struct JustificationRequestDelayImpl {
+struct JustificationRequestDelayImpl<B, C> {
     last_request_time: Instant,
     last_finalization_time: Instant,
-    delay: Duration,
+    session_schedule: Arc<Mutex<SessionSchedule>>,
+    client: Arc<C>,
+    phantom: PhantomData<B>,
 }
 
-impl JustificationRequestDelayImpl {
-    fn new(session_period: &SessionPeriod, millisecs_per_block: &MillisecsPerBlock) -> Self {
+impl<B, C> JustificationRequestDelayImpl<B, C>
+where
+    B: Block,
+    C: HeaderBackend<B>,
+{
+    fn new(session_schedule: Arc<Mutex<SessionSchedule>>, client: Arc<C>) -> Self {
         Self {
             last_request_time: Instant::now(),
             last_finalization_time: Instant::now(),
-            delay: Duration::from_millis(min(
-                millisecs_per_block.0 * 2,
-                millisecs_per_block.0 * session_period.0 as u64 / 10,
-            )),
+            session_schedule,
+            client,
+            phantom: PhantomData,
         }
     }
+
+    fn delay(&self) -> Duration {
+        // The session period and block time in effect for the block we are waiting to finalize.
+        let next_block = self
+            .client
+            .info()
+            .finalized_number
+            .saturated_into::<u32>()
+            .saturating_add(1);
+        let change = {
+            let schedule = self.session_schedule.lock();
+            *schedule.for_session(schedule.session_of_block(next_block))
+        };
+        Duration::from_millis(min(
+            change.millisecs_per_block * 2,
+            change.millisecs_per_block * change.session_period as u64 / 10,
+        ))
+    }
 }
 
-impl JustificationRequestDelay for JustificationRequestDelayImpl {
+impl<B, C> JustificationRequestDelay for JustificationRequestDelayImpl<B, C>
+where
+    B: Block,
+    C: HeaderBackend<B>,
+{
     fn can_request_now(&self) -> bool {
         let now = Instant::now();
-        now - self.last_finalization_time > self.delay
-            && now - self.last_request_time > 2 * self.delay
+        let delay = self.delay();
+        now - self.last_finalization_time > delay && now - self.last_request_time > 2 * delay
     }
 
     fn on_block_finalized(&mut self) {
@@ -95,7 +144,7 @@ impl JustificationRequestDelay for JustificationRequestDelayImpl {
 
 impl<B: Block> Verifier<B> for AuthorityVerifier {
     fn verify(&self, justification: &AlephJustification, hash: B::Hash) -> bool {
-        if !self.is_complete(&hash.encode()[..], &justification.signature) {
+        if !self.verify_justification(&hash.encode()[..], justification) {
             warn!(target: "afa", "Bad justification for block hash #{:?} {:?}", hash, justification);
             return false;
         }
@@ -103,17 +152,271 @@ impl<B: Block> Verifier<B> for AuthorityVerifier {
     }
 }
 
-fn get_session_info_provider<B: Block>(
-    session_authorities: Arc<Mutex<HashMap<SessionId, Vec<AuthorityId>>>>,
-    session_period: SessionPeriod,
-) -> impl SessionInfoProvider<B, AuthorityVerifier> {
+/// Returns the authorities of the session if they are already known, either in memory or persisted
+/// in the aux storage, otherwise reads them from the history kept by the runtime at the last
+/// finalized block.
+pub(crate) fn get_session_authorities<B, C>(
+    session_authorities: &Mutex<SessionMap>,
+    client: &C,
+    session_id: SessionId,
+) -> Option<Vec<AuthorityId>>
+where
+    B: Block,
+    C: HeaderBackend<B> + ProvideRuntimeApi<B> + AuxStore,
+    C::Api: AlephSessionApi<B>,
+{
+    if let Some(authorities) = session_authorities.lock().get(&session_id) {
+        return Some(authorities.clone());
+    }
+    match aux_schema::load_session_authorities(client, session_id) {
+        Ok(Some(authorities)) => {
+            session_authorities
+                .lock()
+                .insert(session_id, authorities.clone());
+            return Some(authorities);
+        }
+        Ok(None) => {}
+        Err(e) => {
+            warn!(target: "afa", "Error when loading persisted authorities of session {:?} {:?}", session_id, e)
+        }
+    }
+    let finalized_hash = client.info().finalized_hash;
+    match session_api::authorities_for_session(client, &BlockId::Hash(finalized_hash), session_id.0)
+    {
+        Ok(authorities) => authorities,
+        Err(e) => {
+            debug!(target: "afa", "Error when reading historical authorities of session {:?} {:?}", session_id, e);
+            None
+        }
+    }
+}
+
+/// Returns the header of the block announcing the authorities of the session, which is the last
+/// block of the preceding session, or the genesis block for the first session.
+fn announcing_header<B, C>(
+    client: &C,
+    session_schedule: &SessionSchedule,
+    session_id: SessionId,
+) -> Option<B::Header>
+where
+    B: Block,
+    C: HeaderBackend<B>,
+{
+    let announcing_block = match session_id {
+        SessionId(0) => <NumberFor<B>>::saturated_from(0u32),
+        _ => last_block_of_session::<B>(SessionId(session_id.0 - 1), session_schedule),
+    };
+    match client.header(BlockId::Number(announcing_block)) {
+        Ok(Some(header)) => Some(header),
+        _ => {
+            debug!(target: "afa", "Cannot read the announcement of session {:?}, block {:?} is unknown.", session_id, announcing_block);
+            None
+        }
+    }
+}
+
+/// Reads the BLS keys of the session if it signs with them, as announced in the last block of the
+/// preceding session. Sessions without an announcement, like the first one, sign with the keys
+/// recorded by the runtime at that block. Sessions without keys sign with ed25519 keys.
+pub(crate) fn read_session_bls_keys<B, C>(
+    client: &C,
+    session_schedule: &SessionSchedule,
+    session_id: SessionId,
+) -> Option<Vec<BlsPublic>>
+where
+    B: Block,
+    C: HeaderBackend<B> + ProvideRuntimeApi<B>,
+    C::Api: AlephSessionApi<B>,
+{
+    let header = announcing_header(client, session_schedule, session_id)?;
+    let registrations = match (
+        find_next_session_authorities::<B>(&header),
+        find_next_session_bls_keys::<B>(&header),
+    ) {
+        (_, Ok(Some((announced_session, keys)))) if announced_session == session_id => keys,
+        (Ok(Some((announced_session, _))), _) if announced_session == session_id => return None,
+        _ => match session_api::bls_keys_for_session(
+            client,
+            &BlockId::Hash(header.hash()),
+            session_id.0,
+        ) {
+            Ok(registrations) => registrations?,
+            Err(e) => {
+                debug!(target: "afa", "Error when reading BLS keys of session {:?} {:?}", session_id, e);
+                return None;
+            }
+        },
+    };
+    let bls_keys = checked_bls_keys(registrations);
+    if bls_keys.is_none() {
+        // Every node rejects such keys the same way, so the session signs with ed25519 keys.
+        warn!(target: "afa", "Invalid BLS keys recorded for session {:?}, using ed25519 keys instead", session_id);
+    }
+    bls_keys
+}
+
+/// Returns the BLS keys of the session if they are already known, otherwise reads them with
+/// `read_session_bls_keys`.
+pub(crate) fn get_session_bls_keys<B, C>(
+    session_bls_keys: &Mutex<SessionBlsKeys>,
+    client: &C,
+    session_schedule: &SessionSchedule,
+    session_id: SessionId,
+) -> Option<Vec<BlsPublic>>
+where
+    B: Block,
+    C: HeaderBackend<B> + ProvideRuntimeApi<B>,
+    C::Api: AlephSessionApi<B>,
+{
+    if let Some(bls_keys) = session_bls_keys.lock().get(&session_id) {
+        return Some(bls_keys.clone());
+    }
+    let bls_keys = read_session_bls_keys(client, session_schedule, session_id)?;
+    session_bls_keys.lock().insert(session_id, bls_keys.clone());
+    Some(bls_keys)
+}
+
+/// Reads the finality version of the session, as announced in the last block of the preceding
+/// session. Sessions without an announcement, like the first one, run the version returned by the
+/// runtime at that block.
+pub(crate) fn read_session_finality_version<B, C>(
+    client: &C,
+    session_schedule: &SessionSchedule,
+    session_id: SessionId,
+) -> Option<FinalityVersion>
+where
+    B: Block,
+    C: HeaderBackend<B> + ProvideRuntimeApi<B>,
+    C::Api: AlephSessionApi<B>,
+{
+    let header = announcing_header(client, session_schedule, session_id)?;
+    match find_next_session_finality_version::<B>(&header) {
+        Ok(Some((announced_session, version))) if announced_session == session_id => Some(version),
+        _ => {
+            match session_api::finality_version(client, &BlockId::Hash(header.hash()), session_id.0)
+            {
+                Ok(version) => Some(version),
+                Err(e) => {
+                    debug!(target: "afa", "Error when reading the finality version of session {:?} {:?}", session_id, e);
+                    None
+                }
+            }
+        }
+    }
+}
+
+/// Returns the finality version of the session if it is already known, otherwise reads it with
+/// `read_session_finality_version`.
+fn get_session_finality_version<B, C>(
+    session_finality_versions: &Mutex<SessionFinalityVersions>,
+    client: &C,
+    session_schedule: &SessionSchedule,
+    session_id: SessionId,
+) -> Option<FinalityVersion>
+where
+    B: Block,
+    C: HeaderBackend<B> + ProvideRuntimeApi<B>,
+    C::Api: AlephSessionApi<B>,
+{
+    if let Some(version) = session_finality_versions.lock().get(&session_id) {
+        return Some(*version);
+    }
+    let version = read_session_finality_version(client, session_schedule, session_id)?;
+    session_finality_versions.lock().insert(session_id, version);
+    Some(version)
+}
+
+/// Returns the authorities of the session, persisted in the aux storage if the session was already
+/// started before, otherwise read from the runtime and persisted.
+fn read_session_authorities<B, C>(
+    client: &C,
+    session_schedule: &SessionSchedule,
+    session_id: SessionId,
+) -> Option<Vec<AuthorityId>>
+where
+    B: Block,
+    C: HeaderBackend<B> + ProvideRuntimeApi<B> + AuxStore,
+    C::Api: AlephSessionApi<B>,
+{
+    let persisted = aux_schema::load_session_authorities(client, session_id).unwrap_or_else(|e| {
+        warn!(target: "afa", "Error when loading persisted authorities of session {:?} {:?}", session_id, e);
+        None
+    });
+    if let Some(authorities) = persisted {
+        return Some(authorities);
+    }
+    let authorities = if session_id == SessionId(0) {
+        client
+            .runtime_api()
+            .authorities(&BlockId::Number(<NumberFor<B>>::saturated_from(0u32)))
+            .unwrap()
+    } else {
+        let last_prev = last_block_of_session::<B>(SessionId(session_id.0 - 1), session_schedule);
+        // We must read the authorities for next session of the latest block of the previous session.
+        // The reason is that we are not guaranteed to have the first block of new session available yet.
+        match client
+            .runtime_api()
+            .next_session_authorities(&BlockId::Number(last_prev))
+        {
+            Ok(authorities) => authorities
+                .expect("authorities must be available at last block of previous session"),
+            Err(e) => {
+                error!(target: "afa", "Error when getting authorities for session {:?} {:?}", session_id, e);
+                return None;
+            }
+        }
+    };
+    if let Err(e) = aux_schema::write_session_authorities(client, session_id, &authorities) {
+        warn!(target: "afa", "Error when persisting authorities of session {:?} {:?}", session_id, e);
+    }
+    Some(authorities)
+}
+
+fn get_session_info_provider<B, C>(
+    session_authorities: Arc<Mutex<SessionMap>>,
+    session_bls_keys: Arc<Mutex<SessionBlsKeys>>,
+    session_finality_versions: Arc<Mutex<SessionFinalityVersions>>,
+    session_schedule: Arc<Mutex<SessionSchedule>>,
+    client: Arc<C>,
+) -> impl SessionInfoProvider<B, AuthorityVerifier>
+where
+    B: Block,
+    C: HeaderBackend<B> + ProvideRuntimeApi<B> + AuxStore,
+    C::Api: AlephSessionApi<B>,
+{
     move |block_num| {
-        let current_session = session_id_from_block_num::<B>(block_num, session_period);
-        let last_block_height = last_block_of_session::<B>(current_session, session_period);
-        let verifier = session_authorities
-            .lock()
-            .get(&current_session)
-            .map(|sa: &Vec<AuthorityId>| AuthorityVerifier::new(sa.to_vec()));
+        let session_schedule = session_schedule.lock().clone();
+        let current_session = session_id_from_block_num::<B>(block_num, &session_schedule);
+        let last_block_height = last_block_of_session::<B>(current_session, &session_schedule);
+        let authorities =
+            get_session_authorities(&session_authorities, client.as_ref(), current_session);
+        let finality_version = get_session_finality_version(
+            &session_finality_versions,
+            client.as_ref(),
+            &session_schedule,
+            current_session,
+        );
+        let verifier = match (authorities, finality_version) {
+            (Some(authorities), Some(finality_version)) => {
+                let verifier = AuthorityVerifier::new(authorities).for_session(
+                    client.info().genesis_hash.encode(),
+                    current_session,
+                    finality_version,
+                );
+                Some(
+                    match get_session_bls_keys(
+                        &session_bls_keys,
+                        client.as_ref(),
+                        &session_schedule,
+                        current_session,
+                    ) {
+                        Some(bls_keys) => verifier.with_bls_keys(bls_keys),
+                        None => verifier,
+                    },
+                )
+            }
+            _ => None,
+        };
 
         SessionInfo {
             current_session,
@@ -126,7 +429,11 @@ fn get_session_info_provider<B: Block>(
 pub async fn run_consensus_party<B, N, C, BE, SC>(aleph_params: AlephParams<B, N, C, SC>)
 where
     B: Block,
-    N: network::Network<B> + network::RequestBlocks<B> + 'static,
+    N: network::Network<B>
+        + new_network::Network
+        + new_network::NetworkIdentity
+        + new_network::RequestBlocks<B>
+        + 'static,
     C: crate::ClientForAleph<B, BE> + Send + Sync + 'static,
     C::Api: aleph_primitives::AlephSessionApi<B>,
     BE: Backend<B> + 'static,
@@ -140,60 +447,128 @@ where
                 select_chain,
                 spawn_handle,
                 keystore,
+                bls_keystore,
                 justification_rx,
+                justification_sender,
                 metrics,
-                session_period,
-                millisecs_per_block,
-                unit_creation_delay,
+                session_schedule,
+                tuning,
                 ..
             },
     } = aleph_params;
+    info!(target: "afa", "Running with tuning {:?}", tuning);
 
     let session_authorities = Arc::new(Mutex::new(HashMap::new()));
+    let session_bls_keys = Arc::new(Mutex::new(HashMap::new()));
+    let session_finality_versions = Arc::new(Mutex::new(HashMap::new()));
+    let session_schedule = Arc::new(Mutex::new(session_schedule));
     let block_requester = network.clone();
+    let (conflicting_justification_tx, conflicting_justification_rx) = mpsc::unbounded();
+    let (equivocation_proofs_tx, equivocation_proofs_rx) = mpsc::unbounded();
 
     let handler = JustificationHandler::new(
-        get_session_info_provider(session_authorities.clone(), session_period),
+        get_session_info_provider(
+            session_authorities.clone(),
+            session_bls_keys.clone(),
+            session_finality_versions.clone(),
+            session_schedule.clone(),
+            client.clone(),
+        ),
         block_requester.clone(),
         client.clone(),
         AlephFinalizer::new(client.clone()),
         JustificationHandlerConfig {
             justification_request_delay: JustificationRequestDelayImpl::new(
-                &session_period,
-                &millisecs_per_block,
+                session_schedule.clone(),
+                client.clone(),
             ),
             metrics: metrics.clone(),
-            verifier_timeout: Duration::from_millis(500),
-            notification_timeout: Duration::from_millis(1000),
+            verifier_timeout: Duration::from_millis(tuning.justification.verifier_timeout_ms),
+            notification_timeout: Duration::from_millis(
+                tuning.justification.notification_timeout_ms,
+            ),
+            conflicting_justification_tx: Some(conflicting_justification_tx),
+            justification_sender: Some(justification_sender),
+            verified_cache_capacity: tuning.justification.verified_cache_capacity,
         },
     );
 
     let authority_justification_tx =
         run_justification_handler(handler, &spawn_handle.clone().into(), justification_rx);
 
-    // Prepare and start the network
-    let network =
-        ConsensusNetwork::<NetworkData<B>, _, _>::new(network.clone(), "/cardinals/aleph/1".into());
-    let session_manager = network.session_manager();
+    let equivocation_reporter_task = run_equivocation_reporter(
+        client.clone(),
+        session_authorities.clone(),
+        session_schedule.clone(),
+        conflicting_justification_rx,
+        equivocation_proofs_rx,
+    );
+    spawn_handle.spawn("aleph/equivocation_reporter", equivocation_reporter_task);
+
+    // Prepare and start the network and the connection manager on top of it.
+    let (commands_for_network, commands_from_manager) = mpsc::unbounded();
+    let (messages_for_network, messages_from_manager) = mpsc::unbounded();
+    let (messages_for_manager, messages_from_network) = mpsc::unbounded();
+    let (commands_for_manager, commands_from_user) = mpsc::unbounded();
+    let (messages_for_service, messages_from_user) = mpsc::unbounded();
+    let network_service = new_network::Service::new(
+        network.clone(),
+        new_network::IO::new(
+            messages_from_manager,
+            messages_for_manager,
+            commands_from_manager,
+        ),
+    );
+    let connection_manager = ConnectionManager::new(network.clone());
+    let connection_io = ConnectionIO::new(
+        commands_for_network,
+        messages_for_network,
+        commands_from_user,
+        messages_from_user,
+        messages_from_network,
+    );
+    let session_manager = SessionManager::new(commands_for_manager, messages_for_service);
+    spawn_handle.spawn("aleph/network", network_service.run());
+    spawn_handle.spawn("aleph/connection_manager", async move {
+        if let Err(e) = connection_io.run(connection_manager).await {
+            error!(target: "afa", "Connection manager stopped with an error {:?}", e);
+        }
+    });
 
-    let network_task = async move { network.run().await };
-    spawn_handle.spawn("aleph/network", network_task);
+    // Sessions of finality versions predating the new network also talk over the legacy one, every
+    // such version over its own protocol, so that nodes that did not upgrade yet can take part.
+    let mut legacy_session_managers = HashMap::new();
+    for version in FinalityVersion::known().filter(|version| version.uses_legacy_network()) {
+        let consensus_network = ConsensusNetwork::<LegacyNetworkData<B>, _, _>::new(
+            network.clone(),
+            network::protocol_name(version),
+        );
+        legacy_session_managers.insert(version, consensus_network.session_manager());
+        let network_task = async move { consensus_network.run().await };
+        spawn_handle.spawn("aleph/legacy_network", network_task);
+    }
 
     debug!(target: "afa", "Consensus network has started.");
 
     let party = ConsensusParty {
         session_manager,
+        legacy_session_managers,
+        genesis_hash: client.info().genesis_hash,
         client,
         keystore,
+        bls_keystore,
         select_chain,
         block_requester,
         metrics,
         authority_justification_tx,
+        equivocation_proofs_tx,
         session_authorities,
-        session_period,
+        session_bls_keys,
+        session_finality_versions,
+        session_schedule,
         spawn_handle: spawn_handle.into(),
         phantom: PhantomData,
-        unit_creation_delay,
+        tuning,
     };
 
     debug!(target: "afa", "Consensus party has started.");
@@ -201,6 +576,115 @@ where
     error!(target: "afa", "Consensus party has finished unexpectedly.");
 }
 
+/// Runs the justification handler alone, on nodes that never take part in sessions, so that
+/// they finalize the blocks they import and publish their justifications. The authorities of
+/// every session are read once the previous session is finalized.
+pub async fn run_nonvalidator_node<B, N, C, BE, SC>(aleph_params: AlephParams<B, N, C, SC>)
+where
+    B: Block,
+    N: new_network::RequestBlocks<B> + 'static,
+    C: crate::ClientForAleph<B, BE> + Send + Sync + 'static,
+    C::Api: aleph_primitives::AlephSessionApi<B>,
+    BE: Backend<B> + 'static,
+{
+    let AlephParams {
+        config:
+            crate::AlephConfig {
+                network,
+                client,
+                spawn_handle,
+                justification_rx,
+                justification_sender,
+                metrics,
+                session_schedule,
+                tuning,
+                ..
+            },
+    } = aleph_params;
+
+    let session_authorities = Arc::new(Mutex::new(HashMap::new()));
+    let session_bls_keys = Arc::new(Mutex::new(HashMap::new()));
+    let session_finality_versions = Arc::new(Mutex::new(HashMap::new()));
+    let session_schedule = Arc::new(Mutex::new(session_schedule));
+
+    let handler = JustificationHandler::new(
+        get_session_info_provider(
+            session_authorities.clone(),
+            session_bls_keys.clone(),
+            session_finality_versions.clone(),
+            session_schedule.clone(),
+            client.clone(),
+        ),
+        network,
+        client.clone(),
+        AlephFinalizer::new(client.clone()),
+        JustificationHandlerConfig {
+            justification_request_delay: JustificationRequestDelayImpl::new(
+                session_schedule.clone(),
+                client.clone(),
+            ),
+            metrics,
+            verifier_timeout: Duration::from_millis(tuning.justification.verifier_timeout_ms),
+            notification_timeout: Duration::from_millis(
+                tuning.justification.notification_timeout_ms,
+            ),
+            conflicting_justification_tx: None,
+            justification_sender: Some(justification_sender),
+            verified_cache_capacity: tuning.justification.verified_cache_capacity,
+        },
+    );
+    // Nothing is ever sent here, but the handler stops once either of its channels is closed.
+    let _authority_justification_tx =
+        run_justification_handler(handler, &spawn_handle.into(), justification_rx);
+    debug!(target: "afa", "Non-validator node has started.");
+
+    let mut finality_notifications = client.finality_notification_stream();
+    let schedule = session_schedule.lock().clone();
+    let mut session_id =
+        session_id_from_block_num::<B>(client.info().finalized_number + 1u32.into(), &schedule);
+    loop {
+        let schedule = session_schedule.lock().clone();
+        match read_session_authorities(client.as_ref(), &schedule, session_id) {
+            Some(authorities) => {
+                session_authorities.lock().insert(session_id, authorities);
+            }
+            None => {
+                Delay::new(Duration::from_millis(
+                    tuning.justification.verifier_timeout_ms,
+                ))
+                .await;
+                continue;
+            }
+        }
+        if session_id.0 >= 10 && session_id.0 % 10 == 0 {
+            let prune_below = SessionId(session_id.0 - 10);
+            debug!(target: "afa", "Pruning session data below {:?}.", prune_below);
+            session_authorities.lock().retain(|&s, _| s >= prune_below);
+            session_bls_keys.lock().retain(|&s, _| s >= prune_below);
+            session_finality_versions
+                .lock()
+                .retain(|&s, _| s >= prune_below);
+        }
+
+        let last_block = last_block_of_session::<B>(session_id, &schedule);
+        while client.info().finalized_number < last_block {
+            if finality_notifications.next().await.is_none() {
+                error!(target: "afa", "Finality notification stream ended, stopping the non-validator node.");
+                return;
+            }
+        }
+        // Changes of the session period are scheduled before the session they affect starts, so
+        // the state at the end of the session determines the next one.
+        match session_api::session_schedule(client.as_ref(), &BlockId::Number(last_block)) {
+            Ok(schedule) => *session_schedule.lock() = schedule,
+            Err(e) => {
+                warn!(target: "afa", "Error when getting session schedule at block {:?}, keeping the previous one {:?}", last_block, e)
+            }
+        }
+        session_id = SessionId(session_id.0 + 1);
+    }
+}
+
 async fn get_node_index(
     authorities: &[AuthorityId],
     keystore: Arc<dyn CryptoStore>,
@@ -222,7 +706,7 @@ fn run_justification_handler<B, V, RB, C, D, SI, F>(
 where
     C: HeaderBackend<B> + Send + Sync + 'static,
     B: Block,
-    RB: network::RequestBlocks<B> + 'static,
+    RB: new_network::RequestBlocks<B> + 'static,
     V: Verifier<B> + Send + 'static,
     D: JustificationRequestDelay + Send + 'static,
     SI: SessionInfoProvider<B, V> + Send + 'static,
@@ -247,28 +731,37 @@ where
     C::Api: aleph_primitives::AlephSessionApi<B>,
     BE: Backend<B> + 'static,
     SC: SelectChain<B> + 'static,
-    RB: network::RequestBlocks<B> + 'static,
+    RB: new_network::RequestBlocks<B> + 'static,
 {
-    session_manager: SessionManager<NetworkData<B>>,
+    session_manager: SessionManager<SessionData<B>>,
+    legacy_session_managers: HashMap<FinalityVersion, LegacySessionManager<LegacyNetworkData<B>>>,
     session_authorities: Arc<Mutex<SessionMap>>,
-    session_period: SessionPeriod,
+    session_bls_keys: Arc<Mutex<SessionBlsKeys>>,
+    session_finality_versions: Arc<Mutex<SessionFinalityVersions>>,
+    session_schedule: Arc<Mutex<SessionSchedule>>,
     spawn_handle: crate::SpawnHandle,
     client: Arc<C>,
     select_chain: SC,
     keystore: Arc<dyn CryptoStore>,
+    bls_keystore: BlsKeystore,
     block_requester: RB,
     phantom: PhantomData<BE>,
     metrics: Option<Metrics<<B::Header as Header>::Hash>>,
     authority_justification_tx: mpsc::UnboundedSender<JustificationNotification<B>>,
-    unit_creation_delay: UnitCreationDelay,
+    equivocation_proofs_tx: mpsc::UnboundedSender<AlephEquivocationProof<B::Header>>,
+    tuning: AlephTuning,
+    genesis_hash: B::Hash,
 }
 
-async fn run_aggregator<B, C, BE>(
-    mut aggregator: BlockSignatureAggregator<'_, B, KeyBox>,
+async fn run_aggregator<B, C, BE, N, MK>(
+    mut aggregator: BlockSignatureAggregator<'_, B, N, MK>,
     mut ordered_units_rx: mpsc::UnboundedReceiver<AlephDataFor<B>>,
     justification_tx: mpsc::UnboundedSender<JustificationNotification<B>>,
     client: Arc<C>,
+    session_id: SessionId,
+    finality_version: FinalityVersion,
     last_block_in_session: NumberFor<B>,
+    last_aggregated: Option<B::Hash>,
     metrics: Option<Metrics<<B::Header as Header>::Hash>>,
     mut exit_rx: futures::channel::oneshot::Receiver<()>,
 ) where
@@ -276,8 +769,26 @@ async fn run_aggregator<B, C, BE>(
     C: crate::ClientForAleph<B, BE> + Send + Sync + 'static,
     C::Api: aleph_primitives::AlephSessionApi<B>,
     BE: Backend<B> + 'static,
+    N: DataNetwork<RmcMessage<B, MK>>,
+    MK: MultiKeychain,
+    MK::PartialMultisignature: IntoJustification,
 {
-    let mut last_finalized = client.info().finalized_hash;
+    let finalized_number = client.info().finalized_number;
+    // After a restart we do not aggregate signatures of the blocks we already did, unless it was the
+    // last block of the session, as no later block would finalize it.
+    let mut last_finalized = match last_aggregated.and_then(|hash| {
+        client
+            .number(hash)
+            .ok()
+            .flatten()
+            .map(|number| (hash, number))
+    }) {
+        Some((hash, number)) if number > finalized_number && number < last_block_in_session => {
+            debug!(target: "afa", "Resuming the aggregation after block {:?}.", number);
+            hash
+        }
+        _ => client.info().finalized_hash,
+    };
     let mut last_block_seen = false;
     loop {
         tokio::select! {
@@ -294,6 +805,13 @@ async fn run_aggregator<B, C, BE>(
                     if let Some(data) = should_finalize(last_finalized, new_block_data, client.as_ref(), last_block_in_session) {
                         aggregator.start_aggregation(data.hash).await;
                         last_finalized = data.hash;
+                        let progress = Progress {
+                            last_started_session: session_id,
+                            last_aggregated_hash: Some(data.hash),
+                        };
+                        if let Err(e) = aux_schema::write_progress(client.as_ref(), &progress) {
+                            warn!(target: "afa", "Error when persisting the progress of the aggregation {:?}", e);
+                        }
                         if data.number == last_block_in_session {
                             aggregator.notify_last_hash();
                             last_block_seen = true;
@@ -309,7 +827,7 @@ async fn run_aggregator<B, C, BE>(
                     let number = client.number(hash).unwrap().unwrap();
                     // The unwrap might actually fail if data availability is not implemented correctly.
                     let notification = JustificationNotification {
-                        justification: AlephJustification{signature: multisignature},
+                        justification: multisignature.into_justification(session_id, finality_version),
                         hash,
                         number
                     };
@@ -340,19 +858,25 @@ where
     C::Api: aleph_primitives::AlephSessionApi<B>,
     BE: Backend<B> + 'static,
     SC: SelectChain<B> + 'static,
-    RB: network::RequestBlocks<B> + 'static,
+    RB: new_network::RequestBlocks<B> + 'static,
 {
     async fn run_session_as_authority(
         &self,
         node_id: NodeIndex,
         multikeychain: KeyBox,
-        data_network: DataNetwork<NetworkData<B>>,
+        rmc_keybox: KeyBox,
+        bls_keybox: Option<BlsKeyBox>,
+        session_network: SessionNetwork<SessionData<B>>,
+        legacy_network: Option<LegacyDataNetwork<LegacyNetworkData<B>>>,
         session_id: SessionId,
         authorities: Vec<AuthorityId>,
+        finality_version: FinalityVersion,
+        consensus_params: ConsensusParams,
+        last_aggregated: Option<B::Hash>,
         exit_rx: futures::channel::oneshot::Receiver<()>,
     ) -> impl Future<Output = ()> {
         debug!(target: "afa", "Authority task {:?}", session_id);
-        let last_block = last_block_of_session::<B>(session_id, self.session_period);
+        let last_block = self.last_block_of_session(session_id);
         let (ordered_units_tx, ordered_units_rx) = mpsc::unbounded();
         let (aleph_network_tx, data_store_rx) = mpsc::unbounded();
         let (data_store_tx, aleph_network_rx) = mpsc::unbounded();
@@ -361,16 +885,32 @@ where
             self.block_requester.clone(),
             data_store_tx,
             data_store_rx,
-            Default::default(),
+            (&self.tuning.data_store).into(),
         );
-        let (aleph_network, rmc_network, forwarder) =
-            split_network(data_network, aleph_network_tx, aleph_network_rx);
+        let (mut unfiltered_aleph_network, rmc_networks) =
+            split(splice(session_network, legacy_network));
+        let (rmc_network, rmc_bls_network) = split(rmc_networks);
+        // AlephBFT sends directly to the network, but only receives the messages the data store
+        // let through, i.e. ones whose blocks are available.
+        let aleph_network = AlephNetworkWrapper::from(SimpleNetwork::new(
+            unfiltered_aleph_network.sender().clone(),
+            aleph_network_rx,
+        ));
+        let forwarder = async move {
+            while let Some(data) = unfiltered_aleph_network.next().await {
+                trace!(target: "afa", "Forwarding a message to DataStore {:?} {:?}", session_id, data);
+                if let Err(e) = aleph_network_tx.unbounded_send(data) {
+                    debug!(target: "afa", "unable to send data for {:?} to DataStore {}", session_id, e);
+                }
+            }
+        };
 
         let consensus_config = create_aleph_config(
             authorities.len(),
             node_id,
             session_id,
-            self.unit_creation_delay,
+            &consensus_params,
+            &self.tuning.consensus,
         );
 
         let best_header = self
@@ -423,26 +963,74 @@ where
             }
         };
 
+        let (signatures_tx, signatures_rx) = mpsc::unbounded();
+        let equivocation_detector_task = run_equivocation_detector(
+            self.client.clone(),
+            EquivocationDetector::new(
+                session_id,
+                authorities,
+                self.signing_domain(finality_version, SigningPurpose::Finality, session_id),
+            ),
+            signatures_rx,
+            self.equivocation_proofs_tx.clone(),
+        );
+
+        let rmc_delay = Duration::from_millis(self.tuning.aggregator.rmc_delay_ms);
         let aggregator_task = {
             let client = self.client.clone();
             let justification_tx = self.authority_justification_tx.clone();
-            let last_block = last_block_of_session::<B>(session_id, self.session_period);
             let metrics = self.metrics.clone();
-            let multikeychain = multikeychain.clone();
             async move {
-                let aggregator =
-                    BlockSignatureAggregator::new(rmc_network, &multikeychain, metrics.clone());
                 debug!(target: "afa", "Running the aggregator task for {:?}", session_id.0);
-                run_aggregator(
-                    aggregator,
-                    ordered_units_rx,
-                    justification_tx,
-                    client,
-                    last_block,
-                    metrics,
-                    exit_aggregator_rx,
-                )
-                .await;
+                match bls_keybox {
+                    // Equivocations are only provable with ed25519 signatures, so the detector
+                    // ends right away in sessions signing with BLS keys.
+                    Some(bls_keybox) => {
+                        drop(signatures_tx);
+                        let aggregator = BlockSignatureAggregator::new(
+                            rmc_bls_network,
+                            &bls_keybox,
+                            metrics.clone(),
+                            None,
+                            rmc_delay,
+                        );
+                        run_aggregator(
+                            aggregator,
+                            ordered_units_rx,
+                            justification_tx,
+                            client,
+                            session_id,
+                            finality_version,
+                            last_block,
+                            last_aggregated,
+                            metrics,
+                            exit_aggregator_rx,
+                        )
+                        .await;
+                    }
+                    None => {
+                        let aggregator = BlockSignatureAggregator::new(
+                            rmc_network,
+                            &rmc_keybox,
+                            metrics.clone(),
+                            Some(signatures_tx),
+                            rmc_delay,
+                        );
+                        run_aggregator(
+                            aggregator,
+                            ordered_units_rx,
+                            justification_tx,
+                            client,
+                            session_id,
+                            finality_version,
+                            last_block,
+                            last_aggregated,
+                            metrics,
+                            exit_aggregator_rx,
+                        )
+                        .await;
+                    }
+                }
                 debug!(target: "afa", "Aggregator task stopped for {:?}", session_id.0);
             }
         };
@@ -479,6 +1067,11 @@ where
         let refresher_handle = self
             .spawn_handle
             .spawn_essential("aleph/consensus_session_refresher", refresher_task);
+        // Stops by itself once the aggregator is gone.
+        self.spawn_handle.spawn(
+            "aleph/consensus_session_equivocation_detector",
+            equivocation_detector_task,
+        );
 
         async move {
             let _ = exit_rx.await;
@@ -512,39 +1105,29 @@ where
         }
     }
 
-    async fn run_session(&mut self, session_id: SessionId) {
-        let authorities = {
-            if session_id == SessionId(0) {
-                self.client
-                    .runtime_api()
-                    .authorities(&BlockId::Number(<NumberFor<B>>::saturated_from(0u32)))
-                    .unwrap()
-            } else {
-                let last_prev =
-                    last_block_of_session::<B>(SessionId(session_id.0 - 1), self.session_period);
-                // We must read the authorities for next session of the latest block of the previous session.
-                // The reason is that we are not guaranteed to have the first block of new session available yet.
-                match self
-                    .client
-                    .runtime_api()
-                    .next_session_authorities(&BlockId::Number(last_prev))
-                {
-                    Ok(authorities) => authorities
-                        .expect("authorities must be available at last block of previous session"),
-                    Err(e) => {
-                        error!(target: "afa", "Error when getting authorities for session {:?} {:?}", session_id, e);
-                        return;
-                    }
-                }
-            }
-        };
+    /// Runs the session. If it was already running before the restart of the node, `progress`
+    /// tells how far it got.
+    async fn run_session(&mut self, session_id: SessionId, progress: Option<Progress<B::Hash>>) {
+        if session_id != SessionId(0) {
+            let last_prev = self.last_block_of_session(SessionId(session_id.0 - 1));
+            // Changes of the session period are scheduled before the session they affect starts,
+            // so the state at the end of the previous session determines the current session.
+            self.refresh_session_schedule(last_prev);
+        }
+        let session_schedule = self.session_schedule.lock().clone();
+        let authorities =
+            match read_session_authorities(self.client.as_ref(), &session_schedule, session_id) {
+                Some(authorities) => authorities,
+                None => return,
+            };
         self.session_authorities
             .lock()
             .insert(session_id, authorities.clone());
-        let last_block = last_block_of_session::<B>(session_id, self.session_period);
+        let last_block = self.last_block_of_session(session_id);
 
-        // Early skip attempt -- this will trigger during catching up (initial sync).
-        if self.client.info().best_number >= last_block {
+        // Early skip attempt -- this will trigger during catching up (initial sync). A session
+        // resumed after a restart was already live, so there is nothing to catch up with.
+        if progress.is_none() && self.client.info().best_number >= last_block {
             // We need to give the JustificationHandler some time to pick up the keybox for the new session,
             // validate justifications and finalize blocks. We wait 2000ms in total, checking every 200ms
             // if the last block has been finalized.
@@ -560,31 +1143,102 @@ where
                 }
             }
         }
+        let last_aggregated = match progress {
+            Some(progress) => progress.last_aggregated_hash,
+            None => {
+                let progress = Progress::<B::Hash> {
+                    last_started_session: session_id,
+                    last_aggregated_hash: None,
+                };
+                if let Err(e) = aux_schema::write_progress(self.client.as_ref(), &progress) {
+                    warn!(target: "afa", "Error when persisting the start of session {:?} {:?}", session_id, e);
+                }
+                None
+            }
+        };
         trace!(target: "afa", "Authorities for session {:?}: {:?}", session_id, authorities);
-        let maybe_node_id = get_node_index(&authorities, self.keystore.clone()).await;
+        let mut maybe_node_id = get_node_index(&authorities, self.keystore.clone()).await;
+        let finality_version = self.finality_version(session_id);
+        debug!(target: "afa", "Session {:?} runs finality version {:?}", session_id, finality_version);
+        if maybe_node_id.is_some() && !finality_version.is_known() {
+            error!(target: "afa", "Session {:?} runs finality version {:?}, which this node does not know, continuing it as non-authority. Please upgrade the node.", session_id, finality_version);
+            maybe_node_id = None;
+        }
 
         let (exit_authority_tx, exit_authority_rx) = futures::channel::oneshot::channel();
+        let authentication_domain =
+            self.signing_domain(finality_version, SigningPurpose::Authentication, session_id);
+        let verifier = AuthorityVerifier::new(authorities.clone());
         if let Some(node_id) = maybe_node_id {
             debug!(target: "afa", "Running session {:?} as authority id {:?}", session_id, node_id);
-            let keybox = KeyBox::new(
-                node_id,
-                AuthorityVerifier::new(authorities.clone()),
-                AuthorityPen::new(authorities[node_id.0].clone(), self.keystore.clone())
-                    .await
-                    .expect("The keys should sign successfully"),
-            );
-            let data_network = self
+            let pen = AuthorityPen::new(authorities[node_id.0].clone(), self.keystore.clone())
+                .await
+                .expect("The keys should sign successfully");
+            let keybox = KeyBox::new(node_id, verifier.clone(), pen.clone());
+            let consensus_params = self.consensus_params(session_id);
+            debug!(target: "afa", "Session {:?} runs with consensus params {:?}", session_id, consensus_params);
+            let finality_domain =
+                self.signing_domain(finality_version, SigningPurpose::Finality, session_id);
+            let bls_keybox = match get_session_bls_keys(
+                &self.session_bls_keys,
+                self.client.as_ref(),
+                &session_schedule,
+                session_id,
+            ) {
+                Some(bls_keys) => match BlsPen::new(&bls_keys[node_id.0], &self.bls_keystore) {
+                    Ok(bls_pen) => {
+                        debug!(target: "afa", "Session {:?} signs blocks with BLS keys", session_id);
+                        Some(
+                            BlsKeyBox::new(
+                                node_id,
+                                AuthorityVerifier::new(authorities.clone()).with_bls_keys(bls_keys),
+                                bls_pen,
+                            )
+                            .in_domain(finality_domain.clone()),
+                        )
+                    }
+                    Err(e) => {
+                        warn!(target: "afa", "Cannot sign with the BLS key registered for session {:?}, our signatures will be rejected: {:?}", session_id, e);
+                        None
+                    }
+                },
+                None => None,
+            };
+            let session_network = self
                 .session_manager
-                .start_session(session_id, keybox.clone())
-                .await;
+                .start_validator_session(
+                    session_id,
+                    verifier.in_domain(authentication_domain.clone()),
+                    node_id,
+                    pen.in_domain(authentication_domain.clone()),
+                )
+                .expect("The connection manager should be running");
+            let legacy_network = match self.legacy_session_managers.get(&finality_version) {
+                Some(legacy_session_manager) => Some(
+                    legacy_session_manager
+                        .start_session(session_id, keybox.in_domain(authentication_domain))
+                        .await,
+                ),
+                None => None,
+            };
 
             let authority_task = self
                 .run_session_as_authority(
                     node_id,
-                    keybox,
-                    data_network,
+                    keybox.in_domain(self.signing_domain(
+                        finality_version,
+                        SigningPurpose::Unit,
+                        session_id,
+                    )),
+                    keybox.in_domain(finality_domain),
+                    bls_keybox,
+                    session_network,
+                    legacy_network,
                     session_id,
                     authorities,
+                    finality_version,
+                    consensus_params,
+                    last_aggregated,
                     exit_authority_rx,
                 )
                 .await;
@@ -592,7 +1246,14 @@ where
                 .spawn("aleph/session_authority", authority_task);
         } else {
             debug!(target: "afa", "Running session {:?} as non-authority", session_id);
+            if let Err(e) = self
+                .session_manager
+                .start_nonvalidator_session(session_id, verifier.in_domain(authentication_domain))
+            {
+                warn!(target: "afa", "Failed to start session {:?} in the network {:?}", session_id, e);
+            }
         }
+        let mut exit_authority_tx = Some(exit_authority_tx);
         loop {
             let last_finalized_number = self.client.info().finalized_number;
             debug!(target: "afa", "Highest finalized: {:?} session {:?}", last_finalized_number, session_id);
@@ -600,12 +1261,118 @@ where
                 debug!(target: "afa", "Terminating session {:?}", session_id);
                 break;
             }
+            if let (Some(node_id), true) = (maybe_node_id, exit_authority_tx.is_some()) {
+                if self.is_disabled(session_id, node_id) {
+                    warn!(target: "afa", "We were disabled in session {:?}, stopping the authority task.", session_id);
+                    if let Some(exit_authority_tx) = exit_authority_tx.take() {
+                        let _ = exit_authority_tx.send(());
+                    }
+                }
+            }
             Delay::new(Duration::from_millis(1000)).await;
         }
         if maybe_node_id.is_some() {
-            debug!(target: "afa", "Sending exit signal to the authority task.");
-            let _ = exit_authority_tx.send(());
-            self.session_manager.stop_session(session_id);
+            if let Some(exit_authority_tx) = exit_authority_tx {
+                debug!(target: "afa", "Sending exit signal to the authority task.");
+                let _ = exit_authority_tx.send(());
+            }
+            if let Some(legacy_session_manager) =
+                self.legacy_session_managers.get(&finality_version)
+            {
+                legacy_session_manager.stop_session(session_id);
+            }
+        }
+        if let Err(e) = self.session_manager.stop_session(session_id) {
+            warn!(target: "afa", "Failed to stop session {:?} in the network {:?}", session_id, e);
+        }
+    }
+
+    /// Checks whether the given member was disabled in the given session, according to the state
+    /// at the last finalized block.
+    fn is_disabled(&self, session_id: SessionId, node_id: NodeIndex) -> bool {
+        let last_finalized_number = self.client.info().finalized_number;
+        let session_schedule = self.session_schedule.lock().clone();
+        if session_id_from_block_num::<B>(last_finalized_number, &session_schedule) != session_id {
+            return false;
+        }
+        match session_api::disabled_authorities(
+            self.client.as_ref(),
+            &BlockId::Number(last_finalized_number),
+        ) {
+            Ok(disabled) => disabled.contains(&(node_id.0 as u32)),
+            Err(e) => {
+                debug!(target: "afa", "Error when getting disabled authorities at block {:?} {:?}", last_finalized_number, e);
+                false
+            }
+        }
+    }
+
+    /// The block whose state determines the given session, as the first block of the session
+    /// might not be available yet when it starts.
+    fn session_state_block(&self, session_id: SessionId) -> NumberFor<B> {
+        match session_id {
+            SessionId(0) => <NumberFor<B>>::saturated_from(0u32),
+            _ => self.last_block_of_session(SessionId(session_id.0 - 1)),
+        }
+    }
+
+    /// The consensus parameters of the given session.
+    fn consensus_params(&self, session_id: SessionId) -> ConsensusParams {
+        let block_number = self.session_state_block(session_id);
+        match session_api::consensus_params(
+            self.client.as_ref(),
+            &BlockId::Number(block_number),
+            session_id.0,
+        ) {
+            Ok(params) => params,
+            Err(e) => {
+                warn!(target: "afa", "Error when getting consensus params for session {:?}, using the defaults {:?}", session_id, e);
+                ConsensusParams::default()
+            }
+        }
+    }
+
+    /// The version of the protocol run in the given session, which decides the signing domains,
+    /// the network protocol and the format of justifications.
+    fn finality_version(&self, session_id: SessionId) -> FinalityVersion {
+        let session_schedule = self.session_schedule.lock().clone();
+        match get_session_finality_version(
+            &self.session_finality_versions,
+            self.client.as_ref(),
+            &session_schedule,
+            session_id,
+        ) {
+            Some(version) => version,
+            None => {
+                warn!(target: "afa", "Cannot get the finality version of session {:?}, using the legacy one", session_id);
+                FinalityVersion::LEGACY
+            }
+        }
+    }
+
+    /// The domain of the messages of the given purpose signed in the given session, if the
+    /// session signs in domains.
+    fn signing_domain(
+        &self,
+        version: FinalityVersion,
+        purpose: SigningPurpose,
+        session_id: SessionId,
+    ) -> Option<SigningDomain> {
+        version
+            .signs_in_domains()
+            .then(|| SigningDomain::new(purpose, self.genesis_hash.encode(), session_id))
+    }
+
+    fn last_block_of_session(&self, session_id: SessionId) -> NumberFor<B> {
+        last_block_of_session::<B>(session_id, &self.session_schedule.lock())
+    }
+
+    fn refresh_session_schedule(&self, block_number: NumberFor<B>) {
+        match session_api::session_schedule(self.client.as_ref(), &BlockId::Number(block_number)) {
+            Ok(session_schedule) => *self.session_schedule.lock() = session_schedule,
+            Err(e) => {
+                warn!(target: "afa", "Error when getting session schedule at block {:?}, keeping the previous one {:?}", block_number, e)
+            }
         }
     }
 
@@ -616,15 +1383,39 @@ where
         self.session_authorities
             .lock()
             .retain(|&s, _| s >= prune_below);
+        self.session_bls_keys
+            .lock()
+            .retain(|&s, _| s >= prune_below);
+        self.session_finality_versions
+            .lock()
+            .retain(|&s, _| s >= prune_below);
+        // The aux storage is pruned in the same steps, so only the last step has to be removed.
+        if let Err(e) = aux_schema::prune_session_authorities(
+            self.client.as_ref(),
+            SessionId(prune_below.0.saturating_sub(10)),
+            prune_below,
+        ) {
+            warn!(target: "afa", "Error when pruning persisted authorities below session {:?} {:?}", prune_below, e);
+        }
     }
 
     async fn run(mut self) {
         let last_finalized_number = self.client.info().finalized_number;
+        let session_schedule = self.session_schedule.lock().clone();
         let starting_session =
-            session_id_from_block_num::<B>(last_finalized_number, self.session_period).0;
+            session_id_from_block_num::<B>(last_finalized_number, &session_schedule).0;
+        let mut progress = aux_schema::load_progress::<_, B::Hash>(self.client.as_ref())
+            .unwrap_or_else(|e| {
+                warn!(target: "afa", "Error when loading the persisted progress {:?}", e);
+                None
+            })
+            .filter(|progress| progress.last_started_session == SessionId(starting_session));
+        if progress.is_some() {
+            info!(target: "afa", "Resuming session {:?} started before the restart.", starting_session);
+        }
         for curr_id in starting_session.. {
             info!(target: "afa", "Running session {:?}.", curr_id);
-            self.run_session(SessionId(curr_id)).await;
+            self.run_session(SessionId(curr_id), progress.take()).await;
             if curr_id >= 10 && curr_id % 10 == 0 {
                 self.prune_session_data(SessionId(curr_id - 10));
             }
@@ -636,21 +1427,44 @@ pub(crate) fn create_aleph_config(
     n_members: usize,
     node_id: NodeIndex,
     session_id: SessionId,
-    unit_creation_delay: UnitCreationDelay,
+    params: &ConsensusParams,
+    tuning: &ConsensusTuning,
 ) -> aleph_bft::Config {
     let mut consensus_config = default_aleph_config(n_members.into(), node_id, session_id.0 as u64);
-    consensus_config.max_round = 7000;
+    consensus_config.max_round = params.max_round.into();
+    let unit_creation_delay_ms = params.unit_creation_delay;
+    // The slowdown is kept on chain in parts per million of the delay.
+    let unit_creation_slowdown_base = 1. + params.unit_creation_slowdown as f64 / 1_000_000.;
+    let ConsensusTuning {
+        first_unit_delay_ms,
+        unit_creation_slowdown_start,
+        unit_broadcast_delay_ms,
+        unit_broadcast_slowdown_base,
+        ..
+    } = *tuning;
     let unit_creation_delay = Arc::new(move |t| {
         if t == 0 {
-            Duration::from_millis(2000)
+            Duration::from_millis(first_unit_delay_ms)
         } else {
-            exponential_slowdown(t, unit_creation_delay.0 as f64, 5000, 1.005)
+            exponential_slowdown(
+                t,
+                unit_creation_delay_ms as f64,
+                unit_creation_slowdown_start,
+                unit_creation_slowdown_base,
+            )
         }
     });
-    let unit_broadcast_delay = Arc::new(|t| exponential_slowdown(t, 4000., 0, 2.));
+    let unit_broadcast_delay = Arc::new(move |t| {
+        exponential_slowdown(
+            t,
+            unit_broadcast_delay_ms as f64,
+            0,
+            unit_broadcast_slowdown_base,
+        )
+    });
     let delay_config = DelayConfig {
-        tick_interval: Duration::from_millis(100),
-        requests_interval: Duration::from_millis(3000),
+        tick_interval: Duration::from_millis(tuning.tick_interval_ms),
+        requests_interval: Duration::from_millis(tuning.requests_interval_ms),
         unit_broadcast_delay,
         unit_creation_delay,
     };
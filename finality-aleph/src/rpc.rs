@@ -0,0 +1,478 @@
+//! The `aleph_` JSON-RPC namespace, exposing the stored Aleph justifications and the session data
+//! needed to check them, as well as a subscription to the justifications of newly finalized blocks.
+use crate::{
+    aux_schema::load_session_authorities,
+    crypto::AuthorityVerifier,
+    justification::{
+        decode_justification, AlephJustification, FinalizedJustification, JustificationStream,
+    },
+    party::{read_session_bls_keys, read_session_finality_version},
+    session_api, AuthorityId, SessionId,
+};
+use aleph_primitives::{AlephSessionApi, ALEPH_ENGINE_ID};
+use codec::Encode;
+use futures::{FutureExt, SinkExt, StreamExt};
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use jsonrpc_pubsub::{manager::SubscriptionManager, typed::Subscriber, SubscriptionId};
+use log::warn;
+use sc_client_api::{AuxStore, BlockBackend};
+use serde::{Deserialize, Serialize};
+use sp_api::{BlockId, ProvideRuntimeApi};
+use sp_blockchain::HeaderBackend;
+use sp_core::Bytes;
+use sp_runtime::{
+    traits::{Block, Header},
+    SaturatedConversion,
+};
+use std::{fmt, sync::Arc};
+
+/// Base code for all the errors of the `aleph_` namespace.
+const BASE_ERROR: i64 = 7000;
+
+#[derive(Debug)]
+pub enum Error {
+    Client(sp_blockchain::Error),
+    Api(sp_api::ApiError),
+    UnknownBlock(String),
+    InvalidJustification(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Client(e) => write!(f, "Client error {:?}", e),
+            Error::Api(e) => write!(f, "Runtime api error {:?}", e),
+            Error::UnknownBlock(block) => write!(f, "Unknown block {}", block),
+            Error::InvalidJustification(block) => {
+                write!(f, "Cannot decode the justification of block {}", block)
+            }
+        }
+    }
+}
+
+impl Error {
+    fn code(&self) -> i64 {
+        match self {
+            Error::Client(_) => BASE_ERROR + 1,
+            Error::Api(_) => BASE_ERROR + 2,
+            Error::UnknownBlock(_) => BASE_ERROR + 3,
+            Error::InvalidJustification(_) => BASE_ERROR + 4,
+        }
+    }
+}
+
+impl From<Error> for RpcError {
+    fn from(e: Error) -> Self {
+        RpcError {
+            code: ErrorCode::ServerError(e.code()),
+            message: e.to_string(),
+            data: None,
+        }
+    }
+}
+
+impl From<sp_blockchain::Error> for Error {
+    fn from(e: sp_blockchain::Error) -> Self {
+        Error::Client(e)
+    }
+}
+
+impl From<sp_api::ApiError> for Error {
+    fn from(e: sp_api::ApiError) -> Self {
+        Error::Api(e)
+    }
+}
+
+/// The decoded justification of a block.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JustificationInfo<Hash> {
+    pub hash: Hash,
+    pub number: u32,
+    pub session: u32,
+    /// The version of the justification format. Justifications stored in the first format are
+    /// reported as version 2, which carries the same signatures.
+    pub version: u8,
+    /// Indices of the authorities whose signatures are in the justification.
+    pub signers: Vec<u32>,
+    /// Whether the signatures are valid and there are enough of them, `None` if the authorities
+    /// of the session are unknown.
+    pub complete: Option<bool>,
+}
+
+/// The schedule and the committee of a session.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfo {
+    pub session: u32,
+    pub first_block: u32,
+    pub last_block: u32,
+    /// `None` if the authorities are neither stored locally nor known to the runtime.
+    pub authorities: Option<Vec<AuthorityId>>,
+}
+
+/// The best and the finalized block together with their sessions.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FinalityStatus<Hash> {
+    pub best_hash: Hash,
+    pub best_number: u32,
+    pub best_session: u32,
+    pub finalized_hash: Hash,
+    pub finalized_number: u32,
+    pub finalized_session: u32,
+}
+
+/// The justification of a newly finalized block, as sent to the subscribers.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FinalizedJustificationInfo<Hash> {
+    pub hash: Hash,
+    pub number: u32,
+    pub session: u32,
+    /// The SCALE encoded justification, in the form stored with the block.
+    pub justification: Bytes,
+}
+
+impl<B: Block> From<FinalizedJustification<B>> for FinalizedJustificationInfo<B::Hash> {
+    fn from(finalized: FinalizedJustification<B>) -> Self {
+        FinalizedJustificationInfo {
+            hash: finalized.hash,
+            number: finalized.number.saturated_into(),
+            session: finalized.session.0,
+            justification: finalized.justification.encode().into(),
+        }
+    }
+}
+
+#[rpc]
+pub trait AlephApi<Hash> {
+    type Metadata;
+
+    /// Returns the decoded Aleph justification of the given block, if the block has one.
+    #[rpc(name = "aleph_getJustification")]
+    fn justification(&self, hash: Hash) -> Result<Option<JustificationInfo<Hash>>>;
+
+    /// Returns the blocks and the authorities of the given session.
+    #[rpc(name = "aleph_sessionInfo")]
+    fn session_info(&self, session: u32) -> Result<SessionInfo>;
+
+    /// Returns the best and the finalized block together with their sessions.
+    #[rpc(name = "aleph_finalityStatus")]
+    fn finality_status(&self) -> Result<FinalityStatus<Hash>>;
+
+    /// Sends the justification of every block finalized from now on. Subscriptions that fall too
+    /// far behind are ended.
+    #[pubsub(
+        subscription = "aleph_justifications",
+        subscribe,
+        name = "aleph_subscribeJustifications"
+    )]
+    fn subscribe_justifications(
+        &self,
+        metadata: Self::Metadata,
+        subscriber: Subscriber<FinalizedJustificationInfo<Hash>>,
+    );
+
+    /// Cancels a subscription to the justifications.
+    #[pubsub(
+        subscription = "aleph_justifications",
+        unsubscribe,
+        name = "aleph_unsubscribeJustifications"
+    )]
+    fn unsubscribe_justifications(
+        &self,
+        metadata: Option<Self::Metadata>,
+        id: SubscriptionId,
+    ) -> Result<bool>;
+}
+
+/// Implements the `aleph_` namespace on top of a full client.
+pub struct Aleph<B: Block, C> {
+    client: Arc<C>,
+    justification_stream: JustificationStream<B>,
+    manager: SubscriptionManager,
+}
+
+impl<B: Block, C> Aleph<B, C> {
+    /// Subscriptions are driven by tasks spawned with the given executor.
+    pub fn new<E>(client: Arc<C>, justification_stream: JustificationStream<B>, executor: E) -> Self
+    where
+        E: futures::task::Spawn + Send + Sync + 'static,
+    {
+        Aleph {
+            client,
+            justification_stream,
+            manager: SubscriptionManager::new(Arc::new(executor)),
+        }
+    }
+}
+
+impl<B, C> Aleph<B, C>
+where
+    B: Block,
+    C: HeaderBackend<B> + BlockBackend<B> + ProvideRuntimeApi<B> + AuxStore,
+    C::Api: AlephSessionApi<B>,
+{
+    fn best_block_id(&self) -> BlockId<B> {
+        BlockId::Hash(self.client.info().best_hash)
+    }
+
+    /// The authorities stored by the gadget, falling back to the ones known to the runtime.
+    fn session_authorities(
+        &self,
+        session: u32,
+    ) -> std::result::Result<Option<Vec<AuthorityId>>, Error> {
+        if let Some(authorities) =
+            load_session_authorities(self.client.as_ref(), SessionId(session))?
+        {
+            return Ok(Some(authorities));
+        }
+        Ok(session_api::authorities_for_session(
+            self.client.as_ref(),
+            &self.best_block_id(),
+            session,
+        )?)
+    }
+
+    /// The verifier of the justifications of the session, if its authorities and finality version
+    /// are known. Sessions signing with BLS keys are verified with the keys announced for them.
+    fn session_verifier(
+        &self,
+        session: u32,
+    ) -> std::result::Result<Option<AuthorityVerifier>, Error> {
+        let authorities = match self.session_authorities(session)? {
+            Some(authorities) => authorities,
+            None => return Ok(None),
+        };
+        let schedule = session_api::session_schedule(self.client.as_ref(), &self.best_block_id())?;
+        let finality_version = match read_session_finality_version(
+            self.client.as_ref(),
+            &schedule,
+            SessionId(session),
+        ) {
+            Some(finality_version) => finality_version,
+            None => return Ok(None),
+        };
+        let verifier = AuthorityVerifier::new(authorities).for_session(
+            self.client.info().genesis_hash.encode(),
+            SessionId(session),
+            finality_version,
+        );
+        let bls_keys = read_session_bls_keys(self.client.as_ref(), &schedule, SessionId(session));
+        Ok(Some(match bls_keys {
+            Some(bls_keys) => verifier.with_bls_keys(bls_keys),
+            None => verifier,
+        }))
+    }
+
+    fn try_justification(
+        &self,
+        hash: B::Hash,
+    ) -> std::result::Result<Option<JustificationInfo<B::Hash>>, Error> {
+        let number = *self
+            .client
+            .header(BlockId::Hash(hash))?
+            .ok_or_else(|| Error::UnknownBlock(format!("{:?}", hash)))?
+            .number();
+        let justification = match self
+            .client
+            .justifications(&BlockId::Hash(hash))?
+            .and_then(|justifications| justifications.into_justification(ALEPH_ENGINE_ID))
+        {
+            Some(justification) => decode_justification(&justification)
+                .map_err(|_| Error::InvalidJustification(format!("{:?}", hash)))?,
+            None => return Ok(None),
+        };
+        let number: u32 = number.saturated_into();
+        let session = session_api::session_schedule(self.client.as_ref(), &self.best_block_id())?
+            .session_of_block(number);
+        let verifier = self.session_verifier(session)?;
+        Ok(Some(justification_info(
+            hash,
+            number,
+            session,
+            &justification,
+            verifier,
+        )))
+    }
+
+    fn try_session_info(&self, session: u32) -> std::result::Result<SessionInfo, Error> {
+        let schedule = session_api::session_schedule(self.client.as_ref(), &self.best_block_id())?;
+        Ok(SessionInfo {
+            session,
+            first_block: schedule.first_block_of_session(session),
+            last_block: schedule.last_block_of_session(session),
+            authorities: self.session_authorities(session)?,
+        })
+    }
+
+    fn try_finality_status(&self) -> std::result::Result<FinalityStatus<B::Hash>, Error> {
+        let info = self.client.info();
+        let schedule =
+            session_api::session_schedule(self.client.as_ref(), &BlockId::Hash(info.best_hash))?;
+        let best_number: u32 = info.best_number.saturated_into();
+        let finalized_number: u32 = info.finalized_number.saturated_into();
+        Ok(FinalityStatus {
+            best_hash: info.best_hash,
+            best_number,
+            best_session: schedule.session_of_block(best_number),
+            finalized_hash: info.finalized_hash,
+            finalized_number,
+            finalized_session: schedule.session_of_block(finalized_number),
+        })
+    }
+}
+
+fn justification_info<H: Encode>(
+    hash: H,
+    number: u32,
+    session: u32,
+    justification: &AlephJustification,
+    verifier: Option<AuthorityVerifier>,
+) -> JustificationInfo<H> {
+    let signers = justification
+        .signers()
+        .into_iter()
+        .map(|index| index as u32)
+        .collect();
+    let complete =
+        verifier.map(|verifier| verifier.verify_justification(&hash.encode(), justification));
+    JustificationInfo {
+        hash,
+        number,
+        session,
+        version: justification.version(),
+        signers,
+        complete,
+    }
+}
+
+impl<B, C> AlephApi<B::Hash> for Aleph<B, C>
+where
+    B: Block,
+    C: HeaderBackend<B> + BlockBackend<B> + ProvideRuntimeApi<B> + AuxStore + Send + Sync + 'static,
+    C::Api: AlephSessionApi<B>,
+{
+    type Metadata = sc_rpc::Metadata;
+
+    fn justification(&self, hash: B::Hash) -> Result<Option<JustificationInfo<B::Hash>>> {
+        self.try_justification(hash).map_err(Into::into)
+    }
+
+    fn session_info(&self, session: u32) -> Result<SessionInfo> {
+        self.try_session_info(session).map_err(Into::into)
+    }
+
+    fn finality_status(&self) -> Result<FinalityStatus<B::Hash>> {
+        self.try_finality_status().map_err(Into::into)
+    }
+
+    fn subscribe_justifications(
+        &self,
+        _metadata: Self::Metadata,
+        subscriber: Subscriber<FinalizedJustificationInfo<B::Hash>>,
+    ) {
+        let stream = self
+            .justification_stream
+            .subscribe()
+            .map(|finalized| Ok(Ok(FinalizedJustificationInfo::from(finalized))));
+
+        self.manager.add(subscriber, |sink| {
+            stream
+                .forward(
+                    sink.sink_map_err(
+                        |e| warn!(target: "afa", "Error sending justifications {:?}", e),
+                    ),
+                )
+                .map(|_| ())
+        });
+    }
+
+    fn unsubscribe_justifications(
+        &self,
+        _metadata: Option<Self::Metadata>,
+        id: SubscriptionId,
+    ) -> Result<bool> {
+        Ok(self.manager.cancel(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::AuthorityPen;
+    use aleph_justification::{AlephSignature, SignatureSet};
+    use aleph_primitives::{FinalityVersion, KEY_TYPE};
+    use sp_core::H256;
+    use sp_keystore::{testing::KeyStore, CryptoStore};
+
+    async fn prepare_test() -> (Vec<AuthorityPen>, Vec<AuthorityId>) {
+        let key_store = Arc::new(KeyStore::new());
+        let mut authorities = Vec::new();
+        for name in &["//Alice", "//Bob", "//Charlie", "//Dave"] {
+            let pk = key_store
+                .ed25519_generate_new(KEY_TYPE, Some(name))
+                .await
+                .unwrap();
+            authorities.push(AuthorityId::from(pk));
+        }
+        let mut pens = Vec::new();
+        for authority_id in authorities.clone() {
+            pens.push(
+                AuthorityPen::new(authority_id, key_store.clone())
+                    .await
+                    .unwrap(),
+            );
+        }
+        (pens, authorities)
+    }
+
+    fn verifier(authorities: Vec<AuthorityId>) -> AuthorityVerifier {
+        AuthorityVerifier::new(authorities).for_session(
+            Vec::new(),
+            SessionId(1),
+            FinalityVersion::LEGACY,
+        )
+    }
+
+    async fn justification(
+        pens: &[AuthorityPen],
+        signers: &[usize],
+        hash: H256,
+    ) -> AlephJustification {
+        let mut signature = SignatureSet::with_size(pens.len());
+        for index in signers {
+            signature =
+                signature.add_signature(&pens[*index].sign(&hash.encode()).await.into(), *index);
+        }
+        AlephJustification::new(SessionId(1), AlephSignature::Signatures(signature))
+    }
+
+    #[tokio::test]
+    async fn describes_complete_justification() {
+        let (pens, authorities) = prepare_test().await;
+        let hash = H256::repeat_byte(3);
+        let justification = justification(&pens, &[0, 1, 3], hash).await;
+
+        let info = justification_info(hash, 10, 1, &justification, Some(verifier(authorities)));
+
+        assert_eq!(info.signers, vec![0, 1, 3]);
+        assert_eq!(info.complete, Some(true));
+    }
+
+    #[tokio::test]
+    async fn describes_incomplete_justification() {
+        let (pens, authorities) = prepare_test().await;
+        let hash = H256::repeat_byte(3);
+        let justification = justification(&pens, &[2], hash).await;
+
+        let info = justification_info(hash, 10, 1, &justification, Some(verifier(authorities)));
+        assert_eq!(info.signers, vec![2]);
+        assert_eq!(info.complete, Some(false));
+
+        let info = justification_info(hash, 10, 1, &justification, None);
+        assert_eq!(info.complete, None);
+    }
+}
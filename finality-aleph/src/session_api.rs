@@ -0,0 +1,150 @@
+//! Calls of `AlephSessionApi` methods that older runtimes might lack. Every method added to the
+//! api bumps its version, so whether the runtime at a block has a method is decided by the version
+//! of the api at that block.
+use aleph_primitives::{
+    AlephSessionApi, AuthorityId, BlsKeyRegistration, ConsensusParams, FinalityVersion,
+    SessionSchedule,
+};
+use sp_api::{ApiError, ApiExt, BlockId, ProvideRuntimeApi};
+use sp_runtime::traits::Block;
+
+/// The version of the api introducing `session_schedule`.
+const SESSION_SCHEDULE_VERSION: u32 = 2;
+/// The version of the api introducing `disabled_authorities`.
+const DISABLED_AUTHORITIES_VERSION: u32 = 3;
+/// The version of the api introducing `submit_report_equivocation_unsigned_extrinsic`.
+const EQUIVOCATION_REPORTS_VERSION: u32 = 4;
+/// The version of the api introducing `authorities_for_session`.
+const SESSION_AUTHORITIES_VERSION: u32 = 5;
+/// The version of the api introducing `bls_keys_for_session`.
+const SESSION_BLS_KEYS_VERSION: u32 = 6;
+/// The version of the api introducing `consensus_params`.
+const CONSENSUS_PARAMS_VERSION: u32 = 7;
+/// The version of the api introducing `finality_version`.
+const FINALITY_VERSION_VERSION: u32 = 8;
+
+/// Whether the runtime at the block implements at least the given version of the api.
+fn has_version<B, C>(client: &C, at: &BlockId<B>, version: u32) -> Result<bool, ApiError>
+where
+    B: Block,
+    C: ProvideRuntimeApi<B>,
+    C::Api: AlephSessionApi<B>,
+{
+    client
+        .runtime_api()
+        .has_api_with::<dyn AlephSessionApi<B>, _>(at, |v| v >= version)
+}
+
+/// The schedule of session periods at the block. Runtimes predating the schedule have a constant
+/// session period and block time.
+pub fn session_schedule<B, C>(client: &C, at: &BlockId<B>) -> Result<SessionSchedule, ApiError>
+where
+    B: Block,
+    C: ProvideRuntimeApi<B>,
+    C::Api: AlephSessionApi<B>,
+{
+    let runtime_api = client.runtime_api();
+    if has_version(client, at, SESSION_SCHEDULE_VERSION)? {
+        return runtime_api.session_schedule(at);
+    }
+    Ok(SessionSchedule::new(
+        runtime_api.session_period(at)?,
+        runtime_api.millisecs_per_block(at)?,
+    ))
+}
+
+/// The indices of the authorities disabled in the session of the block. Runtimes predating
+/// offences never disable anyone.
+pub fn disabled_authorities<B, C>(client: &C, at: &BlockId<B>) -> Result<Vec<u32>, ApiError>
+where
+    B: Block,
+    C: ProvideRuntimeApi<B>,
+    C::Api: AlephSessionApi<B>,
+{
+    if has_version(client, at, DISABLED_AUTHORITIES_VERSION)? {
+        return client.runtime_api().disabled_authorities(at);
+    }
+    Ok(Vec::new())
+}
+
+/// Whether the runtime at the block accepts equivocation reports.
+pub fn accepts_equivocation_reports<B, C>(client: &C, at: &BlockId<B>) -> Result<bool, ApiError>
+where
+    B: Block,
+    C: ProvideRuntimeApi<B>,
+    C::Api: AlephSessionApi<B>,
+{
+    has_version(client, at, EQUIVOCATION_REPORTS_VERSION)
+}
+
+/// The authorities of the session, if they are in the history kept by the runtime at the block.
+/// Runtimes predating the history keep none.
+pub fn authorities_for_session<B, C>(
+    client: &C,
+    at: &BlockId<B>,
+    session_id: u32,
+) -> Result<Option<Vec<AuthorityId>>, ApiError>
+where
+    B: Block,
+    C: ProvideRuntimeApi<B>,
+    C::Api: AlephSessionApi<B>,
+{
+    if has_version(client, at, SESSION_AUTHORITIES_VERSION)? {
+        return client.runtime_api().authorities_for_session(at, session_id);
+    }
+    Ok(None)
+}
+
+/// The BLS keys registered for the session, if it signs with them. Runtimes predating BLS keys
+/// never switch to them.
+pub fn bls_keys_for_session<B, C>(
+    client: &C,
+    at: &BlockId<B>,
+    session_id: u32,
+) -> Result<Option<Vec<BlsKeyRegistration>>, ApiError>
+where
+    B: Block,
+    C: ProvideRuntimeApi<B>,
+    C::Api: AlephSessionApi<B>,
+{
+    if has_version(client, at, SESSION_BLS_KEYS_VERSION)? {
+        return client.runtime_api().bls_keys_for_session(at, session_id);
+    }
+    Ok(None)
+}
+
+/// The consensus parameters of the session. Runtimes predating the parameters run every session
+/// with the defaults.
+pub fn consensus_params<B, C>(
+    client: &C,
+    at: &BlockId<B>,
+    session_id: u32,
+) -> Result<ConsensusParams, ApiError>
+where
+    B: Block,
+    C: ProvideRuntimeApi<B>,
+    C::Api: AlephSessionApi<B>,
+{
+    if has_version(client, at, CONSENSUS_PARAMS_VERSION)? {
+        return client.runtime_api().consensus_params(at, session_id);
+    }
+    Ok(ConsensusParams::default())
+}
+
+/// The version of the protocol run in the session. Runtimes predating finality versions only ran
+/// the legacy one.
+pub fn finality_version<B, C>(
+    client: &C,
+    at: &BlockId<B>,
+    session_id: u32,
+) -> Result<FinalityVersion, ApiError>
+where
+    B: Block,
+    C: ProvideRuntimeApi<B>,
+    C::Api: AlephSessionApi<B>,
+{
+    if has_version(client, at, FINALITY_VERSION_VERSION)? {
+        return client.runtime_api().finality_version(at, session_id);
+    }
+    Ok(FinalityVersion::LEGACY)
+}
@@ -1,5 +1,5 @@
 use crate::data_io::{AlephData, AlephDataFor, AlephNetworkMessage, DataStore, DataStoreConfig};
-use crate::network::RequestBlocks;
+use crate::new_network::RequestBlocks;
 use futures::{
     channel::{
         mpsc::{self, UnboundedReceiver, UnboundedSender},
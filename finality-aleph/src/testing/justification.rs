@@ -3,10 +3,10 @@ use std::collections::VecDeque;
 use std::sync::Arc;
 use std::time::Duration;
 
-use aleph_bft::{NodeCount, PartialMultisignature, SignatureSet};
+use aleph_justification::{AlephJustificationV1, SignatureSet, SignatureV1};
 use codec::Encode;
 use futures::channel::mpsc::{unbounded, UnboundedSender};
-use futures::Future;
+use futures::{Future, StreamExt};
 use sp_api::BlockId;
 use sp_blockchain::HeaderBackend;
 use sp_runtime::traits::Block;
@@ -16,51 +16,61 @@ use tokio::time::timeout;
 use aleph_primitives::AuthoritySignature;
 use AcceptancePolicy::*;
 
-use crate::crypto::{Signature, SignatureV1};
 use crate::justification::{
-    backwards_compatible_decode, AlephJustification, AlephJustificationV1, JustificationDecoding,
-    JustificationHandler, JustificationHandlerConfig,
+    decode_justification, AlephJustification, AlephSignature, FinalizedJustification,
+    JustificationHandler, JustificationHandlerConfig, JustificationStream, SUBSCRIPTION_BUFFER,
 };
 use crate::testing::mocks::{
     create_block, AcceptancePolicy, Client, JustificationRequestDelayImpl, MockedBlockFinalizer,
     MockedBlockRequester, SessionInfoProviderImpl, TBlock, VerifierWrapper,
 };
-use crate::{JustificationNotification, SessionPeriod};
+use crate::{JustificationNotification, SessionId, SessionPeriod};
 
 #[test]
 fn correctly_decodes_v1() {
-    let mut signature_set: SignatureSet<SignatureV1> = SignatureSet::with_size(7.into());
+    let mut signature_set: SignatureSet<SignatureV1> = SignatureSet::with_size(7);
     for i in 0..7 {
-        let id = i.into();
         let signature_v1 = SignatureV1 {
-            _id: id,
+            id: i as u64,
             sgn: Default::default(),
         };
-        signature_set = signature_set.add_signature(&signature_v1, id);
+        signature_set = signature_set.add_signature(&signature_v1, i);
     }
 
     let just_v1 = AlephJustificationV1 {
         signature: signature_set,
     };
     let encoded_just: Vec<u8> = just_v1.encode();
-    let decoded = backwards_compatible_decode(encoded_just);
-    assert_eq!(decoded, JustificationDecoding::V1(just_v1));
+    let decoded = decode_justification(&encoded_just);
+    assert_eq!(decoded, Ok(AlephJustification::from(just_v1)));
 }
 
 #[test]
 fn correctly_decodes_v2() {
-    let mut signature_set: SignatureSet<Signature> = SignatureSet::with_size(7.into());
+    let mut signature_set: SignatureSet<AuthoritySignature> = SignatureSet::with_size(7);
     for i in 0..7 {
         let authority_signature: AuthoritySignature = Default::default();
-        signature_set = signature_set.add_signature(&authority_signature.into(), i.into());
+        signature_set = signature_set.add_signature(&authority_signature, i);
     }
 
-    let just_v2 = AlephJustification {
-        signature: signature_set,
-    };
+    let just_v2 = AlephJustification::V2(signature_set);
     let encoded_just: Vec<u8> = just_v2.encode();
-    let decoded = backwards_compatible_decode(encoded_just);
-    assert_eq!(decoded, JustificationDecoding::V2(just_v2));
+    let decoded = decode_justification(&encoded_just);
+    assert_eq!(decoded, Ok(just_v2));
+}
+
+#[test]
+fn correctly_decodes_v3() {
+    let mut signature_set: SignatureSet<AuthoritySignature> = SignatureSet::with_size(7);
+    for i in 0..7 {
+        let authority_signature: AuthoritySignature = Default::default();
+        signature_set = signature_set.add_signature(&authority_signature, i);
+    }
+
+    let just_v3 = AlephJustification::new(SessionId(3), AlephSignature::Signatures(signature_set));
+    let encoded_just: Vec<u8> = just_v3.encode();
+    let decoded = decode_justification(&encoded_just);
+    assert_eq!(decoded, Ok(just_v3));
 }
 
 #[test]
@@ -79,11 +89,11 @@ fn correctly_decodes_legacy_v1_size4() {
         28, 150, 76, 98, 250, 13, 97, 163, 152, 77, 30, 153, 206, 49, 210, 53, 218, 1, 52, 195, 97,
         58, 229, 250, 198, 35, 155, 118, 249, 180, 123, 12, 8, 0,
     ];
-    let decoded = backwards_compatible_decode(raw);
-    if let JustificationDecoding::V1(just) = decoded {
-        assert_eq!(just.signature.size(), NodeCount(4));
+    let decoded = decode_justification(&raw);
+    if let Ok(AlephJustification::V2(signature_set)) = decoded {
+        assert_eq!(signature_set.size(), 4);
     } else {
-        panic!("decoded should be V1, and is {:?}", decoded);
+        panic!("decoded should be converted to V2, and is {:?}", decoded);
     }
 }
 
@@ -110,11 +120,11 @@ fn correctly_decodes_legacy_v1_size6() {
         75, 63, 244, 63, 1, 179, 125, 53, 110, 220, 13, 126, 46, 124, 173, 98, 164, 194, 175, 52,
         108, 43, 68, 94, 254, 77, 39, 172, 255, 145, 10, 0,
     ];
-    let decoded = backwards_compatible_decode(raw);
-    if let JustificationDecoding::V1(just) = decoded {
-        assert_eq!(just.signature.size(), NodeCount(6));
+    let decoded = decode_justification(&raw);
+    if let Ok(AlephJustification::V2(signature_set)) = decoded {
+        assert_eq!(signature_set.size(), 6);
     } else {
-        panic!("decoded should be V1, and is {:?}", decoded);
+        panic!("decoded should be converted to V2, and is {:?}", decoded);
     }
 }
 
@@ -141,9 +151,7 @@ type Environment = (
 
 fn create_justification_notification_for(block: TBlock) -> JustificationNotification<TBlock> {
     JustificationNotification {
-        justification: AlephJustification {
-            signature: SignatureSet::with_size(0.into()),
-        },
+        justification: AlephJustification::V2(SignatureSet::with_size(0)),
         hash: block.hash(),
         number: block.header.number,
     }
@@ -162,12 +170,23 @@ fn prepare_env(
     finalization_height: u64,
     verification_policy: AcceptancePolicy,
     request_policy: AcceptancePolicy,
+) -> Environment {
+    prepare_env_with_config(
+        finalization_height,
+        verification_policy,
+        JustificationHandlerConfig::new(request_policy),
+    )
+}
+
+fn prepare_env_with_config(
+    finalization_height: u64,
+    verification_policy: AcceptancePolicy,
+    config: JustificationHandlerConfig<TBlock, JustificationRequestDelayImpl>,
 ) -> Environment {
     let client = Client::new(finalization_height);
     let info_provider = SessionInfoProviderImpl::new(SESSION_PERIOD, verification_policy);
     let finalizer = MockedBlockFinalizer::new();
     let requester = MockedBlockRequester::new();
-    let config = JustificationHandlerConfig::new(request_policy);
     let jrd = config.justification_request_delay.clone();
 
     let jh = JustificationHandler::new(
@@ -283,6 +302,25 @@ async fn leads_to_finalization_when_appropriate_justification_comes() {
     .await;
 }
 
+#[tokio::test(threaded_scheduler)]
+async fn ignores_notifications_claiming_other_session() {
+    run_test(
+        prepare_env(FINALIZED_HEIGHT, AlwaysAccept, AlwaysReject),
+        |_, imp_just_tx, client, _, finalizer, jrd| async move {
+            let block = client.next_block_to_finalize();
+            let session = SessionId(block.header.number as u32 / SESSION_PERIOD.0);
+            let mut message = create_justification_notification_for(block);
+            message.justification = AlephJustification::new(
+                SessionId(session.0 + 1),
+                AlephSignature::Signatures(SignatureSet::with_size(0)),
+            );
+            imp_just_tx.unbounded_send(message).unwrap();
+            expect_not_finalized(&finalizer, &jrd).await;
+        },
+    )
+    .await;
+}
+
 #[tokio::test(threaded_scheduler)]
 async fn waits_for_verifier_before_finalizing() {
     let verification_policy = FromSequence(RefCell::new(VecDeque::from(vec![false, false, true])));
@@ -323,6 +361,26 @@ async fn keeps_finalizing_block_if_not_finalized_yet() {
     .await;
 }
 
+#[tokio::test(threaded_scheduler)]
+async fn does_not_verify_the_same_justification_twice() {
+    // The verifier panics if asked a second time.
+    let verification_policy = FromSequence(RefCell::new(VecDeque::from(vec![true])));
+    run_test(
+        prepare_env(FINALIZED_HEIGHT, verification_policy, AlwaysReject),
+        |auth_just_tx, imp_just_tx, client, _, finalizer, jrd| async move {
+            let block = client.next_block_to_finalize();
+            let message = create_justification_notification_for(block.clone());
+
+            imp_just_tx.unbounded_send(message.clone()).unwrap();
+            expect_finalized(&finalizer, &jrd, block.clone()).await;
+
+            auth_just_tx.unbounded_send(message).unwrap();
+            expect_finalized(&finalizer, &jrd, block).await;
+        },
+    )
+    .await;
+}
+
 #[tokio::test(threaded_scheduler)]
 async fn ignores_notifications_for_old_blocks() {
     run_test(
@@ -337,6 +395,89 @@ async fn ignores_notifications_for_old_blocks() {
     .await;
 }
 
+#[tokio::test(threaded_scheduler)]
+async fn forwards_notifications_conflicting_with_finalized_blocks() {
+    let (conflicting_tx, mut conflicting_rx) = unbounded();
+    let mut config = JustificationHandlerConfig::new(AlwaysReject);
+    config.conflicting_justification_tx = Some(conflicting_tx);
+    run_test(
+        prepare_env_with_config(FINALIZED_HEIGHT, AlwaysAccept, config),
+        |_, imp_just_tx, client, _, finalizer, jrd| async move {
+            let finalized = client.get_block(BlockId::Number(1u64)).unwrap();
+            let conflicting = create_block([1u8; 32].into(), 1u64);
+
+            let message = create_justification_notification_for(finalized);
+            imp_just_tx.unbounded_send(message).unwrap();
+            let message = create_justification_notification_for(conflicting.clone());
+            imp_just_tx.unbounded_send(message).unwrap();
+
+            let forwarded = timeout(Duration::from_millis(50), conflicting_rx.next())
+                .await
+                .expect("the conflicting justification should be forwarded")
+                .unwrap();
+            assert_eq!(forwarded.hash, conflicting.hash());
+            expect_not_finalized(&finalizer, &jrd).await;
+        },
+    )
+    .await;
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn broadcasts_justifications_of_finalized_blocks() {
+    let (sender, stream) = JustificationStream::channel();
+    let mut justifications = stream.subscribe();
+    let mut config = JustificationHandlerConfig::new(AlwaysReject);
+    config.justification_sender = Some(sender);
+    run_test(
+        prepare_env_with_config(FINALIZED_HEIGHT, AlwaysAccept, config),
+        |_, imp_just_tx, client, _, finalizer, jrd| async move {
+            let block = client.next_block_to_finalize();
+            let message = create_justification_notification_for(block.clone());
+            imp_just_tx.unbounded_send(message).unwrap();
+            expect_finalized(&finalizer, &jrd, block.clone()).await;
+
+            let broadcast = timeout(Duration::from_millis(50), justifications.next())
+                .await
+                .expect("the justification should be broadcast")
+                .unwrap();
+            assert_eq!(broadcast.hash, block.hash());
+            assert_eq!(broadcast.number, block.header.number);
+            assert_eq!(
+                broadcast.session,
+                SessionId((FINALIZED_HEIGHT + 1) as u32 / SESSION_PERIOD.0)
+            );
+        },
+    )
+    .await;
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn drops_lagging_subscribers() {
+    let (sender, stream) = JustificationStream::channel();
+    let mut keeping_up = stream.subscribe();
+    let lagging = stream.subscribe();
+    let block = create_block([1u8; 32].into(), FINALIZED_HEIGHT);
+    for _ in 0..2 * SUBSCRIPTION_BUFFER {
+        sender.notify(FinalizedJustification {
+            justification: AlephJustification::V2(SignatureSet::with_size(0)),
+            hash: block.hash(),
+            number: block.header.number,
+            session: SessionId(0),
+        });
+        let received = timeout(Duration::from_millis(50), keeping_up.next())
+            .await
+            .expect("the justification should be broadcast")
+            .unwrap();
+        assert_eq!(received.hash, block.hash());
+    }
+
+    let buffered = timeout(Duration::from_millis(50), lagging.collect::<Vec<_>>())
+        .await
+        .expect("the lagging subscription should end");
+    assert!(buffered.len() > SUBSCRIPTION_BUFFER);
+    assert!(buffered.len() < 2 * SUBSCRIPTION_BUFFER);
+}
+
 #[tokio::test(threaded_scheduler)]
 async fn ignores_notifications_from_future_session() {
     run_test(
@@ -1,6 +1,6 @@
 use sp_runtime::traits::Block;
 
-use crate::network::RequestBlocks;
+use crate::new_network::RequestBlocks;
 use crate::testing::mocks::single_action_mock::SingleActionMock;
 use crate::testing::mocks::{TBlock, THash, TNumber};
 
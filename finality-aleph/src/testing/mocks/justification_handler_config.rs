@@ -50,6 +50,7 @@ impl JustificationRequestDelay for JustificationRequestDelayImpl {
 
 const DEFAULT_VERIFIER_TIMEOUT_MS: u64 = 10u64;
 const DEFAULT_NOTIFICATION_TIMEOUT_MS: u64 = 10u64;
+const DEFAULT_VERIFIED_CACHE_CAPACITY: usize = 16;
 
 impl JustificationHandlerConfig<TBlock, JustificationRequestDelayImpl> {
     pub(crate) fn new(request_policy: AcceptancePolicy) -> Self {
@@ -58,6 +59,9 @@ impl JustificationHandlerConfig<TBlock, JustificationRequestDelayImpl> {
             metrics: None,
             verifier_timeout: Duration::from_millis(DEFAULT_VERIFIER_TIMEOUT_MS),
             notification_timeout: Duration::from_millis(DEFAULT_NOTIFICATION_TIMEOUT_MS),
+            conflicting_justification_tx: None,
+            justification_sender: None,
+            verified_cache_capacity: DEFAULT_VERIFIED_CACHE_CAPACITY,
         }
     }
 }
@@ -69,6 +73,9 @@ impl Clone for JustificationHandlerConfig<TBlock, JustificationRequestDelayImpl>
             metrics: self.metrics.clone(),
             verifier_timeout: self.verifier_timeout,
             notification_timeout: self.notification_timeout,
+            conflicting_justification_tx: self.conflicting_justification_tx.clone(),
+            justification_sender: self.justification_sender.clone(),
+            verified_cache_capacity: self.verified_cache_capacity,
         }
     }
 }
@@ -2,7 +2,8 @@ use std::sync::{Arc, Mutex};
 
 use crate::justification::{AlephJustification, SessionInfo, SessionInfoProvider, Verifier};
 use crate::testing::mocks::{AcceptancePolicy, TBlock, THash, TNumber};
-use crate::{last_block_of_session, session_id_from_block_num, SessionPeriod};
+use crate::{last_block_of_session, session_id_from_block_num, SessionPeriod, SessionSchedule};
+use aleph_primitives::DEFAULT_MILLISECS_PER_BLOCK;
 
 pub(crate) struct VerifierWrapper {
     acceptance_policy: Arc<Mutex<AcceptancePolicy>>,
@@ -15,14 +16,14 @@ impl Verifier<TBlock> for VerifierWrapper {
 }
 
 pub(crate) struct SessionInfoProviderImpl {
-    session_period: SessionPeriod,
+    session_schedule: SessionSchedule,
     acceptance_policy: Arc<Mutex<AcceptancePolicy>>,
 }
 
 impl SessionInfoProviderImpl {
     pub(crate) fn new(session_period: SessionPeriod, acceptance_policy: AcceptancePolicy) -> Self {
         Self {
-            session_period,
+            session_schedule: SessionSchedule::new(session_period.0, DEFAULT_MILLISECS_PER_BLOCK),
             acceptance_policy: Arc::new(Mutex::new(acceptance_policy)),
         }
     }
@@ -30,12 +31,12 @@ impl SessionInfoProviderImpl {
 
 impl SessionInfoProvider<TBlock, VerifierWrapper> for SessionInfoProviderImpl {
     fn for_block_num(&self, number: TNumber) -> SessionInfo<TBlock, VerifierWrapper> {
-        let current_session = session_id_from_block_num::<TBlock>(number, self.session_period);
+        let current_session = session_id_from_block_num::<TBlock>(number, &self.session_schedule);
         SessionInfo {
             current_session,
             last_block_height: last_block_of_session::<TBlock>(
                 current_session,
-                self.session_period,
+                &self.session_schedule,
             ),
             verifier: match &*self.acceptance_policy.lock().unwrap() {
                 AcceptancePolicy::Unavailable => None,
@@ -1,4 +1,4 @@
 mod data_io;
 mod justification;
-mod mocks;
+pub(crate) mod mocks;
 mod network;
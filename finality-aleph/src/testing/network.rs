@@ -1,9 +1,9 @@
 use crate::{
     crypto::{AuthorityPen, AuthorityVerifier, KeyBox},
     network::{
-        AuthData, ConsensusNetwork, DataNetwork, InternalMessage, MetaMessage, Network, PeerId,
-        Recipient,
+        AuthData, ConsensusNetwork, DataNetwork, InternalMessage, MetaMessage, Network, Recipient,
     },
+    new_network::PeerId,
     AuthorityId, SessionId,
 };
 use aleph_bft::{Index, KeyBox as _, NodeIndex};
@@ -0,0 +1,245 @@
+//! Timings and limits of the finality gadget, which are tuned per deployment. Durations are given
+//! in milliseconds, so that the tuning can be conveniently written down in configuration files.
+use crate::data_io::DataStoreConfig;
+use serde::Deserialize;
+use std::{fmt, time::Duration};
+
+/// Tuning of AlephBFT. The parameters the whole committee has to agree on are not tuned locally,
+/// they are read from the chain for every session.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConsensusTuning {
+    /// How long to wait before creating the first unit.
+    pub first_unit_delay_ms: u64,
+    /// The round from which the unit creation delay grows exponentially.
+    pub unit_creation_slowdown_start: usize,
+    /// How long to wait before broadcasting a unit for the first time.
+    pub unit_broadcast_delay_ms: u64,
+    /// How many times the delay grows with every following broadcast of the same unit.
+    pub unit_broadcast_slowdown_base: f64,
+    /// How often AlephBFT checks whether it should act.
+    pub tick_interval_ms: u64,
+    /// How long to wait before repeating requests for missing units.
+    pub requests_interval_ms: u64,
+}
+
+impl Default for ConsensusTuning {
+    fn default() -> Self {
+        ConsensusTuning {
+            first_unit_delay_ms: 2000,
+            unit_creation_slowdown_start: 5000,
+            unit_broadcast_delay_ms: 4000,
+            unit_broadcast_slowdown_base: 2.,
+            tick_interval_ms: 100,
+            requests_interval_ms: 3000,
+        }
+    }
+}
+
+/// Tuning of the aggregation of block signatures.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AggregatorTuning {
+    /// How long to wait before rebroadcasting a signature for the first time, the delay doubles
+    /// with every following rebroadcast.
+    pub rmc_delay_ms: u64,
+}
+
+impl Default for AggregatorTuning {
+    fn default() -> Self {
+        AggregatorTuning { rmc_delay_ms: 500 }
+    }
+}
+
+/// Tuning of the store holding back consensus data until the blocks it mentions are imported.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DataStoreTuning {
+    /// How many imported blocks to remember.
+    pub available_blocks_cache_capacity: usize,
+    /// How many pending messages to keep at most.
+    pub message_id_boundary: u64,
+    /// How often to drop the messages waiting for too long.
+    pub periodic_maintenance_interval_ms: u64,
+    /// How long to wait for a block before requesting it.
+    pub request_block_after_ms: u64,
+}
+
+impl Default for DataStoreTuning {
+    fn default() -> Self {
+        let config = DataStoreConfig::default();
+        DataStoreTuning {
+            available_blocks_cache_capacity: config.available_blocks_cache_capacity,
+            message_id_boundary: config.message_id_boundary,
+            periodic_maintenance_interval_ms: config.periodic_maintenance_interval.as_millis()
+                as u64,
+            request_block_after_ms: config.request_block_after.as_millis() as u64,
+        }
+    }
+}
+
+impl From<&DataStoreTuning> for DataStoreConfig {
+    fn from(tuning: &DataStoreTuning) -> DataStoreConfig {
+        DataStoreConfig {
+            available_blocks_cache_capacity: tuning.available_blocks_cache_capacity,
+            message_id_boundary: tuning.message_id_boundary,
+            periodic_maintenance_interval: Duration::from_millis(
+                tuning.periodic_maintenance_interval_ms,
+            ),
+            request_block_after: Duration::from_millis(tuning.request_block_after_ms),
+        }
+    }
+}
+
+/// Tuning of the finalization of blocks with justifications.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JustificationTuning {
+    /// How long to wait when the verifier of the session is not yet available.
+    pub verifier_timeout_ms: u64,
+    /// How long to wait for a justification before requesting one.
+    pub notification_timeout_ms: u64,
+    /// How many verified justifications to remember.
+    pub verified_cache_capacity: usize,
+}
+
+impl Default for JustificationTuning {
+    fn default() -> Self {
+        JustificationTuning {
+            verifier_timeout_ms: 500,
+            notification_timeout_ms: 1000,
+            verified_cache_capacity: 1024,
+        }
+    }
+}
+
+/// All the tuning of the finality gadget. Anything not given explicitly takes the default value.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlephTuning {
+    pub consensus: ConsensusTuning,
+    pub aggregator: AggregatorTuning,
+    pub data_store: DataStoreTuning,
+    pub justification: JustificationTuning,
+}
+
+/// A value of the tuning that the gadget cannot run with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TuningError {
+    /// The named value has to be positive.
+    NotPositive(&'static str),
+    /// The named growth factor of a delay cannot make it shrink.
+    Shrinking(&'static str),
+}
+
+impl fmt::Display for TuningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TuningError::NotPositive(name) => write!(f, "{} has to be positive", name),
+            TuningError::Shrinking(name) => write!(f, "{} has to be at least 1", name),
+        }
+    }
+}
+
+fn positive<T: Default + PartialOrd>(value: T, name: &'static str) -> Result<(), TuningError> {
+    match value > T::default() {
+        true => Ok(()),
+        false => Err(TuningError::NotPositive(name)),
+    }
+}
+
+fn growing(value: f64, name: &'static str) -> Result<(), TuningError> {
+    match value >= 1. {
+        true => Ok(()),
+        false => Err(TuningError::Shrinking(name)),
+    }
+}
+
+impl AlephTuning {
+    /// Checks whether the gadget can run with the tuning.
+    pub fn validate(&self) -> Result<(), TuningError> {
+        let AlephTuning {
+            consensus,
+            aggregator,
+            data_store,
+            justification,
+        } = self;
+        positive(
+            consensus.unit_broadcast_delay_ms,
+            "consensus.unit_broadcast_delay_ms",
+        )?;
+        growing(
+            consensus.unit_broadcast_slowdown_base,
+            "consensus.unit_broadcast_slowdown_base",
+        )?;
+        positive(consensus.tick_interval_ms, "consensus.tick_interval_ms")?;
+        positive(
+            consensus.requests_interval_ms,
+            "consensus.requests_interval_ms",
+        )?;
+        positive(aggregator.rmc_delay_ms, "aggregator.rmc_delay_ms")?;
+        positive(
+            data_store.available_blocks_cache_capacity,
+            "data_store.available_blocks_cache_capacity",
+        )?;
+        positive(
+            data_store.message_id_boundary,
+            "data_store.message_id_boundary",
+        )?;
+        positive(
+            data_store.periodic_maintenance_interval_ms,
+            "data_store.periodic_maintenance_interval_ms",
+        )?;
+        positive(
+            justification.verifier_timeout_ms,
+            "justification.verifier_timeout_ms",
+        )?;
+        positive(
+            justification.notification_timeout_ms,
+            "justification.notification_timeout_ms",
+        )?;
+        positive(
+            justification.verified_cache_capacity,
+            "justification.verified_cache_capacity",
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_tuning_is_valid() {
+        assert_eq!(AlephTuning::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn rejects_tuning_that_cannot_run() {
+        let mut tuning = AlephTuning::default();
+        tuning.consensus.tick_interval_ms = 0;
+        assert_eq!(
+            tuning.validate(),
+            Err(TuningError::NotPositive("consensus.tick_interval_ms"))
+        );
+
+        let mut tuning = AlephTuning::default();
+        tuning.consensus.unit_broadcast_slowdown_base = 0.5;
+        assert_eq!(
+            tuning.validate(),
+            Err(TuningError::Shrinking(
+                "consensus.unit_broadcast_slowdown_base"
+            ))
+        );
+
+        let mut tuning = AlephTuning::default();
+        tuning.justification.verified_cache_capacity = 0;
+        assert_eq!(
+            tuning.validate(),
+            Err(TuningError::NotPositive(
+                "justification.verified_cache_capacity"
+            ))
+        );
+    }
+}
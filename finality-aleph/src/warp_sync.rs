@@ -0,0 +1,702 @@
+//! Warp sync based on the justifications of the last blocks of sessions. Every such block
+//! announces the authorities, the finality version and possibly the BLS keys of the next session
+//! in its digest, so a chain of them, each finalized by the authorities announced in the previous
+//! one, leads from genesis to the latest finalized session end without importing the blocks in
+//! between.
+use crate::{
+    crypto::{checked_bls_keys, AuthorityVerifier},
+    import::{
+        find_next_session_authorities, find_next_session_bls_keys,
+        find_next_session_finality_version,
+    },
+    justification::{decode_justification, AlephJustification},
+    session_api, AuthorityId, SessionId,
+};
+use aleph_primitives::{AlephSessionApi, BlsPublic, FinalityVersion, ALEPH_ENGINE_ID};
+use codec::{Decode, DecodeAll, Encode};
+use log::error;
+use parking_lot::Mutex;
+use sc_client_api::BlockBackend;
+use sc_network::warp_request_handler::{
+    AuthorityList, EncodedProof, SetId, VerificationResult, WarpSyncProvider,
+};
+use sp_api::{BlockId, NumberFor, ProvideRuntimeApi};
+use sp_blockchain::HeaderBackend;
+use sp_core::ed25519;
+use sp_runtime::{
+    traits::{Block, Header},
+    SaturatedConversion,
+};
+use std::{fmt, marker::PhantomData, sync::Arc};
+
+/// The maximal size of an encoded proof, slightly below the limit of the warp sync protocol.
+const MAX_WARP_SYNC_PROOF_SIZE: usize = 8 * 1024 * 1024 - 64;
+
+#[derive(Debug)]
+pub enum Error {
+    Client(sp_blockchain::Error),
+    Api(sp_api::ApiError),
+    UnknownBlock(String),
+    MissingJustification(String),
+    InvalidProof(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Client(e) => write!(f, "Client error {:?}", e),
+            Error::Api(e) => write!(f, "Runtime api error {:?}", e),
+            Error::UnknownBlock(block) => write!(f, "Unknown block {}", block),
+            Error::MissingJustification(block) => {
+                write!(f, "Missing justification of block {}", block)
+            }
+            Error::InvalidProof(reason) => write!(f, "Invalid warp sync proof: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<sp_blockchain::Error> for Error {
+    fn from(e: sp_blockchain::Error) -> Self {
+        Error::Client(e)
+    }
+}
+
+impl From<sp_api::ApiError> for Error {
+    fn from(e: sp_api::ApiError) -> Self {
+        Error::Api(e)
+    }
+}
+
+/// A session whose authorities, finality version and BLS keys, if it signs with them, are
+/// trusted, so that its justifications can be verified. The BLS keys have to be checked with
+/// `checked_bls_keys`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TrustedSession {
+    pub session: SessionId,
+    pub finality_version: FinalityVersion,
+    pub authorities: Vec<AuthorityId>,
+    pub bls_keys: Option<Vec<BlsPublic>>,
+}
+
+impl TrustedSession {
+    /// The verifier of the justifications of the session of the chain with the given genesis
+    /// hash.
+    pub(crate) fn verifier<H: Encode>(&self, genesis_hash: H) -> AuthorityVerifier {
+        let verifier = AuthorityVerifier::new(self.authorities.clone()).for_session(
+            genesis_hash.encode(),
+            self.session,
+            self.finality_version,
+        );
+        match &self.bls_keys {
+            Some(bls_keys) => verifier.with_bls_keys(bls_keys.clone()),
+            None => verifier,
+        }
+    }
+}
+
+/// The last block of a session together with the justification finalizing it.
+#[derive(Clone, Encode, Decode, Debug)]
+pub struct SessionEndProof<B: Block> {
+    pub(crate) header: B::Header,
+    pub(crate) justification: AlephJustification,
+}
+
+/// Reads the Aleph justification stored with the block of the given number, if any.
+pub(crate) fn stored_justification<B, C>(
+    client: &C,
+    number: NumberFor<B>,
+) -> Result<Option<AlephJustification>, Error>
+where
+    B: Block,
+    C: BlockBackend<B>,
+{
+    Ok(client
+        .justifications(&BlockId::Number(number))?
+        .and_then(|justifications| justifications.into_justification(ALEPH_ENGINE_ID))
+        .and_then(|justification| decode_justification(&justification).ok()))
+}
+
+/// Reads the last block of the given session together with its justification.
+pub(crate) fn session_end_proof<B, C>(
+    client: &C,
+    number: NumberFor<B>,
+) -> Result<SessionEndProof<B>, Error>
+where
+    B: Block,
+    C: HeaderBackend<B> + BlockBackend<B>,
+{
+    let header = client
+        .header(BlockId::Number(number))?
+        .ok_or_else(|| Error::UnknownBlock(format!("{:?}", number)))?;
+    let justification = stored_justification(client, number)?
+        .ok_or_else(|| Error::MissingJustification(format!("{:?}", number)))?;
+    Ok(SessionEndProof {
+        header,
+        justification,
+    })
+}
+
+/// Verifies consecutive session ends of the chain with the given genesis hash, starting with the
+/// trusted session, and returns the last session announced in them.
+pub(crate) fn verify_session_ends<B: Block>(
+    proofs: &[SessionEndProof<B>],
+    genesis_hash: B::Hash,
+    trusted: TrustedSession,
+) -> Result<TrustedSession, Error> {
+    let mut trusted = trusted;
+    for SessionEndProof {
+        header,
+        justification,
+    } in proofs
+    {
+        let hash = header.hash();
+        if !trusted
+            .verifier(genesis_hash)
+            .verify_justification(&hash.encode(), justification)
+        {
+            return Err(Error::InvalidProof(format!(
+                "bad justification of block {:?} in session {:?}",
+                hash, trusted.session
+            )));
+        }
+        let next_session = SessionId(trusted.session.0 + 1);
+        match (
+            find_next_session_authorities::<B>(header),
+            find_next_session_finality_version::<B>(header),
+        ) {
+            (Ok(Some((session, authorities))), Ok(Some((version_session, finality_version))))
+                if session == next_session
+                    && version_session == next_session
+                    && !authorities.is_empty() =>
+            {
+                // Sessions with invalid keys sign with ed25519 keys, like the nodes running them.
+                let bls_keys = match find_next_session_bls_keys::<B>(header) {
+                    Ok(Some((keys_session, keys))) if keys_session == next_session => {
+                        checked_bls_keys(keys)
+                    }
+                    Ok(_) => None,
+                    Err(_) => {
+                        return Err(Error::InvalidProof(format!(
+                            "block {:?} announces invalid BLS keys",
+                            hash
+                        )))
+                    }
+                };
+                trusted = TrustedSession {
+                    session,
+                    finality_version,
+                    authorities,
+                    bls_keys,
+                };
+            }
+            _ => {
+                return Err(Error::InvalidProof(format!(
+                    "block {:?} does not announce the authorities and the finality version of session {:?}",
+                    hash, next_session
+                )))
+            }
+        }
+    }
+    Ok(trusted)
+}
+
+/// Consecutive session ends, starting with the first session that ends after the requested block.
+#[derive(Clone, Encode, Decode, Debug)]
+pub struct WarpSyncProof<B: Block> {
+    proofs: Vec<SessionEndProof<B>>,
+    is_finished: bool,
+}
+
+impl<B: Block> WarpSyncProof<B> {
+    /// Collects the ends of the sessions finalized after the block `begin`, until the latest
+    /// finalized one or until the proof reaches the size limit.
+    fn generate<C>(client: &C, begin: B::Hash) -> Result<Self, Error>
+    where
+        C: HeaderBackend<B> + BlockBackend<B> + ProvideRuntimeApi<B>,
+        C::Api: AlephSessionApi<B>,
+    {
+        let info = client.info();
+        let begin_number = *client
+            .header(BlockId::Hash(begin))?
+            .ok_or_else(|| Error::UnknownBlock(format!("{:?}", begin)))?
+            .number();
+        if begin_number > info.finalized_number {
+            return Err(Error::InvalidProof(
+                "the requested block is not finalized".into(),
+            ));
+        }
+        let schedule = session_api::session_schedule(client, &BlockId::Hash(info.finalized_hash))?;
+        let finalized_number: u32 = info.finalized_number.saturated_into();
+
+        let mut session = schedule.session_of_block(begin_number.saturated_into::<u32>() + 1);
+        let mut proofs = Vec::new();
+        let mut proofs_size = 0;
+        let mut is_finished = true;
+        while schedule.last_block_of_session(session) <= finalized_number {
+            let number: NumberFor<B> = schedule.last_block_of_session(session).into();
+            let proof = session_end_proof(client, number)?;
+            let proof_size = proof.encoded_size();
+            if proofs_size + proof_size > MAX_WARP_SYNC_PROOF_SIZE {
+                is_finished = false;
+                break;
+            }
+            proofs_size += proof_size;
+            proofs.push(proof);
+            session += 1;
+        }
+
+        Ok(WarpSyncProof {
+            proofs,
+            is_finished,
+        })
+    }
+
+    /// Verifies the proofs one by one, starting with the trusted session, and returns the last
+    /// session announced in them.
+    fn verify(
+        &self,
+        genesis_hash: B::Hash,
+        trusted: TrustedSession,
+    ) -> Result<TrustedSession, Error> {
+        verify_session_ends(&self.proofs, genesis_hash, trusted)
+    }
+}
+
+// The warp sync protocol of substrate speaks in terms of GRANDPA authorities, which are ed25519
+// keys just like ours, so the conversions are lossless.
+fn to_authority_list(authorities: Vec<AuthorityId>) -> AuthorityList {
+    authorities
+        .into_iter()
+        .map(|id| (ed25519::Public::from(id).into(), 1))
+        .collect()
+}
+
+fn from_authority_list(authorities: AuthorityList) -> Vec<AuthorityId> {
+    authorities
+        .into_iter()
+        .map(|(id, _)| ed25519::Public::from(id).into())
+        .collect()
+}
+
+/// Serves and verifies warp sync proofs made of session end justifications. Session ids are used
+/// as the authority set ids. The warp sync protocol only passes the authorities of the set to
+/// continue from, so the last session reached is remembered here.
+pub struct AlephWarpSyncProvider<B, C> {
+    client: Arc<C>,
+    last_verified: Mutex<Option<TrustedSession>>,
+    _phantom: PhantomData<B>,
+}
+
+impl<B, C> AlephWarpSyncProvider<B, C> {
+    pub fn new(client: Arc<C>) -> Self {
+        AlephWarpSyncProvider {
+            client,
+            last_verified: Mutex::new(None),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<B, C> AlephWarpSyncProvider<B, C>
+where
+    B: Block,
+    C: HeaderBackend<B> + ProvideRuntimeApi<B>,
+    C::Api: AlephSessionApi<B>,
+{
+    /// The session warp sync continues from, which is either the first one, known from the
+    /// genesis state, or the last one reached.
+    fn trusted_session(
+        &self,
+        session: SessionId,
+        authorities: Vec<AuthorityId>,
+    ) -> Result<TrustedSession, Error> {
+        match &*self.last_verified.lock() {
+            Some(trusted) if trusted.session == session && trusted.authorities == authorities => {
+                return Ok(trusted.clone())
+            }
+            _ => {}
+        }
+        if session != SessionId(0) {
+            return Err(Error::InvalidProof(format!(
+                "the finality version and BLS keys of session {:?} are unknown",
+                session
+            )));
+        }
+        let genesis = BlockId::Hash(self.client.info().genesis_hash);
+        Ok(TrustedSession {
+            session,
+            finality_version: session_api::finality_version(
+                self.client.as_ref(),
+                &genesis,
+                session.0,
+            )?,
+            authorities,
+            bls_keys: session_api::bls_keys_for_session(self.client.as_ref(), &genesis, session.0)?
+                .and_then(checked_bls_keys),
+        })
+    }
+}
+
+impl<B, C> WarpSyncProvider<B> for AlephWarpSyncProvider<B, C>
+where
+    B: Block,
+    C: HeaderBackend<B> + BlockBackend<B> + ProvideRuntimeApi<B> + Send + Sync,
+    C::Api: AlephSessionApi<B>,
+{
+    fn generate(
+        &self,
+        start: B::Hash,
+    ) -> Result<EncodedProof, Box<dyn std::error::Error + Send + Sync>> {
+        let proof = WarpSyncProof::<B>::generate(self.client.as_ref(), start)?;
+        Ok(EncodedProof(proof.encode()))
+    }
+
+    fn verify(
+        &self,
+        proof: &EncodedProof,
+        set_id: SetId,
+        authorities: AuthorityList,
+    ) -> Result<VerificationResult<B>, Box<dyn std::error::Error + Send + Sync>> {
+        let EncodedProof(proof) = proof;
+        let proof = WarpSyncProof::<B>::decode_all(proof)
+            .map_err(|e| Error::InvalidProof(format!("cannot decode: {:?}", e)))?;
+        let last_header = proof
+            .proofs
+            .last()
+            .map(|proof| proof.header.clone())
+            .ok_or_else(|| Error::InvalidProof("empty proof".into()))?;
+        let trusted = self.trusted_session(
+            SessionId(set_id.saturated_into()),
+            from_authority_list(authorities),
+        )?;
+        let trusted = proof.verify(self.client.info().genesis_hash, trusted)?;
+        *self.last_verified.lock() = Some(trusted.clone());
+        let set_id = trusted.session.0.into();
+        let authorities = to_authority_list(trusted.authorities);
+        if proof.is_finished {
+            Ok(VerificationResult::Complete(
+                set_id,
+                authorities,
+                last_header,
+            ))
+        } else {
+            Ok(VerificationResult::Partial(
+                set_id,
+                authorities,
+                last_header.hash(),
+            ))
+        }
+    }
+
+    fn current_authorities(&self) -> AuthorityList {
+        let genesis_hash = self.client.info().genesis_hash;
+        match self
+            .client
+            .runtime_api()
+            .authorities(&BlockId::Hash(genesis_hash))
+        {
+            Ok(authorities) => to_authority_list(authorities),
+            Err(e) => {
+                error!(target: "afa", "Cannot read the genesis authorities: {:?}", e);
+                Vec::new()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        crypto::{AuthorityPen, BlsKeystore, BlsPen, SigningDomain, SigningPurpose},
+        testing::mocks::{create_block, TBlock},
+    };
+    use aleph_justification::{bls, AggregatedSignature, AlephSignature, SignatureSet, SignerSet};
+    use aleph_primitives::{ConsensusLog, KEY_TYPE};
+    use sp_keystore::{testing::KeyStore, CryptoStore};
+    use sp_runtime::DigestItem;
+
+    async fn generate_keys(names: &[&str]) -> (Vec<AuthorityPen>, Vec<AuthorityId>) {
+        let key_store = Arc::new(KeyStore::new());
+        let mut pens = Vec::new();
+        let mut authorities = Vec::new();
+        for name in names {
+            let pk = key_store
+                .ed25519_generate_new(KEY_TYPE, Some(name))
+                .await
+                .unwrap();
+            authorities.push(AuthorityId::from(pk));
+            pens.push(
+                AuthorityPen::new(AuthorityId::from(pk), key_store.clone())
+                    .await
+                    .unwrap(),
+            );
+        }
+        (pens, authorities)
+    }
+
+    async fn generate_bls_keys(names: &[&str]) -> (Vec<BlsPen>, Vec<AuthorityId>) {
+        let key_store = Arc::new(KeyStore::new());
+        let bls_keystore = BlsKeystore::in_memory();
+        let mut pens = Vec::new();
+        let mut authorities = Vec::new();
+        for name in names {
+            let pk = key_store
+                .ed25519_generate_new(KEY_TYPE, Some(name))
+                .await
+                .unwrap();
+            authorities.push(AuthorityId::from(pk));
+            let bls_key = bls_keystore.generate_new().unwrap();
+            pens.push(BlsPen::new(&bls_key, &bls_keystore).unwrap());
+        }
+        (pens, authorities)
+    }
+
+    async fn session_end(
+        number: u64,
+        signers: &[AuthorityPen],
+        next_session: u32,
+        next_authorities: Vec<AuthorityId>,
+    ) -> SessionEndProof<TBlock> {
+        let mut header = create_block([number as u8; 32].into(), number).header;
+        header.digest.push(DigestItem::Consensus(
+            ALEPH_ENGINE_ID,
+            ConsensusLog::NextSessionAuthorities {
+                session: next_session,
+                authorities: next_authorities,
+            }
+            .encode(),
+        ));
+        header.digest.push(DigestItem::Consensus(
+            ALEPH_ENGINE_ID,
+            ConsensusLog::<AuthorityId>::NextSessionFinalityVersion {
+                session: next_session,
+                version: FinalityVersion::LEGACY,
+            }
+            .encode(),
+        ));
+        let message = header.hash().encode();
+        let mut signature = SignatureSet::with_size(signers.len());
+        for (index, pen) in signers.iter().enumerate() {
+            signature = signature.add_signature(&pen.sign(&message).await.into(), index);
+        }
+        SessionEndProof {
+            header,
+            justification: AlephJustification::new(
+                SessionId(next_session - 1),
+                AlephSignature::Signatures(signature),
+            ),
+        }
+    }
+
+    fn trusted(
+        session: u32,
+        finality_version: FinalityVersion,
+        authorities: Vec<AuthorityId>,
+    ) -> TrustedSession {
+        TrustedSession {
+            session: SessionId(session),
+            finality_version,
+            authorities,
+            bls_keys: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn follows_announced_authorities() {
+        let (first_pens, first) = generate_keys(&["//Alice", "//Bob", "//Charlie"]).await;
+        let (second_pens, second) = generate_keys(&["//Dave", "//Eve", "//Ferdie"]).await;
+        let proof = WarpSyncProof {
+            proofs: vec![
+                session_end(9, &first_pens, 1, second.clone()).await,
+                session_end(19, &second_pens, 2, first.clone()).await,
+            ],
+            is_finished: true,
+        };
+
+        assert_eq!(
+            proof
+                .verify(
+                    Default::default(),
+                    trusted(0, FinalityVersion::LEGACY, first.clone())
+                )
+                .unwrap(),
+            trusted(2, FinalityVersion::LEGACY, first)
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_justifications_of_wrong_authorities() {
+        let (first_pens, first) = generate_keys(&["//Alice", "//Bob", "//Charlie"]).await;
+        let (_, second) = generate_keys(&["//Dave", "//Eve", "//Ferdie"]).await;
+        let proof = WarpSyncProof {
+            proofs: vec![
+                session_end(9, &first_pens, 1, second).await,
+                session_end(19, &first_pens, 2, first.clone()).await,
+            ],
+            is_finished: true,
+        };
+
+        assert!(proof
+            .verify(
+                Default::default(),
+                trusted(0, FinalityVersion::LEGACY, first)
+            )
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_skipped_sessions() {
+        let (pens, authorities) = generate_keys(&["//Alice", "//Bob", "//Charlie"]).await;
+        let proof = WarpSyncProof {
+            proofs: vec![session_end(19, &pens, 2, authorities.clone()).await],
+            is_finished: true,
+        };
+
+        assert!(proof
+            .verify(
+                Default::default(),
+                trusted(0, FinalityVersion::LEGACY, authorities)
+            )
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_session_ends_without_finality_version() {
+        let (pens, authorities) = generate_keys(&["//Alice", "//Bob", "//Charlie"]).await;
+        let mut session_end = session_end(9, &pens, 1, authorities.clone()).await;
+        session_end.header.digest.pop();
+        let message = session_end.header.hash().encode();
+        let mut signature = SignatureSet::with_size(pens.len());
+        for (index, pen) in pens.iter().enumerate() {
+            signature = signature.add_signature(&pen.sign(&message).await.into(), index);
+        }
+        session_end.justification =
+            AlephJustification::new(SessionId(0), AlephSignature::Signatures(signature));
+        let proof = WarpSyncProof {
+            proofs: vec![session_end],
+            is_finished: true,
+        };
+
+        assert!(proof
+            .verify(
+                Default::default(),
+                trusted(0, FinalityVersion::LEGACY, authorities)
+            )
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn verifies_domain_separated_justifications_of_own_chain() {
+        let (pens, authorities) = generate_keys(&["//Alice", "//Bob", "//Charlie"]).await;
+        let mut session_end = session_end(9, &pens, 1, authorities.clone()).await;
+        let genesis_hash: <TBlock as Block>::Hash = [1u8; 32].into();
+        let domain = SigningDomain::new(
+            SigningPurpose::Finality,
+            genesis_hash.encode(),
+            SessionId(0),
+        );
+        let message = session_end.header.hash().encode();
+        let mut signature = SignatureSet::with_size(pens.len());
+        for (index, pen) in pens.iter().enumerate() {
+            let pen = pen.in_domain(Some(domain.clone()));
+            signature = signature.add_signature(&pen.sign(&message).await.into(), index);
+        }
+        session_end.justification = AlephJustification::domain_separated(
+            SessionId(0),
+            AlephSignature::Signatures(signature),
+        );
+        let proof = WarpSyncProof {
+            proofs: vec![session_end],
+            is_finished: true,
+        };
+
+        assert!(proof
+            .verify(
+                genesis_hash,
+                trusted(0, FinalityVersion::DOMAIN_SEPARATED, authorities.clone())
+            )
+            .is_ok());
+        assert!(proof
+            .verify(
+                Default::default(),
+                trusted(0, FinalityVersion::DOMAIN_SEPARATED, authorities.clone())
+            )
+            .is_err());
+        // Sessions signing bare hashes do not accept domain separated justifications.
+        assert!(proof
+            .verify(
+                genesis_hash,
+                trusted(0, FinalityVersion::LEGACY, authorities)
+            )
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn follows_announced_bls_keys() {
+        let (first_pens, first) = generate_keys(&["//Alice", "//Bob", "//Charlie"]).await;
+        let (second_pens, second) = generate_bls_keys(&["//Dave", "//Eve", "//Ferdie"]).await;
+        let bls_keys = second_pens.iter().map(BlsPen::public).collect::<Vec<_>>();
+
+        let mut first_end = session_end(9, &first_pens, 1, second.clone()).await;
+        first_end.header.digest.push(DigestItem::Consensus(
+            ALEPH_ENGINE_ID,
+            ConsensusLog::<AuthorityId>::NextSessionBlsKeys {
+                session: 1,
+                keys: second_pens.iter().map(BlsPen::registration).collect(),
+            }
+            .encode(),
+        ));
+        let message = first_end.header.hash().encode();
+        let mut signature = SignatureSet::with_size(first_pens.len());
+        for (index, pen) in first_pens.iter().enumerate() {
+            signature = signature.add_signature(&pen.sign(&message).await.into(), index);
+        }
+        first_end.justification =
+            AlephJustification::new(SessionId(0), AlephSignature::Signatures(signature));
+
+        let mut second_end = session_end(19, &first_pens, 2, first.clone()).await;
+        let message = second_end.header.hash().encode();
+        let mut signers = SignerSet::with_size(second_pens.len());
+        let mut signature = second_pens[0].sign(&message);
+        signers.insert(0);
+        for (index, pen) in second_pens.iter().enumerate().skip(1) {
+            signature = bls::aggregate(&signature, &pen.sign(&message)).unwrap();
+            signers.insert(index);
+        }
+        second_end.justification = AlephJustification::new(
+            SessionId(1),
+            AlephSignature::Aggregated(AggregatedSignature { signers, signature }),
+        );
+
+        let proof = WarpSyncProof {
+            proofs: vec![first_end.clone()],
+            is_finished: true,
+        };
+        assert_eq!(
+            proof
+                .verify(
+                    Default::default(),
+                    trusted(0, FinalityVersion::LEGACY, first.clone())
+                )
+                .unwrap()
+                .bls_keys,
+            Some(bls_keys)
+        );
+        let proof = WarpSyncProof {
+            proofs: vec![first_end, second_end],
+            is_finished: true,
+        };
+        assert_eq!(
+            proof
+                .verify(
+                    Default::default(),
+                    trusted(0, FinalityVersion::LEGACY, first.clone())
+                )
+                .unwrap(),
+            trusted(2, FinalityVersion::LEGACY, first)
+        );
+    }
+}
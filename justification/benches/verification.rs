@@ -0,0 +1,52 @@
+use aleph_justification::{AlephJustification, AlephSignature, AuthorityVerifier, SignatureSet};
+use aleph_primitives::{AuthorityPair, AuthoritySignature, FinalityVersion, SessionId};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use sp_core::Pair;
+
+const HASH: &[u8] = b"the hash of a finalized block";
+
+fn committee(size: usize) -> (Vec<AuthorityPair>, AuthorityVerifier) {
+    let pairs: Vec<_> = (0..size)
+        .map(|seed| AuthorityPair::from_seed(&[seed as u8; 32]))
+        .collect();
+    let verifier = AuthorityVerifier::new(pairs.iter().map(|pair| pair.public()).collect())
+        .for_session(Vec::new(), SessionId(0), FinalityVersion::LEGACY);
+    (pairs, verifier)
+}
+
+fn signatures(pairs: &[AuthorityPair]) -> SignatureSet<AuthoritySignature> {
+    pairs.iter().enumerate().fold(
+        SignatureSet::with_size(pairs.len()),
+        |set, (index, pair)| set.add_signature(&pair.sign(HASH), index),
+    )
+}
+
+fn verification(c: &mut Criterion) {
+    let mut group = c.benchmark_group("justification verification");
+    for size in [4, 10, 50, 100, 200].iter().copied() {
+        let (pairs, verifier) = committee(size);
+        let signatures = signatures(&pairs);
+        group.bench_with_input(
+            BenchmarkId::new("one by one", size),
+            &signatures,
+            |b, signatures| {
+                b.iter(|| {
+                    signatures
+                        .iter()
+                        .all(|(index, sgn)| verifier.verify(HASH, sgn, index))
+                })
+            },
+        );
+        let justification =
+            AlephJustification::new(SessionId(0), AlephSignature::Signatures(signatures));
+        group.bench_with_input(
+            BenchmarkId::new("batched", size),
+            &justification,
+            |b, justification| b.iter(|| verifier.verify_justification(HASH, justification)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, verification);
+criterion_main!(benches);
@@ -0,0 +1,137 @@
+//! Batch verification of ed25519 signatures, which is considerably faster than checking the
+//! signatures one by one once there are more than a few of them.
+//!
+//! Batches are verified with the cofactored equation, while single signatures are not. The two
+//! only differ for points with a small order component, so a batch is only tried when every key
+//! and every signature commitment is a canonically encoded point of the prime order subgroup.
+//! Otherwise the signatures are checked one by one, so the result is always the one of the single
+//! checks.
+//!
+//! Large sets of signatures are split into chunks verified on separate threads.
+use aleph_primitives::{AuthorityId, AuthoritySignature};
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use ed25519_dalek::{PublicKey, Signature};
+use sp_application_crypto::RuntimeAppPublic;
+use sp_std::{convert::TryFrom, vec::Vec};
+use std::thread;
+
+/// Sets of at least this many signatures are verified on more than one thread.
+const PARALLEL_THRESHOLD: usize = 128;
+/// The largest number of threads verifying one set of signatures.
+const MAX_THREADS: usize = 8;
+
+fn dalek_key(key: &AuthorityId) -> Option<PublicKey> {
+    PublicKey::from_bytes(key.as_ref()).ok()
+}
+
+fn dalek_signature(sgn: &AuthoritySignature) -> Option<Signature> {
+    Signature::try_from(sgn.as_ref()).ok()
+}
+
+/// Whether the bytes canonically encode a point without a small order component.
+fn is_torsion_free(bytes: &[u8]) -> bool {
+    if bytes.len() != 32 {
+        return false;
+    }
+    let compressed = CompressedEdwardsY::from_slice(bytes);
+    match compressed.decompress() {
+        Some(point) => point.compress() == compressed && point.is_torsion_free(),
+        None => false,
+    }
+}
+
+/// Whether the cofactored batch equation holds exactly when the single checks do.
+fn is_batchable(key: &AuthorityId, sgn: &AuthoritySignature) -> bool {
+    let sgn: &[u8] = sgn.as_ref();
+    is_torsion_free(key.as_ref()) && sgn.len() == 64 && is_torsion_free(&sgn[..32])
+}
+
+fn verify_batch(msg: &[u8], signatures: &[(&AuthorityId, &AuthoritySignature)]) -> bool {
+    if !signatures.iter().all(|(key, sgn)| is_batchable(key, sgn)) {
+        return false;
+    }
+    let keys = signatures
+        .iter()
+        .map(|(key, _)| dalek_key(key))
+        .collect::<Option<Vec<_>>>();
+    let sgns = signatures
+        .iter()
+        .map(|(_, sgn)| dalek_signature(sgn))
+        .collect::<Option<Vec<_>>>();
+    match (keys, sgns) {
+        (Some(keys), Some(sgns)) => {
+            let msgs = sp_std::vec![msg; signatures.len()];
+            ed25519_dalek::verify_batch(&msgs, &sgns, &keys).is_ok()
+        }
+        _ => false,
+    }
+}
+
+/// The positions of the incorrect signatures, checked at once if possible and one by one only if
+/// that fails.
+fn invalid_positions(msg: &[u8], signatures: &[(&AuthorityId, &AuthoritySignature)]) -> Vec<usize> {
+    if signatures.is_empty() || verify_batch(msg, signatures) {
+        return Vec::new();
+    }
+    signatures
+        .iter()
+        .enumerate()
+        .filter(|(_, (key, sgn))| !key.verify(&msg, *sgn))
+        .map(|(position, _)| position)
+        .collect()
+}
+
+/// The positions of the incorrect signatures, with the signatures split into chunks verified on
+/// separate threads.
+fn invalid_positions_in_parallel(
+    msg: &[u8],
+    signatures: &[(&AuthorityId, &AuthoritySignature)],
+) -> Vec<usize> {
+    let chunk_size = (signatures.len() + MAX_THREADS - 1) / MAX_THREADS;
+    let handles = signatures
+        .chunks(chunk_size)
+        .map(|chunk| {
+            let msg = msg.to_vec();
+            let chunk = chunk
+                .iter()
+                .map(|(key, sgn)| ((*key).clone(), (*sgn).clone()))
+                .collect::<Vec<_>>();
+            thread::spawn(move || {
+                let chunk = chunk
+                    .iter()
+                    .map(|(key, sgn)| (key, sgn))
+                    .collect::<Vec<_>>();
+                invalid_positions(&msg, &chunk)
+            })
+        })
+        .collect::<Vec<_>>();
+    handles
+        .into_iter()
+        .enumerate()
+        .flat_map(|(index, handle)| {
+            handle
+                .join()
+                .expect("verification does not panic")
+                .into_iter()
+                .map(move |position| index * chunk_size + position)
+        })
+        .collect()
+}
+
+/// Verifies the signatures of the message made with the given keys. All of them are checked at
+/// once if possible, and only if that fails are they checked one by one to find the incorrect
+/// ones, whose positions are returned. Sets of at least `PARALLEL_THRESHOLD` signatures are split
+/// between up to `MAX_THREADS` threads, each handling its part in the same way.
+pub fn verify(
+    msg: &[u8],
+    signatures: &[(&AuthorityId, &AuthoritySignature)],
+) -> Result<(), Vec<usize>> {
+    let invalid = match signatures.len() < PARALLEL_THRESHOLD {
+        true => invalid_positions(msg, signatures),
+        false => invalid_positions_in_parallel(msg, signatures),
+    };
+    match invalid.is_empty() {
+        true => Ok(()),
+        false => Err(invalid),
+    }
+}
@@ -0,0 +1,155 @@
+//! BLS12-381 signatures with public keys in G1, following the proof of possession scheme of the
+//! IETF BLS signature draft. Aggregated signatures of a single message are only secure if every
+//! key was checked with `verify_possession`.
+use aleph_primitives::{BlsKeyRegistration, BlsPublic, BlsSignature};
+use blst::{
+    min_pk::{AggregateSignature, PublicKey, SecretKey, Signature},
+    BLST_ERROR,
+};
+use sp_std::vec::Vec;
+
+/// Domain separation tag of the signatures of messages.
+pub const SIGNATURE_DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+/// Domain separation tag of the proofs of possession.
+pub const POSSESSION_DST: &[u8] = b"BLS_POP_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+fn public_key(key: &BlsPublic) -> Option<PublicKey> {
+    PublicKey::key_validate(&key.0).ok()
+}
+
+fn signature(signature: &BlsSignature) -> Option<Signature> {
+    Signature::from_bytes(&signature.0).ok()
+}
+
+/// Verifies that the key is valid and its owner knows the secret key.
+pub fn verify_possession(registration: &BlsKeyRegistration) -> bool {
+    match (
+        public_key(&registration.key),
+        signature(&registration.proof_of_possession),
+    ) {
+        (Some(key), Some(proof)) => {
+            proof.verify(true, &registration.key.0, POSSESSION_DST, &[], &key, false)
+                == BLST_ERROR::BLST_SUCCESS
+        }
+        _ => false,
+    }
+}
+
+/// Generates a registration of a key derived from the seed, which has to be at least 32 bytes
+/// long.
+pub fn generate_registration(seed: &[u8]) -> Option<BlsKeyRegistration> {
+    let secret = SecretKey::key_gen(seed, &[]).ok()?;
+    let key = BlsPublic(secret.sk_to_pk().compress());
+    Some(BlsKeyRegistration {
+        key,
+        proof_of_possession: BlsSignature(secret.sign(&key.0, POSSESSION_DST, &[]).compress()),
+    })
+}
+
+/// Verifies a signature of the message made by the owner of the key.
+pub fn verify(msg: &[u8], sgn: &BlsSignature, key: &BlsPublic) -> bool {
+    match (public_key(key), signature(sgn)) {
+        (Some(key), Some(sgn)) => {
+            sgn.verify(true, msg, SIGNATURE_DST, &[], &key, false) == BLST_ERROR::BLST_SUCCESS
+        }
+        _ => false,
+    }
+}
+
+/// Aggregates two signatures of the same message into one.
+pub fn aggregate(first: &BlsSignature, second: &BlsSignature) -> Option<BlsSignature> {
+    let mut aggregated = AggregateSignature::from_signature(&signature(first)?);
+    aggregated.add_signature(&signature(second)?, true).ok()?;
+    Some(BlsSignature(aggregated.to_signature().compress()))
+}
+
+/// Verifies a signature of the message aggregated from the signatures of the owners of all the
+/// keys.
+pub fn verify_aggregated(msg: &[u8], sgn: &BlsSignature, keys: &[BlsPublic]) -> bool {
+    let keys = match keys.iter().map(public_key).collect::<Option<Vec<_>>>() {
+        Some(keys) if !keys.is_empty() => keys,
+        _ => return false,
+    };
+    let keys = keys.iter().collect::<Vec<_>>();
+    match signature(sgn) {
+        Some(sgn) => {
+            sgn.fast_aggregate_verify(true, msg, SIGNATURE_DST, &keys) == BLST_ERROR::BLST_SUCCESS
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secret(seed: u8) -> SecretKey {
+        SecretKey::key_gen(&[seed; 32], &[]).expect("the seed is long enough")
+    }
+
+    fn sign(seed: u8, msg: &[u8]) -> BlsSignature {
+        BlsSignature(secret(seed).sign(msg, SIGNATURE_DST, &[]).compress())
+    }
+
+    fn key(seed: u8) -> BlsPublic {
+        BlsPublic(secret(seed).sk_to_pk().compress())
+    }
+
+    #[test]
+    fn verifies_aggregated_signatures() {
+        let aggregated = aggregate(&sign(0, b"hash"), &sign(1, b"hash")).unwrap();
+
+        assert!(verify(b"hash", &sign(0, b"hash"), &key(0)));
+        assert!(verify_aggregated(b"hash", &aggregated, &[key(0), key(1)]));
+        assert!(!verify_aggregated(b"hash", &aggregated, &[key(0), key(2)]));
+        assert!(!verify_aggregated(
+            b"other hash",
+            &aggregated,
+            &[key(0), key(1)]
+        ));
+    }
+
+    #[test]
+    fn verifies_aggregated_signatures_through_host_functions() {
+        let aggregated = aggregate(&sign(0, b"hash"), &sign(1, b"hash")).unwrap();
+
+        assert!(crate::verify_aggregated(
+            b"hash",
+            &aggregated,
+            &[key(0), key(1)]
+        ));
+        assert!(!crate::verify_aggregated(
+            b"hash",
+            &aggregated,
+            &[key(0), key(2)]
+        ));
+        assert!(!crate::verify_aggregated(b"hash", &aggregated, &[]));
+    }
+
+    #[test]
+    fn verifies_proofs_of_possession() {
+        let key = key(0);
+        let registration = BlsKeyRegistration {
+            key,
+            proof_of_possession: BlsSignature(
+                secret(0).sign(&key.0, POSSESSION_DST, &[]).compress(),
+            ),
+        };
+        let stolen = BlsKeyRegistration {
+            key,
+            proof_of_possession: sign(0, &key.0),
+        };
+
+        assert!(verify_possession(&registration));
+        assert!(!verify_possession(&stolen));
+    }
+
+    #[test]
+    fn generates_registrations_with_valid_proofs() {
+        let registration = generate_registration(&[3; 32]).unwrap();
+
+        assert!(verify_possession(&registration));
+        assert_eq!(generate_registration(&[3; 32]), Some(registration));
+        assert!(generate_registration(&[3; 31]).is_none());
+    }
+}
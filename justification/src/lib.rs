@@ -0,0 +1,860 @@
+//! Aleph justifications and their verification, usable without the node stack, in particular in
+//! runtimes and light clients.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "batch")]
+pub mod batch;
+#[cfg(feature = "std")]
+pub mod bls;
+mod possession;
+
+pub use aleph_primitives::{SigningDomain, SigningPurpose};
+pub use possession::{aleph_bls, verify_aggregated, verify_possession};
+#[cfg(feature = "runtime-benchmarks")]
+pub use possession::{aleph_bls_benchmarking, generate_registration};
+
+use aleph_primitives::{
+    AuthorityId, AuthoritySignature, BlsPublic, BlsSignature, FinalityVersion, SessionId,
+};
+use codec::{Decode, DecodeAll, Encode, EncodeLike, Input, Output};
+use sp_application_crypto::RuntimeAppPublic;
+use sp_std::{vec, vec::Vec};
+
+/// Signatures of some of the members of a committee, indexed by their positions in it. Encoded
+/// the same way as the `SignatureSet` of AlephBFT.
+#[derive(Clone, Encode, Decode, Debug, PartialEq, Eq)]
+pub struct SignatureSet<S> {
+    signatures: Vec<Option<S>>,
+}
+
+impl<S: Clone> SignatureSet<S> {
+    /// An empty set for a committee of the given size.
+    pub fn with_size(size: usize) -> Self {
+        SignatureSet {
+            signatures: vec![None; size],
+        }
+    }
+
+    /// Adds the signature of the member of the given index. Signatures of members outside the
+    /// committee are ignored.
+    pub fn add_signature(mut self, signature: &S, index: usize) -> Self {
+        if let Some(slot) = self.signatures.get_mut(index) {
+            *slot = Some(signature.clone());
+        }
+        self
+    }
+}
+
+impl<S> SignatureSet<S> {
+    /// The size of the committee.
+    pub fn size(&self) -> usize {
+        self.signatures.len()
+    }
+
+    /// The signatures in the set together with the indices of their signers.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &S)> {
+        self.signatures
+            .iter()
+            .enumerate()
+            .filter_map(|(index, signature)| signature.as_ref().map(|signature| (index, signature)))
+    }
+}
+
+fn present<S>((index, signature): (usize, Option<S>)) -> Option<(usize, S)> {
+    signature.map(|signature| (index, signature))
+}
+
+impl<S> IntoIterator for SignatureSet<S> {
+    type Item = (usize, S);
+    type IntoIter = sp_std::iter::FilterMap<
+        sp_std::iter::Enumerate<vec::IntoIter<Option<S>>>,
+        fn((usize, Option<S>)) -> Option<(usize, S)>,
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.signatures
+            .into_iter()
+            .enumerate()
+            .filter_map(present as fn(_) -> _)
+    }
+}
+
+/// Members of a committee, indexed by their positions in it.
+#[derive(Clone, Encode, Decode, Debug, PartialEq, Eq)]
+pub struct SignerSet {
+    #[codec(compact)]
+    size: u32,
+    bits: Vec<u8>,
+}
+
+impl SignerSet {
+    /// An empty set for a committee of the given size.
+    pub fn with_size(size: usize) -> Self {
+        SignerSet {
+            size: size as u32,
+            bits: vec![0; (size + 7) / 8],
+        }
+    }
+
+    /// The size of the committee.
+    pub fn size(&self) -> usize {
+        self.size as usize
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        index < self.size()
+            && self
+                .bits
+                .get(index / 8)
+                .map_or(false, |byte| byte & (1 << (index % 8)) != 0)
+    }
+
+    /// Adds the member of the given index. Returns false if they are outside the committee or
+    /// already in the set.
+    pub fn insert(&mut self, index: usize) -> bool {
+        if index >= self.size() || self.contains(index) {
+            return false;
+        }
+        match self.bits.get_mut(index / 8) {
+            Some(byte) => {
+                *byte |= 1 << (index % 8);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The indices of the members in the set, in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.size()).filter(move |index| self.contains(*index))
+    }
+}
+
+/// A BLS signature aggregated from the signatures of the given members of a committee.
+#[derive(Clone, Encode, Decode, Debug, PartialEq, Eq)]
+pub struct AggregatedSignature {
+    pub signers: SignerSet,
+    pub signature: BlsSignature,
+}
+
+/// The signatures proving the finality of a block.
+#[derive(Clone, Encode, Decode, Debug, PartialEq, Eq)]
+pub enum AlephSignature {
+    /// Separate ed25519 signatures of the signers.
+    Signatures(SignatureSet<AuthoritySignature>),
+    /// A single BLS signature aggregated from the signatures of the signers. Only valid in
+    /// sessions whose authorities registered BLS keys.
+    Aggregated(AggregatedSignature),
+}
+
+impl AlephSignature {
+    /// The size of the committee that made the signature.
+    pub fn committee_size(&self) -> usize {
+        match self {
+            AlephSignature::Signatures(signatures) => signatures.size(),
+            AlephSignature::Aggregated(aggregated) => aggregated.signers.size(),
+        }
+    }
+
+    /// The indices of the signers, in increasing order.
+    pub fn signers(&self) -> Vec<usize> {
+        match self {
+            AlephSignature::Signatures(signatures) => {
+                signatures.iter().map(|(index, _)| index).collect()
+            }
+            AlephSignature::Aggregated(aggregated) => aggregated.signers.iter().collect(),
+        }
+    }
+}
+
+/// A proof of block finality, signed by more than 2/3 of the authorities of its session.
+///
+/// Starting from `V3` every justification is encoded with a leading version byte, preceded by
+/// `VERSION_MARKER`. Older justifications start with the compact length of a signature set
+/// instead, which can never be `VERSION_MARKER`. Justifications in the first format are only
+/// understood by `decode_justification`, which turns them into `V2`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AlephJustification {
+    /// A plain set of ed25519 signatures, without the session.
+    V2(SignatureSet<AuthoritySignature>),
+    /// Signatures of any kind together with the session of the signed block.
+    V3 {
+        session_id: SessionId,
+        signature: AlephSignature,
+    },
+    /// Like `V3`, but signing the block hash tagged with the finality domain of the session.
+    V4 {
+        session_id: SessionId,
+        signature: AlephSignature,
+    },
+}
+
+/// The first byte of all the versioned justifications. An older justification would start with a
+/// compact length of at least 2^30, which cannot be the size of a committee.
+const VERSION_MARKER: u8 = 0b11;
+/// The version byte of `AlephJustification::V3`.
+const VERSION_3: u8 = 3;
+/// The version byte of `AlephJustification::V4`.
+const VERSION_4: u8 = 4;
+
+impl AlephJustification {
+    /// A justification in the current format, for signatures of the bare block hash.
+    pub fn new(session_id: SessionId, signature: AlephSignature) -> Self {
+        AlephJustification::V3 {
+            session_id,
+            signature,
+        }
+    }
+
+    /// A justification in the current format, for signatures of the block hash tagged with the
+    /// finality domain of the session.
+    pub fn domain_separated(session_id: SessionId, signature: AlephSignature) -> Self {
+        AlephJustification::V4 {
+            session_id,
+            signature,
+        }
+    }
+
+    /// The version of the format of the justification.
+    pub fn version(&self) -> u8 {
+        match self {
+            AlephJustification::V2(_) => 2,
+            AlephJustification::V3 { .. } => VERSION_3,
+            AlephJustification::V4 { .. } => VERSION_4,
+        }
+    }
+
+    /// The session of the justified block, unknown for the formats older than `V3`.
+    pub fn session_id(&self) -> Option<SessionId> {
+        match self {
+            AlephJustification::V2(_) => None,
+            AlephJustification::V3 { session_id, .. }
+            | AlephJustification::V4 { session_id, .. } => Some(*session_id),
+        }
+    }
+
+    /// The size of the committee that signed the justification.
+    pub fn committee_size(&self) -> usize {
+        match self {
+            AlephJustification::V2(signatures) => signatures.size(),
+            AlephJustification::V3 { signature, .. } | AlephJustification::V4 { signature, .. } => {
+                signature.committee_size()
+            }
+        }
+    }
+
+    /// The indices of the signers, in increasing order.
+    pub fn signers(&self) -> Vec<usize> {
+        match self {
+            AlephJustification::V2(signatures) => {
+                signatures.iter().map(|(index, _)| index).collect()
+            }
+            AlephJustification::V3 { signature, .. } | AlephJustification::V4 { signature, .. } => {
+                signature.signers()
+            }
+        }
+    }
+}
+
+impl Encode for AlephJustification {
+    fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+        match self {
+            AlephJustification::V2(signatures) => signatures.encode_to(dest),
+            AlephJustification::V3 {
+                session_id,
+                signature,
+            }
+            | AlephJustification::V4 {
+                session_id,
+                signature,
+            } => {
+                dest.push_byte(VERSION_MARKER);
+                dest.push_byte(self.version());
+                session_id.encode_to(dest);
+                signature.encode_to(dest);
+            }
+        }
+    }
+}
+
+impl EncodeLike for AlephJustification {}
+
+/// An input with one byte already read from it put back in front.
+struct Prepended<'a, I: Input> {
+    first: Option<u8>,
+    rest: &'a mut I,
+}
+
+impl<'a, I: Input> Input for Prepended<'a, I> {
+    fn remaining_len(&mut self) -> Result<Option<usize>, codec::Error> {
+        let first = self.first.is_some() as usize;
+        Ok(self.rest.remaining_len()?.map(|len| len + first))
+    }
+
+    fn read(&mut self, into: &mut [u8]) -> Result<(), codec::Error> {
+        match (self.first, into.split_first_mut()) {
+            (Some(byte), Some((head, tail))) => {
+                *head = byte;
+                self.first = None;
+                self.rest.read(tail)
+            }
+            _ => self.rest.read(into),
+        }
+    }
+}
+
+impl Decode for AlephJustification {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+        let first = input.read_byte()?;
+        if first != VERSION_MARKER {
+            let mut input = Prepended {
+                first: Some(first),
+                rest: input,
+            };
+            return Ok(AlephJustification::V2(SignatureSet::decode(&mut input)?));
+        }
+        match input.read_byte()? {
+            VERSION_3 => Ok(AlephJustification::V3 {
+                session_id: SessionId::decode(input)?,
+                signature: AlephSignature::decode(input)?,
+            }),
+            VERSION_4 => Ok(AlephJustification::V4 {
+                session_id: SessionId::decode(input)?,
+                signature: AlephSignature::decode(input)?,
+            }),
+            _ => Err("unknown justification version".into()),
+        }
+    }
+}
+
+/// Old format of signatures, needed for backwards compatibility.
+#[derive(Clone, Encode, Decode, Debug, PartialEq, Eq)]
+pub struct SignatureV1 {
+    /// The index of the signer, redundant with the position in the set.
+    pub id: u64,
+    pub sgn: AuthoritySignature,
+}
+
+/// Old format of justifications, needed for backwards compatibility.
+#[derive(Clone, Encode, Decode, Debug, PartialEq, Eq)]
+pub struct AlephJustificationV1 {
+    pub signature: SignatureSet<SignatureV1>,
+}
+
+impl From<AlephJustificationV1> for AlephJustification {
+    fn from(just_v1: AlephJustificationV1) -> AlephJustification {
+        let size = just_v1.signature.size();
+        let signature = just_v1
+            .signature
+            .into_iter()
+            .fold(SignatureSet::with_size(size), |sig_set, (id, sgn)| {
+                sig_set.add_signature(&sgn.sgn, id)
+            });
+        AlephJustification::V2(signature)
+    }
+}
+
+/// Decodes a stored justification in any of the formats used so far. The first format cannot be
+/// told apart from `V2` by its first bytes, so it is tried only if decoding as `V2` fails.
+pub fn decode_justification(justification_raw: &[u8]) -> Result<AlephJustification, codec::Error> {
+    AlephJustification::decode_all(justification_raw).or_else(|e| {
+        AlephJustificationV1::decode_all(justification_raw)
+            .map(AlephJustification::from)
+            .map_err(|_| e)
+    })
+}
+
+/// The session whose justifications a verifier accepts.
+#[derive(Clone, Debug)]
+struct JustifiedSession {
+    genesis_hash: Vec<u8>,
+    session_id: SessionId,
+    version: FinalityVersion,
+}
+
+/// Holds the public authority keys for a session allowing for verification of messages from that
+/// session.
+#[derive(Clone, Debug)]
+pub struct AuthorityVerifier {
+    authorities: Vec<AuthorityId>,
+    bls_keys: Option<Vec<BlsPublic>>,
+    session: Option<JustifiedSession>,
+}
+
+impl AuthorityVerifier {
+    /// Constructs a new authority verifier from a set of public keys.
+    pub fn new(authorities: Vec<AuthorityId>) -> Self {
+        AuthorityVerifier {
+            authorities,
+            bls_keys: None,
+            session: None,
+        }
+    }
+
+    /// Makes the verifier accept the justifications of the given session of the chain with the
+    /// given encoded genesis hash, in the format of the finality version run in the session.
+    pub fn for_session(
+        mut self,
+        genesis_hash: Vec<u8>,
+        session_id: SessionId,
+        version: FinalityVersion,
+    ) -> Self {
+        self.session = Some(JustifiedSession {
+            genesis_hash,
+            session_id,
+            version,
+        });
+        self
+    }
+
+    /// Makes the verifier accept aggregated justifications signed with the given BLS keys of the
+    /// authorities, which have to be in the same order. The caller is responsible for checking
+    /// the proofs of possession of the keys.
+    pub fn with_bls_keys(mut self, bls_keys: Vec<BlsPublic>) -> Self {
+        if bls_keys.len() == self.authorities.len() {
+            self.bls_keys = Some(bls_keys);
+        }
+        self
+    }
+
+    /// Verifies whether the message is correctly signed with the signature assumed to be made by a
+    /// node of the given index.
+    pub fn verify(&self, msg: &[u8], sgn: &AuthoritySignature, index: usize) -> bool {
+        match self.authorities.get(index) {
+            Some(authority) => authority.verify(&msg, sgn),
+            None => false,
+        }
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.authorities.len()
+    }
+
+    /// The BLS key of the node of the given index, if the verifier accepts aggregated signatures.
+    pub fn bls_key(&self, index: usize) -> Option<&BlsPublic> {
+        self.bls_keys
+            .as_ref()
+            .and_then(|bls_keys| bls_keys.get(index))
+    }
+
+    /// The number of signatures needed to finalize a block, more than 2/3 of all authorities.
+    pub fn threshold(&self) -> usize {
+        2 * self.node_count() / 3 + 1
+    }
+
+    /// Verifies whether the given signature set is a correct and complete multisignature of the
+    /// message.
+    pub fn is_complete(&self, msg: &[u8], partial: &SignatureSet<AuthoritySignature>) -> bool {
+        let signature_count = partial.iter().count();
+        if signature_count < self.threshold() {
+            return false;
+        }
+        self.verify_all(msg, partial.iter()).is_ok()
+    }
+
+    /// Verifies whether the message is correctly signed with all the signatures, each assumed to be
+    /// made by a node of the given index. With the `batch` feature the signatures are checked all
+    /// at once. Returns the indices of the nodes whose signatures are incorrect.
+    pub fn verify_all<'a>(
+        &self,
+        msg: &[u8],
+        signatures: impl IntoIterator<Item = (usize, &'a AuthoritySignature)>,
+    ) -> Result<(), Vec<usize>> {
+        let signatures: Vec<_> = signatures.into_iter().collect();
+        #[cfg(feature = "batch")]
+        {
+            let keyed = signatures
+                .iter()
+                .filter_map(|(i, sgn)| self.authorities.get(*i).map(|key| (key, *sgn)))
+                .collect::<Vec<_>>();
+            if keyed.len() == signatures.len() {
+                return batch::verify(msg, &keyed)
+                    .map_err(|positions| positions.into_iter().map(|p| signatures[p].0).collect());
+            }
+        }
+        let invalid: Vec<usize> = signatures
+            .into_iter()
+            .filter(|(i, sgn)| !self.verify(msg, sgn, *i))
+            .map(|(i, _)| i)
+            .collect();
+        match invalid.is_empty() {
+            true => Ok(()),
+            false => Err(invalid),
+        }
+    }
+
+    /// Verifies whether the aggregated signature is a correct signature of the message made by
+    /// enough authorities. In the runtime the signature is checked through the `aleph_bls` host
+    /// functions.
+    pub fn is_complete_aggregated(&self, msg: &[u8], aggregated: &AggregatedSignature) -> bool {
+        let bls_keys = match &self.bls_keys {
+            Some(bls_keys) => bls_keys,
+            None => return false,
+        };
+        if aggregated.signers.size() != bls_keys.len()
+            || aggregated.signers.iter().count() < self.threshold()
+        {
+            return false;
+        }
+        let keys = aggregated
+            .signers
+            .iter()
+            .map(|index| bls_keys[index])
+            .collect::<Vec<_>>();
+        verify_aggregated(msg, &aggregated.signature, &keys)
+    }
+
+    /// Verifies whether the signature is complete for the block with the given encoded hash.
+    pub fn verify_signature(&self, hash: &[u8], signature: &AlephSignature) -> bool {
+        match signature {
+            AlephSignature::Signatures(signatures) => self.is_complete(hash, signatures),
+            AlephSignature::Aggregated(aggregated) => self.is_complete_aggregated(hash, aggregated),
+        }
+    }
+
+    /// Verifies whether the justification is complete for the block with the given encoded hash.
+    /// Only justifications of the session of the verifier are accepted, in the format of its
+    /// finality version: `V2` and `V3` in sessions signing bare hashes and `V4` in sessions signing
+    /// in domains. A verifier not bound to a session with `for_session` accepts none.
+    pub fn verify_justification(&self, hash: &[u8], justification: &AlephJustification) -> bool {
+        let session = match &self.session {
+            Some(session) => session,
+            None => return false,
+        };
+        let signs_in_domains = session.version.signs_in_domains();
+        match justification {
+            AlephJustification::V2(signatures) => {
+                !signs_in_domains && self.is_complete(hash, signatures)
+            }
+            AlephJustification::V3 {
+                session_id,
+                signature,
+            } => {
+                !signs_in_domains
+                    && *session_id == session.session_id
+                    && self.verify_signature(hash, signature)
+            }
+            AlephJustification::V4 {
+                session_id,
+                signature,
+            } => {
+                let domain = SigningDomain::new(
+                    SigningPurpose::Finality,
+                    session.genesis_hash.clone(),
+                    session.session_id,
+                );
+                signs_in_domains
+                    && *session_id == session.session_id
+                    && self.verify_signature(&domain.tag(hash), signature)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aleph_primitives::AuthorityPair;
+    use sp_core::Pair;
+
+    fn pairs(count: u8) -> Vec<AuthorityPair> {
+        (0..count)
+            .map(|seed| AuthorityPair::from_seed(&[seed; 32]))
+            .collect()
+    }
+
+    fn signature_set(
+        pairs: &[AuthorityPair],
+        signers: &[usize],
+        msg: &[u8],
+    ) -> SignatureSet<AuthoritySignature> {
+        signers
+            .iter()
+            .fold(SignatureSet::with_size(pairs.len()), |set, index| {
+                set.add_signature(&pairs[*index].sign(msg), *index)
+            })
+    }
+
+    fn justify(pairs: &[AuthorityPair], signers: &[usize], msg: &[u8]) -> AlephJustification {
+        AlephJustification::new(
+            SessionId(1),
+            AlephSignature::Signatures(signature_set(pairs, signers, msg)),
+        )
+    }
+
+    fn aggregated(size: usize, signers: &[usize]) -> AlephSignature {
+        let mut signer_set = SignerSet::with_size(size);
+        for index in signers {
+            signer_set.insert(*index);
+        }
+        AlephSignature::Aggregated(AggregatedSignature {
+            signers: signer_set,
+            signature: BlsSignature([7; aleph_primitives::BLS_SIGNATURE_SIZE]),
+        })
+    }
+
+    fn session_verifier(
+        pairs: &[AuthorityPair],
+        genesis_hash: &[u8],
+        version: FinalityVersion,
+    ) -> AuthorityVerifier {
+        AuthorityVerifier::new(pairs.iter().map(|pair| pair.public()).collect()).for_session(
+            genesis_hash.to_vec(),
+            SessionId(1),
+            version,
+        )
+    }
+
+    fn verifier(pairs: &[AuthorityPair]) -> AuthorityVerifier {
+        session_verifier(pairs, b"genesis", FinalityVersion::LEGACY)
+    }
+
+    fn domain_verifier(pairs: &[AuthorityPair], genesis_hash: &[u8]) -> AuthorityVerifier {
+        session_verifier(pairs, genesis_hash, FinalityVersion::DOMAIN_SEPARATED)
+    }
+
+    fn finality_domain(genesis_hash: &[u8], session: u32) -> SigningDomain {
+        SigningDomain::new(
+            SigningPurpose::Finality,
+            genesis_hash.to_vec(),
+            SessionId(session),
+        )
+    }
+
+    #[test]
+    fn accepts_more_than_two_thirds_of_signatures() {
+        let pairs = pairs(4);
+        let justification = justify(&pairs, &[0, 2, 3], b"hash");
+
+        assert!(verifier(&pairs).verify_justification(b"hash", &justification));
+    }
+
+    #[test]
+    fn rejects_too_few_or_wrong_signatures() {
+        let pairs = pairs(4);
+        let verifier = verifier(&pairs);
+
+        let justification = justify(&pairs, &[0, 2], b"hash");
+        assert!(!verifier.verify_justification(b"hash", &justification));
+
+        let justification = justify(&pairs, &[0, 1, 2], b"other hash");
+        assert!(!verifier.verify_justification(b"hash", &justification));
+    }
+
+    #[test]
+    fn finds_incorrect_signatures() {
+        let pairs = pairs(7);
+        let verifier = verifier(&pairs);
+        let correct = signature_set(&pairs, &[0, 1, 2, 3, 4, 5, 6], b"hash");
+        assert_eq!(verifier.verify_all(b"hash", correct.iter()), Ok(()));
+
+        let wrong = signature_set(&pairs, &[0, 1, 2, 3, 4, 5, 6], b"other hash");
+        let mixed = correct
+            .iter()
+            .zip(wrong.iter())
+            .map(|((i, sgn), (_, wrong_sgn))| match i {
+                2 | 5 => (i, wrong_sgn),
+                _ => (i, sgn),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(verifier.verify_all(b"hash", mixed), Err(vec![2, 5]));
+
+        let (_, sgn) = correct.iter().next().unwrap();
+        assert_eq!(verifier.verify_all(b"hash", vec![(7, sgn)]), Err(vec![7]));
+    }
+
+    #[cfg(feature = "batch")]
+    #[test]
+    fn batches_accept_only_individually_correct_signatures() {
+        use curve25519_dalek::edwards::CompressedEdwardsY;
+        use sp_std::convert::TryInto;
+
+        let pairs = pairs(4);
+        let verifier = verifier(&pairs);
+        let correct = signature_set(&pairs, &[0, 1, 2, 3], b"hash");
+
+        // Adding the point of order two to the commitment of a signature breaks it, but the
+        // cofactored equation still holds.
+        let mut torsion = [0xff; 32];
+        torsion[0] = 0xec;
+        torsion[31] = 0x7f;
+        let torsion = CompressedEdwardsY(torsion).decompress().unwrap();
+        let (_, sgn) = correct.iter().next().unwrap();
+        let mut bytes: [u8; 64] = sgn.as_ref().try_into().unwrap();
+        let commitment = CompressedEdwardsY::from_slice(&bytes[..32])
+            .decompress()
+            .unwrap();
+        bytes[..32].copy_from_slice((commitment + torsion).compress().as_bytes());
+        let broken = AuthoritySignature::from(sp_core::ed25519::Signature::from_raw(bytes));
+
+        let mixed = correct
+            .iter()
+            .map(|(i, sgn)| match i {
+                0 => (i, &broken),
+                _ => (i, sgn),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(verifier.verify_all(b"hash", mixed), Err(vec![0]));
+    }
+
+    #[cfg(feature = "batch")]
+    #[test]
+    fn finds_incorrect_signatures_in_large_sets() {
+        let pairs = pairs(200);
+        let verifier = verifier(&pairs);
+        let signers = (0..200).collect::<Vec<_>>();
+        let correct = signature_set(&pairs, &signers, b"hash");
+        let wrong = signature_set(&pairs, &signers, b"other hash");
+
+        assert_eq!(verifier.verify_all(b"hash", correct.iter()), Ok(()));
+        let mixed = correct
+            .iter()
+            .zip(wrong.iter())
+            .map(|(correct, wrong)| match correct.0 {
+                3 | 150 | 199 => wrong,
+                _ => correct,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(verifier.verify_all(b"hash", mixed), Err(vec![3, 150, 199]));
+    }
+
+    #[test]
+    fn round_trips_versioned_justifications() {
+        let pairs = pairs(3);
+        let justifications = vec![
+            justify(&pairs, &[0, 2], b"hash"),
+            AlephJustification::new(SessionId(7), aggregated(10, &[0, 9])),
+            AlephJustification::domain_separated(SessionId(7), aggregated(10, &[0, 9])),
+        ];
+
+        for justification in justifications {
+            let encoded = justification.encode();
+            assert_eq!(encoded[..2], [VERSION_MARKER, justification.version()]);
+            assert_eq!(decode_justification(&encoded), Ok(justification.clone()));
+            // The versioned format does not depend on being the last thing in the input.
+            assert_eq!(
+                <(AlephJustification, u8)>::decode_all(&(justification.clone(), 5u8).encode()),
+                Ok((justification, 5))
+            );
+        }
+    }
+
+    #[test]
+    fn decodes_all_versions() {
+        let pairs = pairs(3);
+        let signatures = signature_set(&pairs, &[0, 1, 2], b"hash");
+        let just_v1 = AlephJustificationV1 {
+            signature: signatures
+                .clone()
+                .into_iter()
+                .fold(SignatureSet::with_size(3), |set, (id, sgn)| {
+                    set.add_signature(&SignatureV1 { id: id as u64, sgn }, id)
+                }),
+        };
+        let just_v2 = AlephJustification::V2(signatures.clone());
+        let just_v3 = AlephJustification::new(SessionId(1), AlephSignature::Signatures(signatures));
+
+        assert_eq!(decode_justification(&just_v1.encode()), Ok(just_v2.clone()));
+        assert_eq!(decode_justification(&just_v2.encode()), Ok(just_v2.clone()));
+        assert_eq!(decode_justification(&just_v3.encode()), Ok(just_v3.clone()));
+        assert_eq!(just_v2.signers(), just_v3.signers());
+        assert_eq!(just_v2.session_id(), None);
+        assert_eq!(just_v3.session_id(), Some(SessionId(1)));
+        for justification in &[just_v2, just_v3] {
+            assert!(verifier(&pairs).verify_justification(b"hash", justification));
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_versions_and_garbage() {
+        let pairs = pairs(3);
+        let mut encoded = justify(&pairs, &[0, 1, 2], b"hash").encode();
+        encoded[1] = VERSION_4 + 1;
+
+        assert!(decode_justification(&encoded).is_err());
+        assert!(decode_justification(&[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn rejects_aggregated_justifications_without_bls_keys() {
+        let pairs = pairs(1);
+        let justification = AlephJustification::new(SessionId(1), aggregated(1, &[0]));
+
+        assert_eq!(justification.signers(), vec![0]);
+        assert!(!verifier(&pairs).verify_justification(b"hash", &justification));
+    }
+
+    #[test]
+    fn separates_signing_domains() {
+        let domain = finality_domain(b"genesis", 1);
+        let tagged = domain.tag(b"hash");
+
+        assert!(tagged.ends_with(b"hash"));
+        assert_ne!(tagged, finality_domain(b"genesis", 2).tag(b"hash"));
+        assert_ne!(tagged, finality_domain(b"other genesis", 1).tag(b"hash"));
+        assert_ne!(
+            tagged,
+            SigningDomain::new(SigningPurpose::Unit, b"genesis".to_vec(), SessionId(1))
+                .tag(b"hash")
+        );
+    }
+
+    #[test]
+    fn verifies_domain_separated_justifications_of_own_chain_only() {
+        let pairs = pairs(4);
+        let tagged = finality_domain(b"genesis", 1).tag(b"hash");
+        let justification = AlephJustification::domain_separated(
+            SessionId(1),
+            AlephSignature::Signatures(signature_set(&pairs, &[0, 1, 2], &tagged)),
+        );
+
+        assert!(domain_verifier(&pairs, b"genesis").verify_justification(b"hash", &justification));
+        assert!(!domain_verifier(&pairs, b"other genesis")
+            .verify_justification(b"hash", &justification));
+
+        // Signatures of the bare hash do not make a domain separated justification.
+        let bare = AlephJustification::domain_separated(
+            SessionId(1),
+            AlephSignature::Signatures(signature_set(&pairs, &[0, 1, 2], b"hash")),
+        );
+        assert!(!domain_verifier(&pairs, b"genesis").verify_justification(b"hash", &bare));
+    }
+
+    #[test]
+    fn accepts_only_justifications_of_own_session_and_version() {
+        let pairs = pairs(4);
+        let bare = signature_set(&pairs, &[0, 1, 2], b"hash");
+        let tagged = signature_set(
+            &pairs,
+            &[0, 1, 2],
+            &finality_domain(b"genesis", 1).tag(b"hash"),
+        );
+        let v2 = AlephJustification::V2(bare.clone());
+        let v3 = AlephJustification::new(SessionId(1), AlephSignature::Signatures(bare.clone()));
+        let v4 = AlephJustification::domain_separated(
+            SessionId(1),
+            AlephSignature::Signatures(tagged.clone()),
+        );
+
+        for justification in &[&v2, &v3] {
+            assert!(verifier(&pairs).verify_justification(b"hash", justification));
+            assert!(
+                !domain_verifier(&pairs, b"genesis").verify_justification(b"hash", justification)
+            );
+        }
+        assert!(!verifier(&pairs).verify_justification(b"hash", &v4));
+        assert!(domain_verifier(&pairs, b"genesis").verify_justification(b"hash", &v4));
+
+        let unbound = AuthorityVerifier::new(pairs.iter().map(|pair| pair.public()).collect());
+        for justification in &[&v2, &v3, &v4] {
+            assert!(!unbound.verify_justification(b"hash", justification));
+        }
+
+        // Justifications naming another session are rejected, even if the signatures match.
+        let other_v3 = AlephJustification::new(SessionId(2), AlephSignature::Signatures(bare));
+        let other_v4 =
+            AlephJustification::domain_separated(SessionId(2), AlephSignature::Signatures(tagged));
+        assert!(!verifier(&pairs).verify_justification(b"hash", &other_v3));
+        assert!(!domain_verifier(&pairs, b"genesis").verify_justification(b"hash", &other_v4));
+    }
+}
@@ -0,0 +1,84 @@
+//! Checks of BLS signatures in the runtime, including the proofs of possession of BLS keys. The
+//! runtime cannot verify BLS signatures on its own, so the node does it through host functions.
+#[cfg(feature = "std")]
+use aleph_primitives::BLS_PUBLIC_SIZE;
+use aleph_primitives::{BlsKeyRegistration, BlsPublic, BlsSignature};
+#[cfg(feature = "runtime-benchmarks")]
+use codec::Decode;
+#[cfg(all(feature = "std", feature = "runtime-benchmarks"))]
+use codec::Encode;
+use sp_runtime_interface::runtime_interface;
+use sp_std::vec::Vec;
+#[cfg(feature = "std")]
+use std::convert::TryInto;
+
+/// Host functions checking BLS keys. Nodes running runtimes that use them have to include
+/// `aleph_bls::HostFunctions` in their executors.
+#[runtime_interface]
+pub trait AlephBls {
+    /// Verifies that the key is valid and its owner knows the secret key.
+    fn verify_possession(key: &[u8], proof_of_possession: &[u8]) -> bool {
+        match (key.try_into(), proof_of_possession.try_into()) {
+            (Ok(key), Ok(proof_of_possession)) => {
+                crate::bls::verify_possession(&BlsKeyRegistration {
+                    key: BlsPublic(key),
+                    proof_of_possession: BlsSignature(proof_of_possession),
+                })
+            }
+            _ => false,
+        }
+    }
+
+    /// Verifies a signature of the message aggregated from the signatures of the owners of all
+    /// the keys, given concatenated.
+    fn verify_aggregated(msg: &[u8], signature: &[u8], keys: &[u8]) -> bool {
+        if keys.len() % BLS_PUBLIC_SIZE != 0 {
+            return false;
+        }
+        let keys = keys
+            .chunks(BLS_PUBLIC_SIZE)
+            .map(|key| BlsPublic(key.try_into().expect("chunks have the size of a key")))
+            .collect::<Vec<_>>();
+        match signature.try_into() {
+            Ok(signature) => crate::bls::verify_aggregated(msg, &BlsSignature(signature), &keys),
+            Err(_) => false,
+        }
+    }
+}
+
+/// Host functions generating BLS keys, which the runtime only needs in benchmarks. Nodes built
+/// for running benchmarks have to include `aleph_bls_benchmarking::HostFunctions` in their
+/// executors.
+#[cfg(feature = "runtime-benchmarks")]
+#[runtime_interface]
+pub trait AlephBlsBenchmarking {
+    /// The encoded registration of a BLS key generated from the seed, or nothing if the seed is
+    /// shorter than 32 bytes.
+    fn generate_registration(seed: &[u8]) -> Vec<u8> {
+        crate::bls::generate_registration(seed)
+            .map(|registration| registration.encode())
+            .unwrap_or_default()
+    }
+}
+
+/// Verifies the proof of possession of the registered BLS key.
+pub fn verify_possession(registration: &BlsKeyRegistration) -> bool {
+    aleph_bls::verify_possession(&registration.key.0, &registration.proof_of_possession.0)
+}
+
+/// Verifies a signature of the message aggregated from the signatures of the owners of all the
+/// keys.
+pub fn verify_aggregated(msg: &[u8], signature: &BlsSignature, keys: &[BlsPublic]) -> bool {
+    let keys = keys
+        .iter()
+        .flat_map(|key| key.0.iter().copied())
+        .collect::<Vec<_>>();
+    aleph_bls::verify_aggregated(msg, &signature.0, &keys)
+}
+
+/// Generates a registration of a BLS key from the seed, which has to be at least 32 bytes long.
+/// Only meant for benchmarks, the secret key is as secret as the seed.
+#[cfg(feature = "runtime-benchmarks")]
+pub fn generate_registration(seed: &[u8]) -> Option<BlsKeyRegistration> {
+    BlsKeyRegistration::decode(&mut &aleph_bls_benchmarking::generate_registration(seed)[..]).ok()
+}
@@ -0,0 +1,354 @@
+//! Light client of a remote Aleph chain.
+//!
+//! This pallet keeps the authorities of one session of a remote Aleph chain and imports the
+//! headers of that chain finalized by them. Anyone can submit a header together with its
+//! `AlephJustification`, which is accepted only if more than 2/3 of the trusted authorities signed
+//! the header hash in the format of the finality version of the trusted session: bare in the legacy
+//! version, tagged with the finality signing domain of the remote chain and session afterwards.
+//! Sessions signing with BLS keys can also be justified with a single aggregated BLS signature,
+//! which is checked through the `aleph_bls` host functions, so runtimes using this pallet have to
+//! run on nodes including them.
+//!
+//! The last block of every remote session announces the authorities of the next session in a
+//! `ConsensusLog::NextSessionAuthorities` digest and its finality version in a
+//! `ConsensusLog::NextSessionFinalityVersion` one, followed by a `ConsensusLog::NextSessionBlsKeys`
+//! one if the next session signs with BLS keys. The announced BLS keys are trusted like the
+//! announced authorities, as the remote chain checked their proofs of possession when they were
+//! registered. Once such a header is imported, a header signed by the announced authorities moves
+//! the pallet to the next session. Relayers therefore have to
+//! submit the last block of every session, otherwise the pallet stops following the remote chain.
+//!
+//! The imported headers are kept for `HeadersToKeep` imports and are available to other pallets,
+//! e.g. for verifying storage or message proofs, through the `FinalizedHeaders` trait.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(test)]
+mod mock;
+#[cfg(test)]
+mod tests;
+
+pub mod weights;
+
+use frame_support::sp_runtime::traits::Header as HeaderT;
+use sp_std::prelude::*;
+
+pub use pallet::*;
+pub use weights::WeightInfo;
+
+pub type RemoteHashOf<T> = <<T as Config>::RemoteHeader as HeaderT>::Hash;
+
+/// Access to the headers of a remote chain that are known to be finalized.
+pub trait FinalizedHeaders<Header: HeaderT> {
+    /// The finalized header of the given hash, if it was imported and not pruned yet.
+    fn finalized_header(hash: &Header::Hash) -> Option<Header>;
+    /// The latest imported header.
+    fn best_finalized_header() -> Option<Header>;
+}
+
+#[frame_support::pallet]
+pub mod pallet {
+    use super::*;
+    use aleph_justification::{AlephJustification, AuthorityVerifier};
+    use codec::Encode;
+    use frame_support::{pallet_prelude::*, sp_runtime::traits::Saturating};
+    use frame_system::pallet_prelude::*;
+    use primitives::{
+        AuthorityId, BlsPublic, ConsensusLog, FinalityVersion, SessionId, ALEPH_ENGINE_ID,
+    };
+
+    #[pallet::config]
+    pub trait Config: frame_system::Config {
+        type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+        /// The header type of the remote chain.
+        type RemoteHeader: HeaderT + Parameter;
+        /// How many of the most recently imported headers are kept.
+        #[pallet::constant]
+        type HeadersToKeep: Get<u32>;
+        /// Weights of the calls.
+        type WeightInfo: WeightInfo;
+    }
+
+    #[pallet::event]
+    #[pallet::metadata(RemoteHashOf<T> = "RemoteHash")]
+    #[pallet::generate_deposit(pub(super) fn deposit_event)]
+    pub enum Event<T: Config> {
+        /// The light client was reset to trust the authorities of the given session.
+        Initialized(u32),
+        /// The remote header of the given hash was imported as finalized.
+        HeaderImported(RemoteHashOf<T>),
+        /// The light client started trusting the authorities of the given session.
+        SessionChanged(u32),
+    }
+
+    #[pallet::error]
+    pub enum Error<T> {
+        /// The light client has not been initialized yet.
+        NotInitialized,
+        /// The set of authorities is empty.
+        NoAuthorities,
+        /// The header is not newer than the best finalized one.
+        OldHeader,
+        /// The justification is not signed by enough trusted authorities.
+        InvalidJustification,
+        /// The number of BLS keys differs from the number of authorities.
+        InvalidBlsKeys,
+    }
+
+    /// The genesis hash of the remote chain, part of the signing domain of its justifications.
+    #[pallet::storage]
+    #[pallet::getter(fn remote_genesis_hash)]
+    pub type RemoteGenesisHash<T: Config> = StorageValue<_, RemoteHashOf<T>, OptionQuery>;
+
+    /// The remote session whose authorities are trusted.
+    #[pallet::storage]
+    #[pallet::getter(fn trusted_session)]
+    pub type TrustedSession<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+    /// The authorities of the trusted session, empty until the pallet is initialized.
+    #[pallet::storage]
+    #[pallet::getter(fn trusted_authorities)]
+    pub type TrustedAuthorities<T: Config> = StorageValue<_, Vec<AuthorityId>, ValueQuery>;
+
+    /// The finality version of the trusted session.
+    #[pallet::storage]
+    #[pallet::getter(fn trusted_finality_version)]
+    pub type TrustedFinalityVersion<T: Config> = StorageValue<_, FinalityVersion, ValueQuery>;
+
+    /// The BLS keys of the authorities of the trusted session, if it signs with them.
+    #[pallet::storage]
+    #[pallet::getter(fn trusted_bls_keys)]
+    pub type TrustedBlsKeys<T: Config> = StorageValue<_, Vec<BlsPublic>, OptionQuery>;
+
+    /// The authorities of the session following the trusted one, as announced in an imported
+    /// header.
+    #[pallet::storage]
+    #[pallet::getter(fn next_authorities)]
+    pub type NextAuthorities<T: Config> = StorageValue<_, Vec<AuthorityId>, OptionQuery>;
+
+    /// The finality version of the session following the trusted one, announced together with
+    /// its authorities.
+    #[pallet::storage]
+    #[pallet::getter(fn next_finality_version)]
+    pub type NextFinalityVersion<T: Config> = StorageValue<_, FinalityVersion, OptionQuery>;
+
+    /// The BLS keys of the authorities of the session following the trusted one, if they were
+    /// announced together with the authorities.
+    #[pallet::storage]
+    #[pallet::getter(fn next_bls_keys)]
+    pub type NextBlsKeys<T: Config> = StorageValue<_, Vec<BlsPublic>, OptionQuery>;
+
+    /// The hash of the latest imported header.
+    #[pallet::storage]
+    #[pallet::getter(fn best_finalized)]
+    pub type BestFinalized<T: Config> = StorageValue<_, RemoteHashOf<T>, OptionQuery>;
+
+    /// The imported headers that were not pruned yet.
+    #[pallet::storage]
+    #[pallet::getter(fn imported_header)]
+    pub type ImportedHeaders<T: Config> =
+        StorageMap<_, Identity, RemoteHashOf<T>, T::RemoteHeader, OptionQuery>;
+
+    /// A ring buffer of the hashes of the imported headers, used for pruning.
+    #[pallet::storage]
+    pub type ImportedHashes<T: Config> = StorageMap<_, Identity, u32, RemoteHashOf<T>, OptionQuery>;
+
+    /// The position in `ImportedHashes` to be written next.
+    #[pallet::storage]
+    pub type ImportedHashesPointer<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+    #[pallet::pallet]
+    pub struct Pallet<T>(_);
+
+    #[pallet::hooks]
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {}
+
+    #[pallet::call]
+    impl<T: Config> Pallet<T> {
+        /// Makes the light client trust `header` and the authorities of `session`, which have to
+        /// be the session of `header`, of the remote chain with the given genesis hash.
+        /// `finality_version` has to be the finality version of `session` and `bls_keys` the BLS
+        /// keys of the authorities, if the session signs with them. Resets any previous state.
+        #[pallet::weight((
+            T::WeightInfo::initialize(authorities.len() as u32),
+            DispatchClass::Operational
+        ))]
+        pub fn initialize(
+            origin: OriginFor<T>,
+            genesis_hash: RemoteHashOf<T>,
+            header: Box<T::RemoteHeader>,
+            session: u32,
+            authorities: Vec<AuthorityId>,
+            finality_version: FinalityVersion,
+            bls_keys: Option<Vec<BlsPublic>>,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(!authorities.is_empty(), Error::<T>::NoAuthorities);
+            if let Some(bls_keys) = &bls_keys {
+                ensure!(
+                    bls_keys.len() == authorities.len(),
+                    Error::<T>::InvalidBlsKeys
+                );
+            }
+
+            RemoteGenesisHash::<T>::put(genesis_hash);
+            TrustedSession::<T>::put(session);
+            TrustedAuthorities::<T>::put(authorities);
+            TrustedFinalityVersion::<T>::put(finality_version);
+            TrustedBlsKeys::<T>::set(bls_keys);
+            NextAuthorities::<T>::kill();
+            NextFinalityVersion::<T>::kill();
+            NextBlsKeys::<T>::kill();
+            Self::import_header(*header);
+            Self::deposit_event(Event::Initialized(session));
+            Ok(())
+        }
+
+        /// Imports a remote header finalized with the given justification. The justification
+        /// has to be signed either by the trusted authorities or, if they were already announced,
+        /// by the authorities of the next session, in the format of the finality version of the
+        /// session of the signers. Sessions with BLS keys also accept aggregated BLS signatures of
+        /// their authorities. Justifications naming their session have to name the session
+        /// of the authorities that signed them.
+        #[pallet::weight(T::WeightInfo::submit_finality_proof(justification.committee_size() as u32))]
+        pub fn submit_finality_proof(
+            origin: OriginFor<T>,
+            header: Box<T::RemoteHeader>,
+            justification: AlephJustification,
+        ) -> DispatchResult {
+            ensure_signed(origin)?;
+            let best_hash = Self::best_finalized().ok_or(Error::<T>::NotInitialized)?;
+            if let Some(best) = Self::imported_header(best_hash) {
+                ensure!(header.number() > best.number(), Error::<T>::OldHeader);
+            }
+
+            let hash = header.hash().encode();
+            let genesis_hash = Self::remote_genesis_hash()
+                .ok_or(Error::<T>::NotInitialized)?
+                .encode();
+            let trusted_session = Self::trusted_session();
+            let signed_in = |session: u32,
+                             authorities: Vec<AuthorityId>,
+                             version: FinalityVersion,
+                             bls_keys: Option<Vec<BlsPublic>>| {
+                let verifier = AuthorityVerifier::new(authorities).for_session(
+                    genesis_hash.clone(),
+                    SessionId(session),
+                    version,
+                );
+                let verifier = match bls_keys {
+                    Some(bls_keys) => verifier.with_bls_keys(bls_keys),
+                    None => verifier,
+                };
+                verifier.verify_justification(&hash, &justification)
+            };
+            if !signed_in(
+                trusted_session,
+                Self::trusted_authorities(),
+                Self::trusted_finality_version(),
+                Self::trusted_bls_keys(),
+            ) {
+                let next_authorities =
+                    Self::next_authorities().ok_or(Error::<T>::InvalidJustification)?;
+                let next_version =
+                    Self::next_finality_version().ok_or(Error::<T>::InvalidJustification)?;
+                let next_bls_keys = Self::next_bls_keys();
+                let session = trusted_session.saturating_add(1);
+                ensure!(
+                    signed_in(
+                        session,
+                        next_authorities.clone(),
+                        next_version,
+                        next_bls_keys.clone()
+                    ),
+                    Error::<T>::InvalidJustification
+                );
+                TrustedSession::<T>::put(session);
+                TrustedAuthorities::<T>::put(next_authorities);
+                TrustedFinalityVersion::<T>::put(next_version);
+                TrustedBlsKeys::<T>::set(next_bls_keys);
+                NextAuthorities::<T>::kill();
+                NextFinalityVersion::<T>::kill();
+                NextBlsKeys::<T>::kill();
+                Self::deposit_event(Event::SessionChanged(session));
+            }
+
+            Self::import_header(*header);
+            Ok(())
+        }
+    }
+
+    impl<T: Config> Pallet<T> {
+        /// Stores the header as the best finalized one, pruning the oldest header if needed, and
+        /// remembers the next session authorities, finality version and BLS keys if the header
+        /// announces them.
+        fn import_header(header: T::RemoteHeader) {
+            let hash = header.hash();
+            if let Some((authorities, version, bls_keys)) = Self::announced_session(&header) {
+                NextAuthorities::<T>::put(authorities);
+                NextFinalityVersion::<T>::put(version);
+                NextBlsKeys::<T>::set(bls_keys);
+            }
+
+            let pointer = ImportedHashesPointer::<T>::get();
+            if let Some(pruned) = ImportedHashes::<T>::get(pointer) {
+                ImportedHeaders::<T>::remove(pruned);
+            }
+            ImportedHashes::<T>::insert(pointer, hash);
+            ImportedHashesPointer::<T>::put((pointer + 1) % T::HeadersToKeep::get().max(1));
+
+            ImportedHeaders::<T>::insert(hash, header);
+            BestFinalized::<T>::put(hash);
+            Self::deposit_event(Event::HeaderImported(hash));
+        }
+
+        /// The authorities and finality version of the session following the trusted one, if
+        /// the header announces both of them, together with their BLS keys if it announces them
+        /// as well.
+        fn announced_session(
+            header: &T::RemoteHeader,
+        ) -> Option<(Vec<AuthorityId>, FinalityVersion, Option<Vec<BlsPublic>>)> {
+            let next_session = Self::trusted_session().saturating_add(1);
+            let mut announced_authorities = None;
+            let mut announced_version = None;
+            let mut announced_bls_keys = None;
+            for log in header.digest().logs() {
+                match log.consensus_try_to(&ALEPH_ENGINE_ID) {
+                    Some(ConsensusLog::<AuthorityId>::NextSessionAuthorities {
+                        session,
+                        authorities,
+                    }) if session == next_session && !authorities.is_empty() => {
+                        announced_authorities = Some(authorities)
+                    }
+                    Some(ConsensusLog::<AuthorityId>::NextSessionFinalityVersion {
+                        session,
+                        version,
+                    }) if session == next_session => announced_version = Some(version),
+                    Some(ConsensusLog::<AuthorityId>::NextSessionBlsKeys { session, keys })
+                        if session == next_session =>
+                    {
+                        announced_bls_keys = Some(
+                            keys.into_iter()
+                                .map(|registration| registration.key)
+                                .collect(),
+                        )
+                    }
+                    _ => {}
+                }
+            }
+            announced_authorities
+                .zip(announced_version)
+                .map(|(authorities, version)| (authorities, version, announced_bls_keys))
+        }
+    }
+
+    impl<T: Config> FinalizedHeaders<T::RemoteHeader> for Pallet<T> {
+        fn finalized_header(hash: &RemoteHashOf<T>) -> Option<T::RemoteHeader> {
+            Self::imported_header(hash)
+        }
+
+        fn best_finalized_header() -> Option<T::RemoteHeader> {
+            Self::best_finalized().and_then(Self::imported_header)
+        }
+    }
+}
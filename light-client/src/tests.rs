@@ -0,0 +1,483 @@
+#![cfg(test)]
+
+use crate::{mock::*, Error, FinalizedHeaders};
+use aleph_justification::{
+    bls::{self, SIGNATURE_DST},
+    AggregatedSignature, AlephJustification, AlephSignature, SignatureSet, SignerSet,
+    SigningDomain, SigningPurpose,
+};
+use blst::min_pk::SecretKey;
+use codec::Encode;
+use frame_support::{assert_noop, assert_ok};
+use primitives::{
+    BlsKeyRegistration, BlsSignature, ConsensusLog, FinalityVersion, SessionId, ALEPH_ENGINE_ID,
+};
+use sp_core::{Pair, H256};
+use sp_runtime::{testing::Header, traits::Header as HeaderT, DigestItem};
+
+fn pairs(seeds: &[u8]) -> Vec<AuthorityPair> {
+    seeds
+        .iter()
+        .map(|seed| AuthorityPair::from_seed(&[*seed; 32]))
+        .collect()
+}
+
+fn authorities(pairs: &[AuthorityPair]) -> Vec<AuthorityId> {
+    pairs.iter().map(|pair| pair.public()).collect()
+}
+
+fn header(number: u64, next_session: Option<(u32, &[AuthorityPair])>) -> Header {
+    let mut header = Header::new_from_number(number);
+    if let Some((session, pairs)) = next_session {
+        header.digest.push(DigestItem::Consensus(
+            ALEPH_ENGINE_ID,
+            ConsensusLog::NextSessionAuthorities {
+                session,
+                authorities: authorities(pairs),
+            }
+            .encode(),
+        ));
+        header.digest.push(DigestItem::Consensus(
+            ALEPH_ENGINE_ID,
+            ConsensusLog::<AuthorityId>::NextSessionFinalityVersion {
+                session,
+                version: FinalityVersion::LEGACY,
+            }
+            .encode(),
+        ));
+    }
+    header
+}
+
+fn justify(pairs: &[AuthorityPair], signers: &[usize], header: &Header) -> AlephJustification {
+    let message = header.hash().encode();
+    let signature = signers
+        .iter()
+        .fold(SignatureSet::with_size(pairs.len()), |set, index| {
+            set.add_signature(&pairs[*index].sign(&message), *index)
+        });
+    AlephJustification::V2(signature)
+}
+
+fn justify_in_session(
+    session: u32,
+    pairs: &[AuthorityPair],
+    signers: &[usize],
+    header: &Header,
+) -> AlephJustification {
+    match justify(pairs, signers, header) {
+        AlephJustification::V2(signature) => {
+            AlephJustification::new(SessionId(session), AlephSignature::Signatures(signature))
+        }
+        justification => justification,
+    }
+}
+
+fn bls_registration(seed: u8) -> BlsKeyRegistration {
+    bls::generate_registration(&[seed; 32]).expect("the seed is long enough")
+}
+
+fn justify_aggregated(session: u32, seeds: &[u8], header: &Header) -> AlephJustification {
+    let message = header.hash().encode();
+    let mut signers = SignerSet::with_size(seeds.len());
+    let signature = seeds
+        .iter()
+        .enumerate()
+        .map(|(index, seed)| {
+            signers.insert(index);
+            let secret = SecretKey::key_gen(&[*seed; 32], &[]).expect("the seed is long enough");
+            BlsSignature(secret.sign(&message, SIGNATURE_DST, &[]).compress())
+        })
+        .reduce(|first, second| bls::aggregate(&first, &second).expect("signatures are valid"))
+        .expect("there are signers");
+    AlephJustification::new(
+        SessionId(session),
+        AlephSignature::Aggregated(AggregatedSignature { signers, signature }),
+    )
+}
+
+fn genesis_hash() -> H256 {
+    header(0, None).hash()
+}
+
+fn initialize_with_version(pairs: &[AuthorityPair], version: FinalityVersion) {
+    assert_ok!(LightClient::initialize(
+        Origin::root(),
+        genesis_hash(),
+        Box::new(header(0, None)),
+        0,
+        authorities(pairs),
+        version,
+        None
+    ));
+}
+
+fn initialize(pairs: &[AuthorityPair]) {
+    initialize_with_version(pairs, FinalityVersion::LEGACY)
+}
+
+#[test]
+fn initialize_requires_root_and_authorities() {
+    new_test_ext().execute_with(|| {
+        let pairs = pairs(&[1, 2, 3]);
+
+        assert_noop!(
+            LightClient::initialize(
+                Origin::signed(1),
+                genesis_hash(),
+                Box::new(header(0, None)),
+                0,
+                authorities(&pairs),
+                FinalityVersion::LEGACY,
+                None
+            ),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        assert_noop!(
+            LightClient::initialize(
+                Origin::root(),
+                genesis_hash(),
+                Box::new(header(0, None)),
+                0,
+                vec![],
+                FinalityVersion::LEGACY,
+                None
+            ),
+            Error::<Test>::NoAuthorities
+        );
+        assert_noop!(
+            LightClient::initialize(
+                Origin::root(),
+                genesis_hash(),
+                Box::new(header(0, None)),
+                0,
+                authorities(&pairs),
+                FinalityVersion::LEGACY,
+                Some(vec![bls_registration(1).key])
+            ),
+            Error::<Test>::InvalidBlsKeys
+        );
+
+        initialize(&pairs);
+        assert_eq!(LightClient::trusted_authorities(), authorities(&pairs));
+        assert_eq!(LightClient::best_finalized_header(), Some(header(0, None)));
+    });
+}
+
+#[test]
+fn rejects_proofs_before_initialization() {
+    new_test_ext().execute_with(|| {
+        let pairs = pairs(&[1, 2, 3]);
+        let header = header(1, None);
+        let justification = justify(&pairs, &[0, 1, 2], &header);
+
+        assert_noop!(
+            LightClient::submit_finality_proof(Origin::signed(1), Box::new(header), justification),
+            Error::<Test>::NotInitialized
+        );
+    });
+}
+
+#[test]
+fn imports_headers_finalized_by_trusted_authorities() {
+    new_test_ext().execute_with(|| {
+        let pairs = pairs(&[1, 2, 3, 4]);
+        initialize(&pairs);
+
+        let header = header(5, None);
+        assert_noop!(
+            LightClient::submit_finality_proof(
+                Origin::signed(1),
+                Box::new(header.clone()),
+                justify(&pairs, &[0, 1], &header)
+            ),
+            Error::<Test>::InvalidJustification
+        );
+        assert_ok!(LightClient::submit_finality_proof(
+            Origin::signed(1),
+            Box::new(header.clone()),
+            justify(&pairs, &[0, 1, 3], &header)
+        ));
+
+        assert_eq!(LightClient::best_finalized(), Some(header.hash()));
+        assert_eq!(
+            LightClient::finalized_header(&header.hash()),
+            Some(header.clone())
+        );
+        assert_noop!(
+            LightClient::submit_finality_proof(
+                Origin::signed(1),
+                Box::new(header.clone()),
+                justify(&pairs, &[0, 1, 2, 3], &header)
+            ),
+            Error::<Test>::OldHeader
+        );
+    });
+}
+
+#[test]
+fn follows_announced_session_changes() {
+    new_test_ext().execute_with(|| {
+        let first = pairs(&[1, 2, 3]);
+        let second = pairs(&[4, 5, 6]);
+        initialize(&first);
+
+        let header = header(2, None);
+        assert_noop!(
+            LightClient::submit_finality_proof(
+                Origin::signed(1),
+                Box::new(header.clone()),
+                justify(&second, &[0, 1, 2], &header)
+            ),
+            Error::<Test>::InvalidJustification
+        );
+
+        let session_end = self::header(1, Some((1, &second)));
+        assert_ok!(LightClient::submit_finality_proof(
+            Origin::signed(1),
+            Box::new(session_end.clone()),
+            justify(&first, &[0, 1, 2], &session_end)
+        ));
+        assert_eq!(LightClient::next_authorities(), Some(authorities(&second)));
+        assert_eq!(
+            LightClient::next_finality_version(),
+            Some(FinalityVersion::LEGACY)
+        );
+
+        assert_ok!(LightClient::submit_finality_proof(
+            Origin::signed(1),
+            Box::new(header.clone()),
+            justify(&second, &[0, 1, 2], &header)
+        ));
+        assert_eq!(LightClient::trusted_session(), 1);
+        assert_eq!(LightClient::trusted_authorities(), authorities(&second));
+        assert_eq!(LightClient::next_authorities(), None);
+    });
+}
+
+#[test]
+fn checks_sessions_claimed_by_justifications() {
+    new_test_ext().execute_with(|| {
+        let first = pairs(&[1, 2, 3]);
+        let second = pairs(&[4, 5, 6]);
+        initialize(&first);
+
+        let session_end = self::header(1, Some((1, &second)));
+        assert_noop!(
+            LightClient::submit_finality_proof(
+                Origin::signed(1),
+                Box::new(session_end.clone()),
+                justify_in_session(1, &first, &[0, 1, 2], &session_end)
+            ),
+            Error::<Test>::InvalidJustification
+        );
+        assert_ok!(LightClient::submit_finality_proof(
+            Origin::signed(1),
+            Box::new(session_end.clone()),
+            justify_in_session(0, &first, &[0, 1, 2], &session_end)
+        ));
+
+        let header = header(2, None);
+        assert_noop!(
+            LightClient::submit_finality_proof(
+                Origin::signed(1),
+                Box::new(header.clone()),
+                justify_in_session(2, &second, &[0, 1, 2], &header)
+            ),
+            Error::<Test>::InvalidJustification
+        );
+        assert_ok!(LightClient::submit_finality_proof(
+            Origin::signed(1),
+            Box::new(header.clone()),
+            justify_in_session(1, &second, &[0, 1, 2], &header)
+        ));
+        assert_eq!(LightClient::trusted_session(), 1);
+    });
+}
+
+#[test]
+fn accepts_domain_separated_justifications_of_remote_chain() {
+    new_test_ext().execute_with(|| {
+        let pairs = pairs(&[1, 2, 3]);
+        initialize_with_version(&pairs, FinalityVersion::DOMAIN_SEPARATED);
+        let justify_in_domain = |genesis_hash: H256, header: &Header| {
+            let domain = SigningDomain::new(
+                SigningPurpose::Finality,
+                genesis_hash.encode(),
+                SessionId(0),
+            );
+            let message = domain.tag(&header.hash().encode());
+            let signature = pairs.iter().enumerate().fold(
+                SignatureSet::with_size(pairs.len()),
+                |set, (index, pair)| set.add_signature(&pair.sign(&message), index),
+            );
+            AlephJustification::domain_separated(
+                SessionId(0),
+                AlephSignature::Signatures(signature),
+            )
+        };
+
+        let header = header(1, None);
+        assert_noop!(
+            LightClient::submit_finality_proof(
+                Origin::signed(1),
+                Box::new(header.clone()),
+                justify_in_domain(H256::repeat_byte(1), &header)
+            ),
+            Error::<Test>::InvalidJustification
+        );
+        assert_noop!(
+            LightClient::submit_finality_proof(
+                Origin::signed(1),
+                Box::new(header.clone()),
+                justify(&pairs, &[0, 1, 2], &header)
+            ),
+            Error::<Test>::InvalidJustification
+        );
+        assert_ok!(LightClient::submit_finality_proof(
+            Origin::signed(1),
+            Box::new(header.clone()),
+            justify_in_domain(genesis_hash(), &header)
+        ));
+        assert_eq!(LightClient::best_finalized_header(), Some(header));
+    });
+}
+
+#[test]
+fn prunes_old_headers() {
+    new_test_ext().execute_with(|| {
+        let pairs = pairs(&[1, 2, 3]);
+        initialize(&pairs);
+        let genesis = header(0, None);
+
+        for number in 1..=3 {
+            let header = header(number, None);
+            assert_ok!(LightClient::submit_finality_proof(
+                Origin::signed(1),
+                Box::new(header.clone()),
+                justify(&pairs, &[0, 1, 2], &header)
+            ));
+        }
+
+        assert_eq!(LightClient::finalized_header(&genesis.hash()), None);
+        for number in 1..=3 {
+            let header = header(number, None);
+            assert_eq!(LightClient::finalized_header(&header.hash()), Some(header));
+        }
+    });
+}
+
+#[test]
+fn ignores_authorities_announced_without_finality_version() {
+    new_test_ext().execute_with(|| {
+        let first = pairs(&[1, 2, 3]);
+        let second = pairs(&[4, 5, 6]);
+        initialize(&first);
+
+        let mut session_end = header(1, None);
+        session_end.digest.push(DigestItem::Consensus(
+            ALEPH_ENGINE_ID,
+            ConsensusLog::NextSessionAuthorities {
+                session: 1,
+                authorities: authorities(&second),
+            }
+            .encode(),
+        ));
+        assert_ok!(LightClient::submit_finality_proof(
+            Origin::signed(1),
+            Box::new(session_end.clone()),
+            justify(&first, &[0, 1, 2], &session_end)
+        ));
+        assert_eq!(LightClient::next_authorities(), None);
+
+        let header = header(2, None);
+        assert_noop!(
+            LightClient::submit_finality_proof(
+                Origin::signed(1),
+                Box::new(header.clone()),
+                justify(&second, &[0, 1, 2], &header)
+            ),
+            Error::<Test>::InvalidJustification
+        );
+    });
+}
+
+#[test]
+fn follows_sessions_signing_with_bls_keys() {
+    new_test_ext().execute_with(|| {
+        let first = pairs(&[1, 2, 3]);
+        let second = pairs(&[4, 5, 6]);
+        initialize(&first);
+
+        let header = header(2, None);
+        let aggregated = justify_aggregated(1, &[4, 5, 6], &header);
+        let mut session_end = self::header(1, Some((1, &second)));
+        session_end.digest.push(DigestItem::Consensus(
+            ALEPH_ENGINE_ID,
+            ConsensusLog::<AuthorityId>::NextSessionBlsKeys {
+                session: 1,
+                keys: vec![
+                    bls_registration(4),
+                    bls_registration(5),
+                    bls_registration(6),
+                ],
+            }
+            .encode(),
+        ));
+        assert_ok!(LightClient::submit_finality_proof(
+            Origin::signed(1),
+            Box::new(session_end.clone()),
+            justify(&first, &[0, 1, 2], &session_end)
+        ));
+        assert_eq!(
+            LightClient::next_bls_keys(),
+            Some(vec![
+                bls_registration(4).key,
+                bls_registration(5).key,
+                bls_registration(6).key
+            ])
+        );
+
+        assert_noop!(
+            LightClient::submit_finality_proof(
+                Origin::signed(1),
+                Box::new(header.clone()),
+                justify_aggregated(1, &[4, 5, 7], &header)
+            ),
+            Error::<Test>::InvalidJustification
+        );
+        assert_ok!(LightClient::submit_finality_proof(
+            Origin::signed(1),
+            Box::new(header.clone()),
+            aggregated
+        ));
+        assert_eq!(LightClient::trusted_session(), 1);
+        assert_eq!(
+            LightClient::trusted_bls_keys(),
+            Some(vec![
+                bls_registration(4).key,
+                bls_registration(5).key,
+                bls_registration(6).key
+            ])
+        );
+        assert_eq!(LightClient::next_bls_keys(), None);
+    });
+}
+
+#[test]
+fn rejects_aggregated_justifications_of_sessions_without_bls_keys() {
+    new_test_ext().execute_with(|| {
+        let pairs = pairs(&[1, 2, 3]);
+        initialize(&pairs);
+
+        let header = header(1, None);
+        assert_noop!(
+            LightClient::submit_finality_proof(
+                Origin::signed(1),
+                Box::new(header.clone()),
+                justify_aggregated(0, &[1, 2, 3], &header)
+            ),
+            Error::<Test>::InvalidJustification
+        );
+    });
+}
@@ -0,0 +1,52 @@
+//! Weights for pallet_aleph_light_client
+//!
+//! THESE ARE HAND-WRITTEN ESTIMATES, NOT BENCHMARK RESULTS. They were derived from the storage
+//! accesses and signature checks of every call and have not been measured on any hardware. The
+//! pallet has no benchmarks yet, so they cannot be regenerated with `./.maintain/run_benchmarks.sh`
+//! until it gets some.
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use sp_std::marker::PhantomData;
+
+/// Weight functions needed for pallet_aleph_light_client.
+pub trait WeightInfo {
+	fn initialize(v: u32, ) -> Weight;
+	fn submit_finality_proof(v: u32, ) -> Weight;
+}
+
+/// Estimated weights for pallet_aleph_light_client, see the module documentation.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	fn initialize(v: u32, ) -> Weight {
+		(25_000_000 as Weight)
+			.saturating_add((60_000 as Weight).saturating_mul(v as Weight))
+			.saturating_add(T::DbWeight::get().reads(3 as Weight))
+			.saturating_add(T::DbWeight::get().writes(10 as Weight))
+	}
+	fn submit_finality_proof(v: u32, ) -> Weight {
+		(40_000_000 as Weight)
+			.saturating_add((55_000_000 as Weight).saturating_mul(v as Weight))
+			.saturating_add(T::DbWeight::get().reads(10 as Weight))
+			.saturating_add(T::DbWeight::get().writes(11 as Weight))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	fn initialize(v: u32, ) -> Weight {
+		(25_000_000 as Weight)
+			.saturating_add((60_000 as Weight).saturating_mul(v as Weight))
+			.saturating_add(RocksDbWeight::get().reads(3 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(10 as Weight))
+	}
+	fn submit_finality_proof(v: u32, ) -> Weight {
+		(40_000_000 as Weight)
+			.saturating_add((55_000_000 as Weight).saturating_mul(v as Weight))
+			.saturating_add(RocksDbWeight::get().reads(10 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(11 as Weight))
+	}
+}
@@ -0,0 +1,68 @@
+#!/bin/env python
+import os
+import sys
+from os.path import join
+from time import sleep
+
+from chainrunner import Chain, Seq, generate_keys
+from chainrunner.node import Node
+
+# Path to working directory, where chainspec, logs and nodes' dbs are written:
+workdir = os.getenv('WORKDIR', '/tmp/workdir')
+# Path to the aleph-node binary:
+binary = os.getenv('ALEPH_NODE_BINARY', join(workdir, 'aleph-node'))
+
+SESSION_PERIOD = 30
+
+
+def check_highest(nodes):
+    results = [node.highest_block() for node in nodes]
+    highest, finalized = zip(*results)
+    print('Blocks seen by nodes:')
+    print('  Highest:   ', *highest)
+    print('  Finalized: ', *finalized)
+    return finalized
+
+
+phrases = ['//Cartman', '//Stan', '//Kyle', '//Kenny']
+keys = generate_keys(binary, phrases)
+
+chain = Chain(workdir)
+print('Bootstraping the chain')
+chain.bootstrap(binary,
+                keys.values(),
+                sudo_account_id=keys[phrases[0]],
+                chain_type='local',
+                millisecs_per_block=1000,
+                session_period=SESSION_PERIOD)
+
+chain.set_flags('validator',
+                port=Seq(30334),
+                ws_port=Seq(9944),
+                rpc_port=Seq(9933),
+                unit_creation_delay=200,
+                execution='Native')
+
+print('Starting the validators')
+chain.start('validator')
+
+# A full node: same chainspec, no keys and no --validator flag.
+full = Node(binary, join(workdir, 'chainspec.json'), join(workdir, 'full'), workdir)
+full.flags.update({'port': 30344, 'ws-port': 9954, 'rpc-port': 9943, 'execution': 'Native'})
+print('Starting the full node')
+full.start('full')
+
+print('Waiting for three sessions')
+sleep(3 * SESSION_PERIOD + 20)
+
+finalized = check_highest(list(chain) + [full])
+chain.stop()
+full.stop()
+
+if finalized[-1] <= 2 * SESSION_PERIOD:
+    print('Full node did not finalize blocks past the session boundaries')
+    sys.exit(1)
+if finalized[-1] < min(finalized[:-1]) - SESSION_PERIOD:
+    print('Full node lags behind the validators')
+    sys.exit(1)
+print('Full node follows finality')
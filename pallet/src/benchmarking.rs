@@ -0,0 +1,218 @@
+//! Benchmarks for the calls of the aleph pallet.
+
+use super::*;
+use crate::Pallet as Aleph;
+use codec::Encode;
+use frame_benchmarking::{account, benchmarks, impl_benchmark_test_suite, whitelisted_caller};
+use frame_support::{
+    sp_runtime::{
+        traits::{Hash, Header as HeaderT, Saturating},
+        RuntimeAppPublic,
+    },
+    traits::{Get, ReservableCurrency},
+};
+use frame_system::RawOrigin;
+use pallet_session::Pallet as Session;
+use primitives::{
+    ConsensusParams, EquivocationProof, FinalityVersion, DEFAULT_MILLISECS_PER_BLOCK,
+    DEFAULT_SESSION_PERIOD,
+};
+
+const SEED: u32 = 0;
+/// The number of session period changes already scheduled when scheduling a new one.
+const SCHEDULED_CHANGES: u32 = 100;
+
+fn fund<T: Config>(who: &T::AccountId) {
+    T::Currency::make_free_balance_be(who, T::MinCandidateBond::get().saturating_mul(10u32.into()));
+}
+
+fn candidate<T: Config>(index: u32) -> T::AccountId {
+    let who = account("candidate", index, SEED);
+    fund::<T>(&who);
+    let bond = T::MinCandidateBond::get();
+    T::Currency::reserve(&who, bond).expect("the account was just funded");
+    Candidates::<T>::insert(&who, bond);
+    CandidateCount::<T>::mutate(|count| *count += 1);
+    who
+}
+
+fn committee<T: Config>(size: u32) -> Vec<T::AccountId> {
+    (0..size).map(|i| account("validator", i, SEED)).collect()
+}
+
+fn header<T: Config>(number: T::BlockNumber, parent: &[u8]) -> T::Header {
+    T::Header::new(
+        number,
+        Default::default(),
+        Default::default(),
+        T::Hashing::hash(parent),
+        Default::default(),
+    )
+}
+
+benchmarks! {
+    change_validators {
+        let v in 1 .. T::MaxCommitteeSize::get();
+        let validators = committee::<T>(v);
+        let session = Session::<T>::current_index() + 2;
+    }: _(RawOrigin::Root, validators, session)
+    verify {
+        assert!(PendingValidatorsChanges::<T>::contains_key(session));
+    }
+
+    cancel_validators_change {
+        let session = Session::<T>::current_index() + 2;
+        PendingValidatorsChanges::<T>::insert(session, committee::<T>(T::MaxCommitteeSize::get()));
+    }: _(RawOrigin::Root, session)
+    verify {
+        assert!(!PendingValidatorsChanges::<T>::contains_key(session));
+    }
+
+    report_offence {
+        let offender = Session::<T>::validators()
+            .first()
+            .cloned()
+            .expect("the committee is not empty");
+    }: _(RawOrigin::Root, offender.clone())
+    verify {
+        assert!(Offenders::<T>::get().contains(&offender));
+    }
+
+    report_equivocation_unsigned {
+        let offender = T::AuthorityId::generate_pair(None);
+        let session = Session::<T>::current_index();
+        // Replace the first authority with one we can sign with.
+        let mut authorities = Aleph::<T>::authorities();
+        authorities[0] = offender.clone();
+        SessionAuthorities::<T>::insert(session, authorities);
+        // Messages signed in domains are longer.
+        SessionFinalityVersions::<T>::insert(session, FinalityVersion::LATEST);
+        let domain = Aleph::<T>::finality_domain(session)
+            .flatten()
+            .expect("the latest version signs in domains");
+        let number = Aleph::<T>::session_schedule().first_block_of_session(session).into();
+        let first_header = header::<T>(number, b"first");
+        let second_header = header::<T>(number, b"second");
+        let proof = EquivocationProof {
+            session,
+            offender: offender.clone(),
+            first_signature: offender
+                .sign(&domain.tag(&first_header.hash().encode()))
+                .expect("the key was just generated"),
+            first_header,
+            second_signature: offender
+                .sign(&domain.tag(&second_header.hash().encode()))
+                .expect("the key was just generated"),
+            second_header,
+        };
+    }: _(RawOrigin::None, proof)
+    verify {
+        assert!(ReportedEquivocations::<T>::contains_key(session, &offender));
+    }
+
+    schedule_session_period_change {
+        let current = Session::<T>::current_index();
+        let mut schedule = Aleph::<T>::session_schedule();
+        for session in current + 1 ..= current + SCHEDULED_CHANGES {
+            schedule = schedule
+                .with_change(session, DEFAULT_SESSION_PERIOD, DEFAULT_MILLISECS_PER_BLOCK)
+                .expect("the sessions are increasing");
+        }
+        ScheduledSessionPeriods::<T>::put(schedule);
+        let first_session = current + SCHEDULED_CHANGES + 1;
+    }: _(RawOrigin::Root, DEFAULT_SESSION_PERIOD, DEFAULT_MILLISECS_PER_BLOCK, first_session)
+    verify {
+        assert_eq!(Aleph::<T>::session_schedule().latest().first_session, first_session);
+    }
+
+    bond_candidate {
+        let caller: T::AccountId = whitelisted_caller();
+        fund::<T>(&caller);
+        Session::<T>::set_keys(RawOrigin::Signed(caller.clone()).into(), T::Keys::default(), Vec::new())?;
+        let bond = T::MinCandidateBond::get();
+    }: _(RawOrigin::Signed(caller.clone()), bond)
+    verify {
+        assert_eq!(Candidates::<T>::get(&caller), Some(bond));
+    }
+
+    withdraw_candidacy {
+        let caller = candidate::<T>(0);
+    }: _(RawOrigin::Signed(caller.clone()))
+    verify {
+        assert!(!Candidates::<T>::contains_key(&caller));
+    }
+
+    nominate {
+        let candidate = candidate::<T>(0);
+        let caller: T::AccountId = whitelisted_caller();
+        fund::<T>(&caller);
+        let amount = T::MinNominatorBond::get();
+    }: _(RawOrigin::Signed(caller.clone()), candidate.clone(), amount)
+    verify {
+        assert_eq!(Nominations::<T>::get(&caller, &candidate), Some(amount));
+    }
+
+    withdraw_nomination {
+        let candidate = candidate::<T>(0);
+        let caller: T::AccountId = whitelisted_caller();
+        fund::<T>(&caller);
+        Aleph::<T>::nominate(
+            RawOrigin::Signed(caller.clone()).into(),
+            candidate.clone(),
+            T::MinNominatorBond::get(),
+        )?;
+    }: _(RawOrigin::Signed(caller.clone()), candidate.clone())
+    verify {
+        assert!(!Nominations::<T>::contains_key(&caller, &candidate));
+    }
+
+    register_bls_key {
+        let caller: T::AccountId = whitelisted_caller();
+        let authority = T::AuthorityId::generate_pair(None);
+        let registration = aleph_justification::generate_registration(&[1; 32])
+            .expect("the seed is long enough");
+        BlsRegistrationNonces::<T>::insert(&authority, 1);
+        let signature = authority
+            .sign(&registration.payload(&authority, 2))
+            .expect("the key was just generated");
+    }: _(RawOrigin::Signed(caller), authority.clone(), registration.clone(), 2, signature)
+    verify {
+        assert_eq!(BlsKeys::<T>::get(&authority), Some(registration));
+        assert_eq!(BlsRegistrationNonces::<T>::get(&authority), 2);
+    }
+
+    schedule_bls_switch {
+        let first_session = Session::<T>::current_index() + 2;
+    }: _(RawOrigin::Root, first_session)
+    verify {
+        assert_eq!(BlsSwitchSession::<T>::get(), Some(first_session));
+    }
+
+    schedule_consensus_params {
+        let first_session = Session::<T>::current_index() + 2;
+        let params = ConsensusParams {
+            unit_creation_delay: 500,
+            ..Default::default()
+        };
+    }: _(RawOrigin::Root, params, first_session)
+    verify {
+        assert_eq!(Aleph::<T>::consensus_params(first_session), params);
+    }
+
+    schedule_finality_version {
+        let first_session = Session::<T>::current_index() + 2;
+        let version = FinalityVersion::LATEST;
+    }: _(RawOrigin::Root, version, first_session)
+    verify {
+        assert_eq!(Aleph::<T>::finality_version(first_session), version);
+    }
+
+    clear_validators_override {
+        ValidatorsOverride::<T>::put(committee::<T>(T::MaxCommitteeSize::get()));
+    }: _(RawOrigin::Root)
+    verify {
+        assert!(ValidatorsOverride::<T>::get().is_none());
+    }
+}
+
+impl_benchmark_test_suite!(Aleph, crate::mock::new_bench_ext(), crate::mock::Test);
@@ -1,8 +1,36 @@
 //! Aleph session manager.
 //!
 //! This pallet manages the changes in the committee responsible for establishing consensus.
-//! Currently, it's PoA where the validators are set by the root account. In the future, a new
-//! pallet for PoS elections will replace this one.
+//! Candidates bond tokens to stand for election and nominators back them with their own stake.
+//! At every new session the candidates with the highest total backing form the next committee.
+//! The root account can still force a committee through `change_validators`, which takes
+//! precedence over the election and serves as an emergency override. The forced committee stays
+//! in place until root clears it with `clear_validators_override`, and the offenders reported in
+//! the meantime are excluded from the first election afterwards.
+//!
+//! Authorities that sign two different blocks of the same height in one session can be reported
+//! with an unsigned `report_equivocation_unsigned` extrinsic carrying an `EquivocationProof`.
+//!
+//! The first and the last block of every session carry a `ConsensusLog::NextSessionAuthorities`
+//! and a `ConsensusLog::NextSessionFinalityVersion` digest under `ALEPH_ENGINE_ID`, followed by a
+//! `ConsensusLog::NextSessionBlsKeys` one if the next session signs with BLS keys, so that the
+//! committee changes can be followed from the headers alone.
+//!
+//! Authorities can register BLS keys with `register_bls_key`, proving they know the secret keys.
+//! The proofs are checked by the node through the `aleph_bls` host functions. Every registration
+//! of an authority carries a greater nonce than the previous one, so none can be replayed to bring
+//! an old key back. Once root schedules
+//! the switch with `schedule_bls_switch`, every session starting from the given one whose
+//! authorities all have BLS keys is finalized with aggregated BLS signatures instead of sets of
+//! ed25519 signatures. The BLS keys of such sessions are kept in `SessionBlsKeys`.
+//!
+//! The parameters of AlephBFT that the whole committee has to agree on are kept in
+//! `CurrentConsensusParams`. Root changes them with `schedule_consensus_params`, starting from a
+//! future session, so that the finality gadget learns about the change before the session starts.
+//!
+//! Incompatible changes of the finality gadget are rolled out as new finality versions. Root
+//! schedules the switch to a newer version with `schedule_finality_version`, and every node runs
+//! the version returned by `finality_version` for the given session.
 //!
 //! For full integration with Aleph finality gadget, the `primitives::AlephSessionApi` should be implemented.
 
@@ -13,59 +41,280 @@ mod mock;
 #[cfg(test)]
 mod tests;
 
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
 mod migrations;
+pub mod weights;
 
 use frame_support::Parameter;
 use sp_std::prelude::*;
 
 use frame_support::{
     sp_runtime::BoundToRuntimeAppPublic,
-    traits::{OneSessionHandler, StorageVersion},
+    traits::{Currency, OneSessionHandler, StorageVersion},
 };
 pub use pallet::*;
+pub use weights::WeightInfo;
+
+pub type BalanceOf<T> =
+    <<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+
+pub type EquivocationProofOf<T> = primitives::EquivocationProof<
+    <T as frame_system::Config>::Header,
+    <T as Config>::AuthorityId,
+    <<T as Config>::AuthorityId as frame_support::sp_runtime::RuntimeAppPublic>::Signature,
+>;
 
 /// The current storage version.
-const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+const STORAGE_VERSION: StorageVersion = StorageVersion::new(4);
 
 #[frame_support::pallet]
 pub mod pallet {
     use super::*;
     use frame_support::{
         pallet_prelude::*,
-        sp_runtime::{traits::OpaqueKeys, RuntimeAppPublic},
+        sp_runtime::{
+            traits::{
+                Convert, Header as HeaderT, One, OpaqueKeys, SaturatedConversion, Saturating, Zero,
+            },
+            DigestItem, Permill, RuntimeAppPublic,
+        },
         sp_std,
+        traits::{EstimateNextSessionRotation, ReservableCurrency},
     };
-    use frame_system::pallet_prelude::*;
-    use pallet_session::{Pallet as Session, SessionManager};
+    use frame_system::{
+        offchain::{SendTransactionTypes, SubmitTransaction},
+        pallet_prelude::*,
+    };
+    use pallet_session::{Pallet as Session, SessionManager, ShouldEndSession};
     use primitives::{
-        ApiError as AlephApiError, DEFAULT_MILLISECS_PER_BLOCK, DEFAULT_SESSION_PERIOD,
+        ApiError as AlephApiError, BlsKeyRegistration, ConsensusLog, ConsensusParams,
+        FinalityVersion, SessionId, SessionSchedule, SigningDomain, SigningPurpose,
+        ALEPH_ENGINE_ID, DEFAULT_MILLISECS_PER_BLOCK, DEFAULT_SESSION_PERIOD,
+        DEFAULT_UNIT_CREATION_DELAY,
     };
 
+    /// Committee changes scheduled by root, indexed by the session in which they should be
+    /// applied.
     #[pallet::storage]
-    #[pallet::getter(fn validators)]
-    pub type Validators<T: Config> = StorageValue<_, Vec<T::AccountId>, OptionQuery>;
+    #[pallet::getter(fn pending_validators_change)]
+    pub type PendingValidatorsChanges<T: Config> =
+        StorageMap<_, Twox64Concat, u32, Vec<T::AccountId>, OptionQuery>;
 
+    /// The committee forced by root, used instead of the elected one in every new session until
+    /// root clears it.
     #[pallet::storage]
-    #[pallet::getter(fn session_for_validators_change)]
-    pub type SessionForValidatorsChange<T: Config> = StorageValue<_, u32, OptionQuery>;
+    #[pallet::getter(fn validators_override)]
+    pub type ValidatorsOverride<T: Config> = StorageValue<_, Vec<T::AccountId>, OptionQuery>;
 
     #[pallet::config]
-    pub trait Config: frame_system::Config + pallet_session::Config {
+    pub trait Config:
+        frame_system::Config
+        + pallet_session::Config<ValidatorId = <Self as frame_system::Config>::AccountId>
+        + SendTransactionTypes<Call<Self>>
+    {
         type AuthorityId: Member
             + Parameter
             + RuntimeAppPublic
             + Default
             + MaybeSerializeDeserialize;
         type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+        /// The currency in which candidates and nominators bond their stake.
+        type Currency: ReservableCurrency<Self::AccountId>;
+        /// The minimal amount a candidate has to bond on their own to stand for election.
+        #[pallet::constant]
+        type MinCandidateBond: Get<BalanceOf<Self>>;
+        /// The minimal amount a nominator has to bond behind a candidate.
+        #[pallet::constant]
+        type MinNominatorBond: Get<BalanceOf<Self>>;
+        /// The election result is ignored if there are fewer candidates than this.
+        #[pallet::constant]
+        type MinCommitteeSize: Get<u32>;
+        /// The maximal number of validators elected into the committee.
+        #[pallet::constant]
+        type MaxCommitteeSize: Get<u32>;
+        /// The maximal number of accounts standing for election at once, which bounds the work
+        /// done by every election.
+        #[pallet::constant]
+        type MaxCandidates: Get<u32>;
+        /// For how many sessions, including the current one, the authorities are kept in
+        /// `SessionAuthorities`.
+        #[pallet::constant]
+        type SessionHistoryDepth: Get<u32>;
+        /// Weights of the calls. The ones in `weights.rs` are estimates until they are regenerated
+        /// with `.maintain/run_benchmarks.sh`.
+        type WeightInfo: WeightInfo;
     }
 
     #[pallet::event]
-    #[pallet::metadata(T::AccountId = "AccountId")]
+    #[pallet::metadata(
+        T::AccountId = "AccountId",
+        BalanceOf<T> = "Balance",
+        T::AuthorityId = "AuthorityId"
+    )]
     #[pallet::generate_deposit(pub(super) fn deposit_event)]
     pub enum Event<T: Config> {
         ChangeValidators(Vec<T::AccountId>, u32),
+        /// The committee change scheduled for the given session was cancelled.
+        ValidatorsChangeCancelled(u32),
+        /// Starting from the given session (first), sessions will last the given number of blocks
+        /// (second) produced every given number of milliseconds (third).
+        SessionPeriodChangeScheduled(u32, u32, u64),
+        /// A candidate bonded the given amount and stands for election.
+        CandidateBonded(T::AccountId, BalanceOf<T>),
+        /// A candidate withdrew from the election and got their bond back.
+        CandidacyWithdrawn(T::AccountId),
+        /// A nominator (first) backed a candidate (second) with the given amount.
+        Nominated(T::AccountId, T::AccountId, BalanceOf<T>),
+        /// A nominator (first) withdrew their backing of a candidate (second).
+        NominationWithdrawn(T::AccountId, T::AccountId),
+        /// The committee was elected for the given session.
+        CommitteeElected(Vec<T::AccountId>, u32),
+        /// The validator was disabled for the rest of the given session because of an offence.
+        ValidatorDisabled(T::AccountId, u32),
+        /// The validator was reported for signing two different blocks of the same height in the
+        /// given session.
+        EquivocationReported(T::AccountId, u32),
+        /// The authority registered a BLS key.
+        BlsKeyRegistered(T::AuthorityId),
+        /// Sessions starting from the given one will be finalized with BLS signatures.
+        BlsSwitchScheduled(u32),
+        /// Sessions starting from the given one (second) will run with the given consensus
+        /// parameters (first).
+        ConsensusParamsScheduled(ConsensusParams, u32),
+        /// Sessions starting from the given one (second) will run the given finality version
+        /// (first).
+        FinalityVersionScheduled(FinalityVersion, u32),
+        /// The committee forced by root was cleared, the next committees will be elected.
+        ValidatorsOverrideCleared,
+    }
+
+    #[pallet::error]
+    pub enum Error<T> {
+        /// The account already stands for election.
+        AlreadyCandidate,
+        /// The account does not stand for election.
+        NotCandidate,
+        /// The bonded amount is below the required minimum.
+        BondTooLow,
+        /// The candidate has not registered their session keys.
+        NoSessionKeys,
+        /// The account does not back the given candidate.
+        NotNominating,
+        /// The candidate is still backed by nominations, which have to be withdrawn first.
+        HasNominations,
+        /// As many accounts as allowed already stand for election.
+        TooManyCandidates,
+        /// A committee change is already scheduled for the given session.
+        ChangeAlreadyScheduled,
+        /// No committee change is scheduled for the given session.
+        NoChangeScheduled,
+        /// A session period change must start at a future session, after all the already
+        /// scheduled changes, and have non-zero values.
+        InvalidSessionPeriodChange,
+        /// The block time cannot be changed, as nodes only read the slot duration at startup.
+        BlockTimeChangeUnsupported,
+        /// The reported offender is not a member of the committee of the session.
+        NotValidator,
+        /// The equivocation proof is malformed, contains invalid signatures or does not concern
+        /// one of the sessions in the history.
+        InvalidEquivocationProof,
+        /// The equivocation of the given authority in the given session was already reported.
+        DuplicateEquivocationReport,
+        /// The BLS key registration is not signed by the authority.
+        InvalidBlsKeyRegistration,
+        /// The nonce of the BLS key registration is not greater than the one of the previous
+        /// registration of the authority.
+        StaleBlsKeyRegistration,
+        /// The proof of possession of the BLS key is invalid.
+        InvalidBlsKeyPossession,
+        /// The switch to BLS signatures must be scheduled for a future session.
+        InvalidBlsSwitch,
+        /// Consensus parameters must be scheduled for a session after the next one and allow
+        /// AlephBFT to run.
+        InvalidConsensusParams,
+        /// A finality version must be scheduled for a session after the next one, whose version
+        /// is already announced, be newer than the current one and be known to the runtime.
+        InvalidFinalityVersion,
+        /// The finality version scheduled for the next session is already announced and can no
+        /// longer be replaced.
+        FinalityVersionAnnounced,
+        /// No committee is forced by root.
+        NoValidatorsOverride,
     }
 
+    /// Self-bonds of all the accounts standing for election.
+    #[pallet::storage]
+    #[pallet::getter(fn candidates)]
+    pub type Candidates<T: Config> =
+        StorageMap<_, Twox64Concat, T::AccountId, BalanceOf<T>, OptionQuery>;
+
+    /// The number of entries in `Candidates`.
+    #[pallet::storage]
+    #[pallet::getter(fn candidate_count)]
+    pub type CandidateCount<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+    /// Amounts bonded by nominators (first key) behind candidates (second key).
+    #[pallet::storage]
+    #[pallet::getter(fn nominations)]
+    pub type Nominations<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        T::AccountId,
+        Twox64Concat,
+        T::AccountId,
+        BalanceOf<T>,
+        OptionQuery,
+    >;
+
+    /// Indices of the authorities disabled in the current session.
+    #[pallet::storage]
+    #[pallet::getter(fn disabled_authorities)]
+    pub type DisabledAuthorities<T: Config> = StorageValue<_, Vec<u32>, ValueQuery>;
+
+    /// Validators that committed an offence since the last committee was planned. They will be
+    /// excluded from the next planned committee.
+    #[pallet::storage]
+    #[pallet::getter(fn offenders)]
+    pub type Offenders<T: Config> = StorageValue<_, Vec<T::AccountId>, ValueQuery>;
+
+    /// Authorities (second key) reported for equivocating in the given session (first key).
+    #[pallet::storage]
+    #[pallet::getter(fn reported_equivocations)]
+    pub type ReportedEquivocations<T: Config> =
+        StorageDoubleMap<_, Twox64Concat, u32, Twox64Concat, T::AuthorityId, (), OptionQuery>;
+
+    /// Total amount nominated behind every candidate, excluding their own bond.
+    #[pallet::storage]
+    #[pallet::getter(fn nominated_total)]
+    pub type NominatedTotal<T: Config> =
+        StorageMap<_, Twox64Concat, T::AccountId, BalanceOf<T>, ValueQuery>;
+
+    /// BLS keys registered by the authorities.
+    #[pallet::storage]
+    #[pallet::getter(fn bls_keys)]
+    pub type BlsKeys<T: Config> =
+        StorageMap<_, Twox64Concat, T::AuthorityId, BlsKeyRegistration, OptionQuery>;
+
+    /// The nonce of the last BLS key registration of every authority. Never pruned, so that the
+    /// registrations made before cannot be replayed.
+    #[pallet::storage]
+    #[pallet::getter(fn bls_registration_nonce)]
+    pub type BlsRegistrationNonces<T: Config> =
+        StorageMap<_, Twox64Concat, T::AuthorityId, u32, ValueQuery>;
+
+    /// The first session that can be finalized with BLS signatures.
+    #[pallet::storage]
+    #[pallet::getter(fn bls_switch_session)]
+    pub type BlsSwitchSession<T: Config> = StorageValue<_, u32, OptionQuery>;
+
+    /// BLS keys of the authorities of the sessions finalized with BLS signatures, in the order of
+    /// the authorities. Kept for `SessionHistoryDepth` sessions, like `SessionAuthorities`.
+    #[pallet::storage]
+    #[pallet::getter(fn session_bls_keys)]
+    pub type SessionBlsKeys<T: Config> =
+        StorageMap<_, Twox64Concat, u32, Vec<BlsKeyRegistration>, OptionQuery>;
+
     pub struct AlephSessionManager<T>(sp_std::marker::PhantomData<T>);
 
     #[pallet::pallet]
@@ -76,32 +325,379 @@ pub mod pallet {
     impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
         fn on_runtime_upgrade() -> frame_support::weights::Weight {
             migrations::v0_to_v1::migrate::<T, Self>()
+                + migrations::v1_to_v2::migrate::<T, Self>()
+                + migrations::v2_to_v3::migrate::<T, Self>()
+                + migrations::v3_to_v4::migrate::<T, Self>()
+        }
+
+        fn on_initialize(_: T::BlockNumber) -> Weight {
+            T::DbWeight::get().reads(4)
+        }
+
+        /// Repeats the announcement of the next session authorities in the last block of the
+        /// session, so that it is covered by the justification closing the session.
+        fn on_finalize(now: T::BlockNumber) {
+            if !T::ShouldEndSession::should_end_session(now + One::one())
+                || Self::next_session_authorities_announced()
+            {
+                return;
+            }
+            if let Ok(authorities) = Self::next_session_authorities() {
+                Self::deposit_next_session_authorities(
+                    Session::<T>::current_index() + 1,
+                    authorities,
+                );
+            }
         }
     }
 
     #[pallet::call]
     impl<T: Config> Pallet<T> {
-        #[pallet::weight((T::BlockWeights::get().max_block, DispatchClass::Operational))]
+        /// Forces the committee starting from the given session, in place of the elected ones,
+        /// until cleared with `clear_validators_override`.
+        #[pallet::weight((
+            T::WeightInfo::change_validators(validators.len() as u32),
+            DispatchClass::Operational
+        ))]
         pub fn change_validators(
             origin: OriginFor<T>,
             validators: Vec<T::AccountId>,
             session_for_validators_change: u32,
         ) -> DispatchResult {
             ensure_root(origin)?;
-            Validators::<T>::put(validators.clone());
-            SessionForValidatorsChange::<T>::put(session_for_validators_change);
+            ensure!(
+                !PendingValidatorsChanges::<T>::contains_key(session_for_validators_change),
+                Error::<T>::ChangeAlreadyScheduled
+            );
+            PendingValidatorsChanges::<T>::insert(
+                session_for_validators_change,
+                validators.clone(),
+            );
             Self::deposit_event(Event::ChangeValidators(
                 validators,
                 session_for_validators_change,
             ));
             Ok(())
         }
+
+        /// Cancels the committee change scheduled for the given session.
+        #[pallet::weight((T::WeightInfo::cancel_validators_change(), DispatchClass::Operational))]
+        pub fn cancel_validators_change(
+            origin: OriginFor<T>,
+            session_for_validators_change: u32,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+            PendingValidatorsChanges::<T>::take(session_for_validators_change)
+                .ok_or(Error::<T>::NoChangeScheduled)?;
+            Self::deposit_event(Event::ValidatorsChangeCancelled(
+                session_for_validators_change,
+            ));
+            Ok(())
+        }
+
+        /// Reports an offence committed by a member of the current committee. The offender is
+        /// disabled for the rest of the session and excluded from the next planned committee.
+        #[pallet::weight((T::WeightInfo::report_offence(), DispatchClass::Operational))]
+        pub fn report_offence(origin: OriginFor<T>, offender: T::AccountId) -> DispatchResult {
+            ensure_root(origin)?;
+            Self::handle_offence(offender)
+        }
+
+        /// Reports an authority that signed two different blocks of the same height in one of the
+        /// last `SessionHistoryDepth` sessions. The offender is punished as in `report_offence`,
+        /// except that an offender no longer in the committee is only excluded from the next
+        /// planned one. Only accepted when submitted by a block author, see
+        /// `submit_unsigned_equivocation_report`.
+        #[pallet::weight((
+            T::WeightInfo::report_equivocation_unsigned(),
+            DispatchClass::Operational
+        ))]
+        pub fn report_equivocation_unsigned(
+            origin: OriginFor<T>,
+            equivocation_proof: EquivocationProofOf<T>,
+        ) -> DispatchResult {
+            ensure_none(origin)?;
+            let offender = Self::check_equivocation_proof(&equivocation_proof)?;
+            ReportedEquivocations::<T>::insert(
+                equivocation_proof.session,
+                &equivocation_proof.offender,
+                (),
+            );
+            Self::deposit_event(Event::EquivocationReported(
+                offender.clone(),
+                equivocation_proof.session,
+            ));
+            if Session::<T>::validators().contains(&offender) {
+                Self::handle_offence(offender)
+            } else {
+                Self::exclude_from_next_committee(offender);
+                Ok(())
+            }
+        }
+
+        /// Schedules a change of the session period starting at `first_session`. The change must
+        /// be included in a block before `first_session` starts, so that the finality gadget
+        /// learns about it when reading the state at the end of the previous session.
+        ///
+        /// The block time cannot change yet, so `millisecs_per_block` must be the current one:
+        /// the Aura slot duration follows it and nodes read the slot duration only when they
+        /// start, so they would stop producing blocks after such a change.
+        #[pallet::weight((
+            T::WeightInfo::schedule_session_period_change(),
+            DispatchClass::Operational
+        ))]
+        pub fn schedule_session_period_change(
+            origin: OriginFor<T>,
+            session_period: u32,
+            millisecs_per_block: u64,
+            first_session: u32,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(
+                first_session > Session::<T>::current_index(),
+                Error::<T>::InvalidSessionPeriodChange
+            );
+            let schedule = Self::session_schedule();
+            ensure!(
+                millisecs_per_block == schedule.latest().millisecs_per_block,
+                Error::<T>::BlockTimeChangeUnsupported
+            );
+            let schedule = schedule
+                .with_change(first_session, session_period, millisecs_per_block)
+                .ok_or(Error::<T>::InvalidSessionPeriodChange)?;
+            ScheduledSessionPeriods::<T>::put(schedule);
+            Self::deposit_event(Event::SessionPeriodChangeScheduled(
+                first_session,
+                session_period,
+                millisecs_per_block,
+            ));
+            Ok(())
+        }
+
+        /// Bonds `amount` of the caller's funds and makes the caller a candidate in the next
+        /// elections. The caller must have already set their session keys.
+        #[pallet::weight(T::WeightInfo::bond_candidate())]
+        pub fn bond_candidate(origin: OriginFor<T>, amount: BalanceOf<T>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(
+                !Candidates::<T>::contains_key(&who),
+                Error::<T>::AlreadyCandidate
+            );
+            ensure!(amount >= T::MinCandidateBond::get(), Error::<T>::BondTooLow);
+            ensure!(Self::has_session_keys(&who), Error::<T>::NoSessionKeys);
+            let candidate_count = CandidateCount::<T>::get();
+            ensure!(
+                candidate_count < T::MaxCandidates::get(),
+                Error::<T>::TooManyCandidates
+            );
+
+            T::Currency::reserve(&who, amount)?;
+            Candidates::<T>::insert(&who, amount);
+            CandidateCount::<T>::put(candidate_count + 1);
+            Self::deposit_event(Event::CandidateBonded(who, amount));
+            Ok(())
+        }
+
+        /// Withdraws the caller from the elections and unreserves their bond. Takes effect
+        /// starting from the next election, the current committee is not affected. All the
+        /// nominations of the caller have to be withdrawn first.
+        #[pallet::weight(T::WeightInfo::withdraw_candidacy())]
+        pub fn withdraw_candidacy(origin: OriginFor<T>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(
+                !NominatedTotal::<T>::contains_key(&who),
+                Error::<T>::HasNominations
+            );
+            let bond = Candidates::<T>::take(&who).ok_or(Error::<T>::NotCandidate)?;
+
+            T::Currency::unreserve(&who, bond);
+            CandidateCount::<T>::mutate(|count| *count = count.saturating_sub(1));
+            Self::deposit_event(Event::CandidacyWithdrawn(who));
+            Ok(())
+        }
+
+        /// Bonds `amount` of the caller's funds behind `candidate`. Nominating the same
+        /// candidate again increases the backing.
+        #[pallet::weight(T::WeightInfo::nominate())]
+        pub fn nominate(
+            origin: OriginFor<T>,
+            candidate: T::AccountId,
+            amount: BalanceOf<T>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(
+                Candidates::<T>::contains_key(&candidate),
+                Error::<T>::NotCandidate
+            );
+            let nominated = Nominations::<T>::get(&who, &candidate)
+                .unwrap_or_else(Zero::zero)
+                .saturating_add(amount);
+            ensure!(
+                nominated >= T::MinNominatorBond::get(),
+                Error::<T>::BondTooLow
+            );
+
+            T::Currency::reserve(&who, amount)?;
+            Nominations::<T>::insert(&who, &candidate, nominated);
+            NominatedTotal::<T>::mutate(&candidate, |total| *total = total.saturating_add(amount));
+            Self::deposit_event(Event::Nominated(who, candidate, amount));
+            Ok(())
+        }
+
+        /// Withdraws the whole backing of `candidate` by the caller and unreserves it.
+        #[pallet::weight(T::WeightInfo::withdraw_nomination())]
+        pub fn withdraw_nomination(
+            origin: OriginFor<T>,
+            candidate: T::AccountId,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let nominated =
+                Nominations::<T>::take(&who, &candidate).ok_or(Error::<T>::NotNominating)?;
+
+            T::Currency::unreserve(&who, nominated);
+            NominatedTotal::<T>::mutate_exists(&candidate, |maybe_total| {
+                *maybe_total = maybe_total
+                    .map(|total| total.saturating_sub(nominated))
+                    .filter(|total| !total.is_zero())
+            });
+            Self::deposit_event(Event::NominationWithdrawn(who, candidate));
+            Ok(())
+        }
+
+        /// Registers a BLS key of `authority`, replacing the previous one. The registration has to
+        /// be signed with the `authority` key together with `nonce`, which must be greater than
+        /// the nonce of the previous registration of `authority`, so that old registrations cannot
+        /// be replayed. It also has to carry a valid proof of possession, which is checked by the
+        /// node through the `aleph_bls` host functions. Takes effect starting from the next
+        /// planned committee.
+        #[pallet::weight(T::WeightInfo::register_bls_key())]
+        pub fn register_bls_key(
+            origin: OriginFor<T>,
+            authority: T::AuthorityId,
+            registration: BlsKeyRegistration,
+            nonce: u32,
+            signature: <T::AuthorityId as RuntimeAppPublic>::Signature,
+        ) -> DispatchResult {
+            ensure_signed(origin)?;
+            ensure!(
+                nonce > Self::bls_registration_nonce(&authority),
+                Error::<T>::StaleBlsKeyRegistration
+            );
+            ensure!(
+                authority.verify(&registration.payload(&authority, nonce), &signature),
+                Error::<T>::InvalidBlsKeyRegistration
+            );
+            ensure!(
+                aleph_justification::verify_possession(&registration),
+                Error::<T>::InvalidBlsKeyPossession
+            );
+            BlsRegistrationNonces::<T>::insert(&authority, nonce);
+            BlsKeys::<T>::insert(&authority, registration);
+            Self::deposit_event(Event::BlsKeyRegistered(authority));
+            Ok(())
+        }
+
+        /// Makes the sessions starting from `first_session` finalized with BLS signatures, as
+        /// long as all their authorities registered BLS keys. The switch must be scheduled at
+        /// least two sessions ahead, as the keys of a session are fixed when it is planned.
+        #[pallet::weight((T::WeightInfo::schedule_bls_switch(), DispatchClass::Operational))]
+        pub fn schedule_bls_switch(origin: OriginFor<T>, first_session: u32) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(
+                first_session > Session::<T>::current_index() + 1,
+                Error::<T>::InvalidBlsSwitch
+            );
+            BlsSwitchSession::<T>::put(first_session);
+            Self::deposit_event(Event::BlsSwitchScheduled(first_session));
+            Ok(())
+        }
+
+        /// Makes the sessions starting from `first_session` run with the given consensus
+        /// parameters. The parameters of the next session are settled, so `first_session` must
+        /// come after it. Replaces the parameters already scheduled for that session.
+        #[pallet::weight((T::WeightInfo::schedule_consensus_params(), DispatchClass::Operational))]
+        pub fn schedule_consensus_params(
+            origin: OriginFor<T>,
+            params: ConsensusParams,
+            first_session: u32,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(
+                first_session > Session::<T>::current_index() + 1 && params.is_valid(),
+                Error::<T>::InvalidConsensusParams
+            );
+            ScheduledConsensusParams::<T>::mutate(|scheduled| {
+                scheduled.retain(|(session, _)| *session != first_session);
+                scheduled.push((first_session, params));
+                scheduled.sort_by_key(|(session, _)| *session);
+            });
+            Self::deposit_event(Event::ConsensusParamsScheduled(params, first_session));
+            Ok(())
+        }
+
+        /// Makes the sessions starting from `first_session` run the given finality version.
+        /// Replaces the version already scheduled, unless it starts with the next session and is
+        /// therefore already announced.
+        #[pallet::weight((T::WeightInfo::schedule_finality_version(), DispatchClass::Operational))]
+        pub fn schedule_finality_version(
+            origin: OriginFor<T>,
+            version: FinalityVersion,
+            first_session: u32,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(
+                first_session > Session::<T>::current_index() + 1
+                    && version > Self::current_finality_version()
+                    && version.is_known(),
+                Error::<T>::InvalidFinalityVersion
+            );
+            if let Some((scheduled_session, _)) = Self::next_finality_version() {
+                ensure!(
+                    scheduled_session > Session::<T>::current_index() + 1,
+                    Error::<T>::FinalityVersionAnnounced
+                );
+            }
+            NextFinalityVersion::<T>::put((first_session, version));
+            Self::deposit_event(Event::FinalityVersionScheduled(version, first_session));
+            Ok(())
+        }
+
+        /// Stops forcing the committee applied by `change_validators`, so that the committees
+        /// of the sessions planned from now on are elected again. Committee changes that are
+        /// still scheduled are not affected.
+        #[pallet::weight((T::WeightInfo::clear_validators_override(), DispatchClass::Operational))]
+        pub fn clear_validators_override(origin: OriginFor<T>) -> DispatchResult {
+            ensure_root(origin)?;
+            ValidatorsOverride::<T>::take().ok_or(Error::<T>::NoValidatorsOverride)?;
+            Self::deposit_event(Event::ValidatorsOverrideCleared);
+            Ok(())
+        }
     }
 
     #[pallet::storage]
     #[pallet::getter(fn authorities)]
     pub(super) type Authorities<T: Config> = StorageValue<_, Vec<T::AuthorityId>, ValueQuery>;
 
+    /// Authorities of the past `SessionHistoryDepth` sessions, including the current one.
+    #[pallet::storage]
+    #[pallet::getter(fn session_authorities)]
+    pub type SessionAuthorities<T: Config> =
+        StorageMap<_, Twox64Concat, u32, Vec<T::AuthorityId>, OptionQuery>;
+
+    /// Accounts of the validators of the given session, in the order of the authorities. Kept for
+    /// `SessionHistoryDepth` sessions, like `SessionAuthorities`.
+    #[pallet::storage]
+    #[pallet::getter(fn session_validators)]
+    pub type SessionValidators<T: Config> =
+        StorageMap<_, Twox64Concat, u32, Vec<T::AccountId>, OptionQuery>;
+
+    /// Finality versions the given sessions started with. Kept for `SessionHistoryDepth`
+    /// sessions, like `SessionAuthorities`.
+    #[pallet::storage]
+    #[pallet::getter(fn session_finality_version)]
+    pub type SessionFinalityVersions<T: Config> =
+        StorageMap<_, Twox64Concat, u32, FinalityVersion, OptionQuery>;
+
     #[pallet::type_value]
     pub(super) fn DefaultForSessionPeriod() -> u32 {
         DEFAULT_SESSION_PERIOD
@@ -122,11 +718,41 @@ pub mod pallet {
     pub(super) type MillisecsPerBlock<T: Config> =
         StorageValue<_, u64, ValueQuery, DefaultForMillisecsPerBlock>;
 
+    /// History and scheduled future changes of the session period and block time. Empty until the
+    /// first change is scheduled, in which case the genesis values are in effect.
+    #[pallet::storage]
+    pub(super) type ScheduledSessionPeriods<T: Config> =
+        StorageValue<_, SessionSchedule, OptionQuery>;
+
+    /// The consensus parameters of the current session.
+    #[pallet::storage]
+    #[pallet::getter(fn current_consensus_params)]
+    pub type CurrentConsensusParams<T: Config> = StorageValue<_, ConsensusParams, ValueQuery>;
+
+    /// Consensus parameters scheduled for future sessions, ordered by the first session they are
+    /// in effect in.
+    #[pallet::storage]
+    #[pallet::getter(fn scheduled_consensus_params)]
+    pub type ScheduledConsensusParams<T: Config> =
+        StorageValue<_, Vec<(u32, ConsensusParams)>, ValueQuery>;
+
+    /// The finality version of the current session.
+    #[pallet::storage]
+    #[pallet::getter(fn current_finality_version)]
+    pub type CurrentFinalityVersion<T: Config> = StorageValue<_, FinalityVersion, ValueQuery>;
+
+    /// The finality version scheduled for the sessions starting from the given one.
+    #[pallet::storage]
+    #[pallet::getter(fn next_finality_version)]
+    pub type NextFinalityVersion<T: Config> = StorageValue<_, (u32, FinalityVersion), OptionQuery>;
+
     #[pallet::genesis_config]
     pub struct GenesisConfig<T: Config> {
         pub authorities: Vec<T::AuthorityId>,
         pub session_period: u32,
         pub millisecs_per_block: u64,
+        pub unit_creation_delay: u64,
+        pub finality_version: FinalityVersion,
     }
 
     #[cfg(feature = "std")]
@@ -136,6 +762,8 @@ pub mod pallet {
                 authorities: Vec::new(),
                 session_period: DEFAULT_SESSION_PERIOD,
                 millisecs_per_block: DEFAULT_MILLISECS_PER_BLOCK,
+                unit_creation_delay: DEFAULT_UNIT_CREATION_DELAY,
+                finality_version: FinalityVersion::default(),
             }
         }
     }
@@ -145,6 +773,12 @@ pub mod pallet {
         fn build(&self) {
             <SessionPeriod<T>>::put(&self.session_period);
             <MillisecsPerBlock<T>>::put(&self.millisecs_per_block);
+            <CurrentConsensusParams<T>>::put(ConsensusParams {
+                unit_creation_delay: self.unit_creation_delay,
+                ..Default::default()
+            });
+            <CurrentFinalityVersion<T>>::put(self.finality_version);
+            <SessionFinalityVersions<T>>::insert(0, self.finality_version);
         }
     }
 
@@ -156,11 +790,81 @@ pub mod pallet {
                     "Authorities are already initialized!"
                 );
                 <Authorities<T>>::put(authorities);
+                Self::record_session_authorities(0, authorities);
             }
         }
 
         pub(crate) fn update_authorities(authorities: &[T::AuthorityId]) {
             <Authorities<T>>::put(authorities);
+            Self::record_session_authorities(Session::<T>::current_index(), authorities);
+        }
+
+        /// Stores the authorities of `session` and forgets the ones that are older than
+        /// `SessionHistoryDepth` sessions.
+        pub(crate) fn record_session_authorities(session: u32, authorities: &[T::AuthorityId]) {
+            SessionAuthorities::<T>::insert(session, authorities);
+            if let Some(expired) = session.checked_sub(T::SessionHistoryDepth::get()) {
+                SessionAuthorities::<T>::remove(expired);
+                SessionValidators::<T>::remove(expired);
+                SessionFinalityVersions::<T>::remove(expired);
+                SessionBlsKeys::<T>::remove(expired);
+            }
+        }
+
+        /// Stores the accounts of the validators of `session`, pruned with the authorities.
+        pub(crate) fn record_session_validators(session: u32, validators: Vec<T::AccountId>) {
+            SessionValidators::<T>::insert(session, validators);
+        }
+
+        /// Fixes the BLS keys of the authorities of `session`, if it is to be finalized with BLS
+        /// signatures and all the authorities registered their keys.
+        pub(crate) fn record_session_bls_keys(session: u32, authorities: &[T::AuthorityId]) {
+            match Self::bls_switch_session() {
+                Some(first_session) if first_session <= session => {}
+                _ => return,
+            }
+            if let Some(keys) = authorities
+                .iter()
+                .map(BlsKeys::<T>::get)
+                .collect::<Option<Vec<_>>>()
+            {
+                SessionBlsKeys::<T>::insert(session, keys);
+            }
+        }
+
+        /// Announces the authorities and the finality version of `session` in the digest of the
+        /// current block, together with their BLS keys if the session signs with them.
+        pub(crate) fn deposit_next_session_authorities(
+            session: u32,
+            authorities: Vec<T::AuthorityId>,
+        ) {
+            let mut logs = sp_std::vec![
+                ConsensusLog::NextSessionAuthorities {
+                    session,
+                    authorities,
+                },
+                ConsensusLog::NextSessionFinalityVersion {
+                    session,
+                    version: Self::finality_version(session),
+                },
+            ];
+            if let Some(keys) = Self::session_bls_keys(session) {
+                logs.push(ConsensusLog::NextSessionBlsKeys { session, keys });
+            }
+            for log in logs.iter() {
+                <frame_system::Pallet<T>>::deposit_log(DigestItem::Consensus(
+                    ALEPH_ENGINE_ID,
+                    log.encode(),
+                ));
+            }
+        }
+
+        fn next_session_authorities_announced() -> bool {
+            <frame_system::Pallet<T>>::digest()
+                .logs()
+                .iter()
+                .filter_map(|log| log.as_consensus())
+                .any(|(engine_id, _)| engine_id == ALEPH_ENGINE_ID)
         }
 
         pub fn next_session_authorities() -> Result<Vec<T::AuthorityId>, AlephApiError> {
@@ -169,28 +873,318 @@ pub mod pallet {
                 .map(|(_, key)| key.get(T::AuthorityId::ID).ok_or(AlephApiError::DecodeKey))
                 .collect::<Result<Vec<T::AuthorityId>, AlephApiError>>()
         }
+
+        /// The schedule of session periods and block times, both past and future.
+        pub fn session_schedule() -> SessionSchedule {
+            ScheduledSessionPeriods::<T>::get().unwrap_or_else(|| {
+                SessionSchedule::new(Self::session_period(), Self::millisecs_per_block())
+            })
+        }
+
+        /// Updates the session period and block time to the values in effect in `session`.
+        pub(crate) fn update_session_period(session: u32) {
+            if let Some(schedule) = ScheduledSessionPeriods::<T>::get() {
+                let change = schedule.for_session(session);
+                <SessionPeriod<T>>::put(change.session_period);
+                <MillisecsPerBlock<T>>::put(change.millisecs_per_block);
+            }
+        }
+
+        /// The consensus parameters of `session`, which is either the current or a future one.
+        pub fn consensus_params(session: u32) -> ConsensusParams {
+            Self::scheduled_consensus_params()
+                .into_iter()
+                .rev()
+                .find(|(first_session, _)| *first_session <= session)
+                .map(|(_, params)| params)
+                .unwrap_or_else(Self::current_consensus_params)
+        }
+
+        /// Puts the consensus parameters of `session` in effect and forgets the ones scheduled
+        /// for earlier sessions.
+        pub(crate) fn update_consensus_params(session: u32) {
+            let mut scheduled = Self::scheduled_consensus_params();
+            if !scheduled
+                .iter()
+                .any(|(first_session, _)| *first_session <= session)
+            {
+                return;
+            }
+            CurrentConsensusParams::<T>::put(Self::consensus_params(session));
+            scheduled.retain(|(first_session, _)| *first_session > session);
+            ScheduledConsensusParams::<T>::put(scheduled);
+        }
+
+        /// The finality version of `session`, which is either the current or a future one.
+        pub fn finality_version(session: u32) -> FinalityVersion {
+            match Self::next_finality_version() {
+                Some((first_session, version)) if first_session <= session => version,
+                _ => Self::current_finality_version(),
+            }
+        }
+
+        /// Puts the finality version of `session` in effect and records it, pruned with the
+        /// authorities.
+        pub(crate) fn update_finality_version(session: u32) {
+            if let Some((first_session, version)) = Self::next_finality_version() {
+                if first_session <= session {
+                    CurrentFinalityVersion::<T>::put(version);
+                    NextFinalityVersion::<T>::kill();
+                }
+            }
+            SessionFinalityVersions::<T>::insert(session, Self::current_finality_version());
+        }
+
+        /// The domain in which the block hashes of `session` are signed, if the session signs in
+        /// domains. Only known for the sessions in the history.
+        pub fn finality_domain(session: u32) -> Option<Option<SigningDomain>> {
+            let version = match Self::session_finality_version(session) {
+                Some(version) => version,
+                // The finality version of the sessions started before it was recorded.
+                None if session == Session::<T>::current_index() => {
+                    Self::current_finality_version()
+                }
+                None => return None,
+            };
+            let genesis_hash = <frame_system::Pallet<T>>::block_hash(T::BlockNumber::zero());
+            Some(version.signs_in_domains().then(|| {
+                SigningDomain::new(
+                    SigningPurpose::Finality,
+                    genesis_hash.encode(),
+                    SessionId(session),
+                )
+            }))
+        }
+
+        /// All the scheduled committee changes, ordered by the session of the change.
+        pub fn scheduled_validators_changes() -> Vec<(u32, Vec<T::AccountId>)> {
+            let mut changes = PendingValidatorsChanges::<T>::iter().collect::<Vec<_>>();
+            changes.sort_by_key(|(session, _)| *session);
+            changes
+        }
+
+        /// Removes all the committee changes scheduled for `session` or earlier and returns the
+        /// latest of them.
+        pub(crate) fn take_validators_change(session: u32) -> Option<Vec<T::AccountId>> {
+            let mut due = Self::scheduled_validators_changes();
+            due.retain(|(change_session, _)| *change_session <= session);
+            for (change_session, _) in &due {
+                PendingValidatorsChanges::<T>::remove(change_session);
+            }
+            due.pop().map(|(_, validators)| validators)
+        }
+
+        fn has_session_keys(who: &T::AccountId) -> bool {
+            T::ValidatorIdOf::convert(who.clone())
+                .map(pallet_session::NextKeys::<T>::contains_key)
+                .unwrap_or(false)
+        }
+
+        /// Disables `offender` for the rest of the current session and excludes them from the next
+        /// planned committee.
+        pub fn handle_offence(offender: T::AccountId) -> DispatchResult {
+            Session::<T>::disable(&offender).map_err(|_| Error::<T>::NotValidator)?;
+            Self::exclude_from_next_committee(offender.clone());
+            Self::deposit_event(Event::ValidatorDisabled(
+                offender,
+                Session::<T>::current_index(),
+            ));
+            Ok(())
+        }
+
+        /// Excludes `offender` from the next planned committee.
+        fn exclude_from_next_committee(offender: T::AccountId) {
+            Offenders::<T>::mutate(|offenders| {
+                if !offenders.contains(&offender) {
+                    offenders.push(offender)
+                }
+            });
+        }
+
+        /// Checks that the proof is valid, concerns one of the sessions in the history and was not
+        /// reported yet. Returns the account of the offender.
+        pub fn check_equivocation_proof(
+            proof: &EquivocationProofOf<T>,
+        ) -> Result<T::AccountId, DispatchError> {
+            let domain =
+                Self::finality_domain(proof.session).ok_or(Error::<T>::InvalidEquivocationProof)?;
+            ensure!(
+                proof.is_valid(domain.as_ref()),
+                Error::<T>::InvalidEquivocationProof
+            );
+            let schedule = Self::session_schedule();
+            let number = (*proof.first_header.number()).saturated_into::<u32>();
+            ensure!(
+                schedule.session_of_block(number) == proof.session,
+                Error::<T>::InvalidEquivocationProof
+            );
+            ensure!(
+                !ReportedEquivocations::<T>::contains_key(proof.session, &proof.offender),
+                Error::<T>::DuplicateEquivocationReport
+            );
+            let authorities =
+                Self::session_authorities(proof.session).ok_or(Error::<T>::NotValidator)?;
+            let validators = match Self::session_validators(proof.session) {
+                Some(validators) => validators,
+                // The validators of the sessions started before they were recorded.
+                None if proof.session == Session::<T>::current_index() => {
+                    Session::<T>::validators()
+                }
+                None => return Err(Error::<T>::NotValidator.into()),
+            };
+            // Authorities are stored in the same order as the session validators.
+            let index = authorities
+                .iter()
+                .position(|authority| authority == &proof.offender)
+                .ok_or(Error::<T>::NotValidator)?;
+            validators
+                .get(index)
+                .cloned()
+                .ok_or_else(|| Error::<T>::NotValidator.into())
+        }
+
+        /// Submits an unsigned `report_equivocation_unsigned` extrinsic to the local transaction
+        /// pool. Meant to be called through the runtime API by the finality gadget.
+        pub fn submit_unsigned_equivocation_report(
+            equivocation_proof: EquivocationProofOf<T>,
+        ) -> Option<()> {
+            let call = Call::report_equivocation_unsigned(equivocation_proof);
+            SubmitTransaction::<T, Call<T>>::submit_unsigned_transaction(call.into()).ok()
+        }
+
+        /// Total stake backing `candidate`, i.e. their own bond and all the nominations.
+        pub fn total_backing(candidate: &T::AccountId) -> Option<BalanceOf<T>> {
+            Candidates::<T>::get(candidate)
+                .map(|bond| bond.saturating_add(NominatedTotal::<T>::get(candidate)))
+        }
+
+        /// Chooses at most `MaxCommitteeSize` candidates with the highest total backing, skipping
+        /// the `excluded` ones. Ties are resolved by account ids. Returns `None` if there are fewer
+        /// than `MinCommitteeSize` eligible candidates. Goes through at most `MaxCandidates`
+        /// candidates.
+        pub fn elect_committee(excluded: &[T::AccountId]) -> Option<Vec<T::AccountId>> {
+            let mut candidates = Candidates::<T>::iter()
+                .filter(|(who, _)| !excluded.contains(who))
+                .map(|(who, bond)| {
+                    let backing = bond.saturating_add(NominatedTotal::<T>::get(&who));
+                    (who, backing)
+                })
+                .collect::<Vec<_>>();
+            if candidates.is_empty() || candidates.len() < T::MinCommitteeSize::get() as usize {
+                return None;
+            }
+            candidates.sort_by(|(a, a_backing), (b, b_backing)| {
+                b_backing.cmp(a_backing).then_with(|| a.cmp(b))
+            });
+            candidates.truncate(T::MaxCommitteeSize::get() as usize);
+            Some(candidates.into_iter().map(|(who, _)| who).collect())
+        }
+    }
+
+    #[pallet::validate_unsigned]
+    impl<T: Config> ValidateUnsigned for Pallet<T> {
+        type Call = Call<T>;
+
+        fn validate_unsigned(source: TransactionSource, call: &Self::Call) -> TransactionValidity {
+            let equivocation_proof = match call {
+                Call::report_equivocation_unsigned(equivocation_proof) => equivocation_proof,
+                _ => return InvalidTransaction::Call.into(),
+            };
+            // Reports are only accepted from the local node or in blocks, so that they cannot be
+            // used to spam the network.
+            match source {
+                TransactionSource::Local | TransactionSource::InBlock => {}
+                _ => return InvalidTransaction::Call.into(),
+            }
+            Self::check_equivocation_proof(equivocation_proof)
+                .map_err(|_| InvalidTransaction::BadProof)?;
+
+            ValidTransaction::with_tag_prefix("AlephEquivocation")
+                .priority(TransactionPriority::max_value())
+                .and_provides((
+                    equivocation_proof.session,
+                    equivocation_proof.offender.clone(),
+                ))
+                .longevity(Self::session_period().into())
+                .propagate(false)
+                .build()
+        }
     }
 
     impl<T: Config> SessionManager<T::AccountId> for AlephSessionManager<T> {
         fn new_session(session: u32) -> Option<Vec<T::AccountId>> {
-            if let Some(session_for_validators_change) =
-                Pallet::<T>::session_for_validators_change()
-            {
-                if session_for_validators_change <= session {
-                    let validators = Validators::<T>::take()
-                        .expect("When SessionForValidatorsChange is Some so should be Validators");
-                    let _ = SessionForValidatorsChange::<T>::take().unwrap();
-                    return Some(validators);
-                }
+            if let Some(validators) = Pallet::<T>::take_validators_change(session) {
+                ValidatorsOverride::<T>::put(validators);
+            }
+            // The emergency override is applied as is, even if it contains offenders, who are
+            // kept to be excluded from the first election after the override is cleared.
+            if let Some(validators) = Pallet::<T>::validators_override() {
+                return Some(validators);
+            }
+            let offenders = Offenders::<T>::take();
+            if let Some(elected) = Pallet::<T>::elect_committee(&offenders) {
+                Pallet::<T>::deposit_event(Event::CommitteeElected(elected.clone(), session));
+                return Some(elected);
+            }
+            if offenders.is_empty() {
+                return None;
+            }
+            let validators = Session::<T>::validators()
+                .into_iter()
+                .filter(|validator| !offenders.contains(validator))
+                .collect::<Vec<_>>();
+            if validators.is_empty() {
+                None
+            } else {
+                Some(validators)
             }
-            None
         }
 
-        fn start_session(_: u32) {}
+        fn start_session(session: u32) {
+            Pallet::<T>::update_session_period(session);
+            Pallet::<T>::update_consensus_params(session);
+            Pallet::<T>::update_finality_version(session);
+        }
 
         fn end_session(_: u32) {}
     }
 
+    impl<T: Config> ShouldEndSession<T::BlockNumber> for Pallet<T> {
+        fn should_end_session(now: T::BlockNumber) -> bool {
+            let now = now.saturated_into::<u32>();
+            let schedule = Self::session_schedule();
+            now > 0 && schedule.first_block_of_session(schedule.session_of_block(now)) == now
+        }
+    }
+
+    impl<T: Config> EstimateNextSessionRotation<T::BlockNumber> for Pallet<T> {
+        fn average_session_length() -> T::BlockNumber {
+            Self::session_period().into()
+        }
+
+        fn estimate_current_session_progress(now: T::BlockNumber) -> (Option<Permill>, Weight) {
+            let now = now.saturated_into::<u32>();
+            let schedule = Self::session_schedule();
+            let session = schedule.session_of_block(now);
+            let current = now - schedule.first_block_of_session(session) + 1;
+            let period = schedule.for_session(session).session_period;
+            (
+                Some(Permill::from_rational(current, period)),
+                T::DbWeight::get().reads(3),
+            )
+        }
+
+        fn estimate_next_session_rotation(now: T::BlockNumber) -> (Option<T::BlockNumber>, Weight) {
+            let now = now.saturated_into::<u32>();
+            let schedule = Self::session_schedule();
+            let next_session = schedule.session_of_block(now) + 1;
+            (
+                Some(schedule.first_block_of_session(next_session).into()),
+                T::DbWeight::get().reads(3),
+            )
+        }
+    }
+
     impl<T: Config> BoundToRuntimeAppPublic for Pallet<T> {
         type Public = T::AuthorityId;
     }
@@ -203,19 +1197,40 @@ pub mod pallet {
             I: Iterator<Item = (&'a T::AccountId, T::AuthorityId)>,
             T::AccountId: 'a,
         {
-            let authorities = validators.map(|(_, key)| key).collect::<Vec<_>>();
+            let (accounts, authorities): (Vec<_>, Vec<_>) =
+                validators.map(|(who, key)| (who.clone(), key)).unzip();
             Self::initialize_authorities(authorities.as_slice());
+            Self::record_session_validators(0, accounts);
         }
 
-        fn on_new_session<'a, I: 'a>(_changed: bool, validators: I, _queued_validators: I)
+        fn on_new_session<'a, I: 'a>(_changed: bool, validators: I, queued_validators: I)
         where
             I: Iterator<Item = (&'a T::AccountId, T::AuthorityId)>,
             T::AccountId: 'a,
         {
-            let authorities = validators.map(|(_, key)| key).collect::<Vec<_>>();
+            let (accounts, authorities): (Vec<_>, Vec<_>) =
+                validators.map(|(who, key)| (who.clone(), key)).unzip();
             Self::update_authorities(authorities.as_slice());
+            Self::record_session_validators(Session::<T>::current_index(), accounts);
+            DisabledAuthorities::<T>::kill();
+            let next_authorities = queued_validators.map(|(_, key)| key).collect::<Vec<_>>();
+            Self::record_session_bls_keys(
+                Session::<T>::current_index() + 1,
+                next_authorities.as_slice(),
+            );
+            Self::deposit_next_session_authorities(
+                Session::<T>::current_index() + 1,
+                next_authorities,
+            );
         }
 
-        fn on_disabled(_validator_index: usize) {}
+        fn on_disabled(validator_index: usize) {
+            DisabledAuthorities::<T>::mutate(|disabled| {
+                let index = validator_index as u32;
+                if let Err(position) = disabled.binary_search(&index) {
+                    disabled.insert(position, index);
+                }
+            });
+        }
     }
 }
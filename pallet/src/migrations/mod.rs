@@ -1 +1,12 @@
+use crate::Config;
+use frame_support::generate_storage_alias;
+use sp_std::vec::Vec;
+
 pub mod v0_to_v1;
+pub mod v1_to_v2;
+pub mod v2_to_v3;
+pub mod v3_to_v4;
+
+// Storage items removed in STORAGE_VERSION 2, kept here for the migrations.
+generate_storage_alias!(Aleph, SessionForValidatorsChange => Value<u32>);
+generate_storage_alias!(Aleph, Validators<T: Config> => Value<Vec<T::AccountId>>);
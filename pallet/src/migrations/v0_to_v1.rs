@@ -1,6 +1,10 @@
-use crate::Config;
+use crate::{
+    migrations::{SessionForValidatorsChange, Validators},
+    Config,
+};
 use frame_support::log;
 use frame_support::{
+    storage::StorageValue,
     traits::{Get, GetStorageVersion, PalletInfoAccess, StorageVersion},
     weights::Weight,
 };
@@ -10,20 +14,18 @@ pub fn migrate<T: Config, P: GetStorageVersion + PalletInfoAccess>() -> Weight {
     let on_chain_storage_version = <P as GetStorageVersion>::on_chain_storage_version();
     let current_storage_version = <P as GetStorageVersion>::current_storage_version();
 
-    if on_chain_storage_version == StorageVersion::default() && current_storage_version == 1 {
+    if on_chain_storage_version == StorageVersion::default() && current_storage_version >= 1 {
         log::info!(target: "pallet_aleph", "Running migration from STORAGE_VERSION 0 to 1");
 
         let mut writes = 0;
 
-        match crate::SessionForValidatorsChange::<T>::translate(
-            |old: Option<Option<u32>>| -> Option<u32> {
-                log::info!(target: "pallet_aleph", "Current storage value for SessionForValidatorsChange {:?}", old);
-                match old {
-                    Some(Some(x)) => Some(x),
-                    _ => None,
-                }
-            },
-        ) {
+        match SessionForValidatorsChange::translate(|old: Option<Option<u32>>| -> Option<u32> {
+            log::info!(target: "pallet_aleph", "Current storage value for SessionForValidatorsChange {:?}", old);
+            match old {
+                Some(Some(x)) => Some(x),
+                _ => None,
+            }
+        }) {
             Ok(_) => {
                 writes += 1;
                 log::info!(target: "pallet_aleph", "Succesfully migrated storage for SessionForValidatorsChange");
@@ -33,7 +35,7 @@ pub fn migrate<T: Config, P: GetStorageVersion + PalletInfoAccess>() -> Weight {
             }
         };
 
-        match crate::Validators::<T>::translate(
+        match Validators::<T>::translate(
             |old: Option<Option<Vec<T::AccountId>>>| -> Option<Vec<T::AccountId>> {
                 log::info!(target: "pallet_aleph", "Current storage value for Validators {:?}", old);
                 match old {
@@ -0,0 +1,51 @@
+use crate::{
+    migrations::{SessionForValidatorsChange, Validators},
+    Config, PendingValidatorsChanges,
+};
+use frame_support::log;
+use frame_support::{
+    storage::StorageValue,
+    traits::{Get, GetStorageVersion, PalletInfoAccess, StorageVersion},
+    weights::Weight,
+};
+
+pub fn migrate<T: Config, P: GetStorageVersion + PalletInfoAccess>() -> Weight {
+    let on_chain_storage_version = <P as GetStorageVersion>::on_chain_storage_version();
+    let current_storage_version = <P as GetStorageVersion>::current_storage_version();
+
+    if on_chain_storage_version == 1 && current_storage_version >= 2 {
+        log::info!(target: "pallet_aleph", "Running migration from STORAGE_VERSION 1 to 2");
+
+        let mut writes = 2;
+
+        let session = SessionForValidatorsChange::take();
+        let validators = Validators::<T>::take();
+        log::info!(target: "pallet_aleph", "Current storage values for SessionForValidatorsChange {:?} and Validators {:?}", session, validators);
+
+        match (session, validators) {
+            (Some(session), Some(validators)) => {
+                PendingValidatorsChanges::<T>::insert(session, validators);
+                writes += 1;
+                log::info!(target: "pallet_aleph", "Succesfully migrated the pending change of validators to PendingValidatorsChanges");
+            }
+            (None, None) => {}
+            _ => {
+                log::error!(target: "pallet_aleph", "Inconsistent SessionForValidatorsChange and Validators storage, dropping the pending change");
+            }
+        }
+
+        // store new version
+        StorageVersion::new(2).put::<P>();
+        writes += 1;
+
+        T::DbWeight::get().reads(3) + T::DbWeight::get().writes(writes)
+    } else {
+        log::warn!(
+            target: "pallet_aleph",
+            "Not applying storage migration to version 2 because on-chain storage version is {:?} and the version declared in the aleph pallet is {:?}",
+            on_chain_storage_version,
+            current_storage_version
+        );
+        T::DbWeight::get().reads(1)
+    }
+}
@@ -0,0 +1,33 @@
+use crate::{pallet::Authorities, Config, SessionAuthorities};
+use frame_support::log;
+use frame_support::{
+    traits::{Get, GetStorageVersion, PalletInfoAccess, StorageVersion},
+    weights::Weight,
+};
+
+pub fn migrate<T: Config, P: GetStorageVersion + PalletInfoAccess>() -> Weight {
+    let on_chain_storage_version = <P as GetStorageVersion>::on_chain_storage_version();
+    let current_storage_version = <P as GetStorageVersion>::current_storage_version();
+
+    if on_chain_storage_version == 2 && current_storage_version >= 3 {
+        log::info!(target: "pallet_aleph", "Running migration from STORAGE_VERSION 2 to 3");
+
+        // The history starts with the current session, older authorities are not known anymore.
+        let session = pallet_session::Pallet::<T>::current_index();
+        SessionAuthorities::<T>::insert(session, Authorities::<T>::get());
+        log::info!(target: "pallet_aleph", "Recorded the authorities of session {:?}", session);
+
+        // store new version
+        StorageVersion::new(3).put::<P>();
+
+        T::DbWeight::get().reads(4) + T::DbWeight::get().writes(2)
+    } else {
+        log::warn!(
+            target: "pallet_aleph",
+            "Not applying storage migration to version 3 because on-chain storage version is {:?} and the version declared in the aleph pallet is {:?}",
+            on_chain_storage_version,
+            current_storage_version
+        );
+        T::DbWeight::get().reads(1)
+    }
+}
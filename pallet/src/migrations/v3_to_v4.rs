@@ -0,0 +1,32 @@
+use crate::{CandidateCount, Candidates, Config};
+use frame_support::log;
+use frame_support::{
+    traits::{Get, GetStorageVersion, PalletInfoAccess, StorageVersion},
+    weights::Weight,
+};
+
+pub fn migrate<T: Config, P: GetStorageVersion + PalletInfoAccess>() -> Weight {
+    let on_chain_storage_version = <P as GetStorageVersion>::on_chain_storage_version();
+    let current_storage_version = <P as GetStorageVersion>::current_storage_version();
+
+    if on_chain_storage_version == 3 && current_storage_version >= 4 {
+        log::info!(target: "pallet_aleph", "Running migration from STORAGE_VERSION 3 to 4");
+
+        let candidate_count = Candidates::<T>::iter().count() as u32;
+        CandidateCount::<T>::put(candidate_count);
+        log::info!(target: "pallet_aleph", "Counted {:?} candidates", candidate_count);
+
+        // store new version
+        StorageVersion::new(4).put::<P>();
+
+        T::DbWeight::get().reads(candidate_count as Weight + 2) + T::DbWeight::get().writes(2)
+    } else {
+        log::warn!(
+            target: "pallet_aleph",
+            "Not applying storage migration to version 4 because on-chain storage version is {:?} and the version declared in the aleph pallet is {:?}",
+            on_chain_storage_version,
+            current_storage_version
+        );
+        T::DbWeight::get().reads(1)
+    }
+}
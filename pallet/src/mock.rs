@@ -8,7 +8,9 @@ use frame_support::{
     traits::{OnFinalize, OnInitialize},
     weights::RuntimeDbWeight,
 };
-use primitives::AuthorityId;
+pub(crate) use primitives::{
+    AuthorityId, AuthorityPair, EquivocationProof, DEFAULT_SESSION_PERIOD,
+};
 use sp_core::H256;
 use sp_runtime::{
     impl_opaque_keys,
@@ -33,7 +35,7 @@ construct_runtime!(
         System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
         Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
         Session: pallet_session::{Pallet, Call, Storage, Event, Config<T>},
-        Aleph: pallet_aleph::{Pallet, Call, Config<T>, Storage, Event<T>},
+        Aleph: pallet_aleph::{Pallet, Call, Config<T>, Storage, Event<T>, ValidateUnsigned},
         Timestamp: pallet_timestamp::{Pallet, Call, Storage, Inherent},
     }
 );
@@ -134,11 +136,26 @@ impl pallet_timestamp::Config for Test {
     type WeightInfo = ();
 }
 
-parameter_types! {}
+parameter_types! {
+    pub const MinCandidateBond: u128 = 1_000;
+    pub const MinNominatorBond: u128 = 100;
+    pub const MinCommitteeSize: u32 = 2;
+    pub const MaxCommitteeSize: u32 = 2;
+    pub const MaxCandidates: u32 = 3;
+    pub const SessionHistoryDepth: u32 = 3;
+}
 
 impl Config for Test {
     type AuthorityId = AuthorityId;
     type Event = Event;
+    type Currency = Balances;
+    type MinCandidateBond = MinCandidateBond;
+    type MinNominatorBond = MinNominatorBond;
+    type MinCommitteeSize = MinCommitteeSize;
+    type MaxCommitteeSize = MaxCommitteeSize;
+    type MaxCandidates = MaxCandidates;
+    type SessionHistoryDepth = SessionHistoryDepth;
+    type WeightInfo = ();
 }
 
 pub fn to_authorities(authorities: &[u64]) -> Vec<AuthorityId> {
@@ -175,6 +192,15 @@ pub fn new_test_ext(authorities: &[(u64, u64)]) -> sp_io::TestExternalities {
     t.into()
 }
 
+#[cfg(feature = "runtime-benchmarks")]
+pub fn new_bench_ext() -> sp_io::TestExternalities {
+    let mut ext = new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]);
+    ext.register_extension(sp_keystore::KeystoreExt(std::sync::Arc::new(
+        sp_keystore::testing::KeyStore::new(),
+    )));
+    ext
+}
+
 pub(crate) fn run_session(n: u64) {
     while System::block_number() < n {
         Session::on_finalize(System::block_number());
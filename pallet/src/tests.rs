@@ -1,8 +1,24 @@
 #![cfg(test)]
 
-use crate::{migrations, mock::*, pallet};
-use frame_support::assert_ok;
-use frame_support::traits::{GetStorageVersion, StorageVersion};
+use crate::{
+    migrations::{self, SessionForValidatorsChange, Validators},
+    mock::*,
+    pallet, AlephSessionManager, EquivocationProofOf, Error,
+};
+use codec::Encode;
+use frame_support::traits::{GetStorageVersion, OnFinalize, StorageVersion};
+use frame_support::{
+    assert_noop, assert_ok,
+    storage::StorageValue,
+    unsigned::{TransactionSource, ValidateUnsigned},
+};
+use pallet_session::{SessionManager, ShouldEndSession};
+use primitives::{
+    BlsKeyRegistration, ConsensusLog, ConsensusParams, FinalityVersion, SessionId, SigningDomain,
+    SigningPurpose, ALEPH_ENGINE_ID, DEFAULT_MILLISECS_PER_BLOCK,
+};
+use sp_core::{Pair, H256};
+use sp_runtime::{testing::Header, traits::Header as HeaderT, DigestItem};
 
 #[test]
 fn migration_from_v0_to_v1_works() {
@@ -52,13 +68,13 @@ fn migration_from_v0_to_v1_works() {
         );
 
         assert_eq!(
-            Aleph::session_for_validators_change(),
+            SessionForValidatorsChange::get(),
             Some(7u32),
             "Migration should preserve ongoing session change with respect to the session number"
         );
 
         assert_eq!(
-            Aleph::validators(),
+            Validators::<Test>::get(),
             Some(vec![AccountId::default()]),
             "Migration should preserve ongoing session change with respect to the validators set"
         );
@@ -72,6 +88,174 @@ fn migration_from_v0_to_v1_works() {
     })
 }
 
+#[test]
+fn migration_from_v1_to_v2_works() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        StorageVersion::new(1).put::<Aleph>();
+        SessionForValidatorsChange::put(7u32);
+        Validators::<Test>::put(vec![AccountId::default()]);
+
+        let _weight = migrations::v1_to_v2::migrate::<Test, Aleph>();
+
+        assert_eq!(
+            <pallet::Pallet<Test> as GetStorageVersion>::on_chain_storage_version(),
+            StorageVersion::new(2),
+            "Storage version after applying migration should be incremented"
+        );
+
+        assert_eq!(
+            Aleph::pending_validators_change(7),
+            Some(vec![AccountId::default()]),
+            "Migration should move the ongoing session change into the queue"
+        );
+
+        assert_eq!(
+            SessionForValidatorsChange::get(),
+            None,
+            "Migration should remove the old session number"
+        );
+
+        assert_eq!(
+            Validators::<Test>::get(),
+            None,
+            "Migration should remove the old validators set"
+        );
+
+        let noop_weight = migrations::v1_to_v2::migrate::<Test, Aleph>();
+        assert_eq!(
+            noop_weight,
+            TestDbWeight::get().reads(1),
+            "Migration cannot be run twice"
+        );
+    })
+}
+
+#[test]
+fn migration_from_v2_to_v3_works() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        StorageVersion::new(2).put::<Aleph>();
+        pallet::SessionAuthorities::<Test>::remove(0);
+
+        let _weight = migrations::v2_to_v3::migrate::<Test, Aleph>();
+
+        assert_eq!(
+            <pallet::Pallet<Test> as GetStorageVersion>::on_chain_storage_version(),
+            StorageVersion::new(3),
+            "Storage version after applying migration should be incremented"
+        );
+
+        assert_eq!(
+            Aleph::session_authorities(0),
+            Some(to_authorities(&[1, 2])),
+            "Migration should record the authorities of the current session"
+        );
+
+        let noop_weight = migrations::v2_to_v3::migrate::<Test, Aleph>();
+        assert_eq!(
+            noop_weight,
+            TestDbWeight::get().reads(1),
+            "Migration cannot be run twice"
+        );
+    })
+}
+
+#[test]
+fn migration_from_v3_to_v4_works() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        StorageVersion::new(3).put::<Aleph>();
+        pallet::Candidates::<Test>::insert(0, 1_000);
+        pallet::Candidates::<Test>::insert(1, 1_000);
+
+        let _weight = migrations::v3_to_v4::migrate::<Test, Aleph>();
+
+        assert_eq!(
+            <pallet::Pallet<Test> as GetStorageVersion>::on_chain_storage_version(),
+            StorageVersion::new(4),
+            "Storage version after applying migration should be incremented"
+        );
+
+        assert_eq!(
+            Aleph::candidate_count(),
+            2,
+            "Migration should count the existing candidates"
+        );
+
+        let noop_weight = migrations::v3_to_v4::migrate::<Test, Aleph>();
+        assert_eq!(
+            noop_weight,
+            TestDbWeight::get().reads(1),
+            "Migration cannot be run twice"
+        );
+    })
+}
+
+#[test]
+fn test_session_authorities_are_recorded_and_pruned() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        assert_eq!(Aleph::session_authorities(0), Some(to_authorities(&[1, 2])));
+
+        assert_ok!(Aleph::change_validators(Origin::root(), vec![1], 1));
+        initialize_session();
+        run_session(4);
+
+        // The session rotates every block in tests, the change planned in session 1 is applied in
+        // session 2.
+        assert_eq!(Aleph::session_authorities(0), None);
+        assert_eq!(Aleph::session_authorities(1), None);
+        assert_eq!(Aleph::session_authorities(2), Some(to_authorities(&[2])));
+        assert_eq!(Aleph::session_authorities(4), Some(to_authorities(&[2])));
+    });
+}
+
+fn announcements(session: u32, authorities: &[u64]) -> Vec<DigestItem<H256>> {
+    let mut logs = vec![
+        ConsensusLog::NextSessionAuthorities {
+            session,
+            authorities: to_authorities(authorities),
+        },
+        ConsensusLog::NextSessionFinalityVersion {
+            session,
+            version: Aleph::finality_version(session),
+        },
+    ];
+    if let Some(keys) = Aleph::session_bls_keys(session) {
+        logs.push(ConsensusLog::NextSessionBlsKeys { session, keys });
+    }
+    logs.into_iter()
+        .map(|log| DigestItem::Consensus(ALEPH_ENGINE_ID, log.encode()))
+        .collect()
+}
+
+#[test]
+fn test_next_session_authorities_are_announced_in_digest() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        assert_ok!(Aleph::change_validators(Origin::root(), vec![1], 1));
+        initialize_session();
+
+        assert_eq!(System::digest().logs(), &announcements(2, &[2])[..]);
+    });
+}
+
+#[test]
+fn test_next_session_authorities_are_announced_in_last_block_of_session() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        System::initialize(
+            &5,
+            &System::parent_hash(),
+            &Default::default(),
+            Default::default(),
+        );
+        Aleph::on_finalize(5);
+
+        assert_eq!(System::digest().logs(), &announcements(1, &[1, 2])[..]);
+
+        // The block that both starts and ends a session announces the authorities only once.
+        initialize_session();
+        Aleph::on_finalize(1);
+        assert_eq!(System::digest().logs().len(), 2);
+    });
+}
+
 #[test]
 fn test_update_authorities() {
     new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
@@ -94,7 +278,7 @@ fn test_initialize_authorities() {
 #[test]
 fn test_validators_should_be_none() {
     new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
-        assert_eq!(Aleph::validators(), None);
+        assert_eq!(Aleph::scheduled_validators_changes(), vec![]);
     });
 }
 
@@ -107,8 +291,62 @@ fn test_change_validators() {
             0
         ));
 
-        assert_eq!(Aleph::session_for_validators_change(), Some(0));
-        assert_eq!(Aleph::validators(), Some(vec![AccountId::default()]));
+        assert_eq!(
+            Aleph::pending_validators_change(0),
+            Some(vec![AccountId::default()])
+        );
+    });
+}
+
+#[test]
+fn test_schedule_many_validators_changes() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        assert_ok!(Aleph::change_validators(Origin::root(), vec![3], 5));
+        assert_ok!(Aleph::change_validators(Origin::root(), vec![1], 2));
+        assert_noop!(
+            Aleph::change_validators(Origin::root(), vec![2], 2),
+            Error::<Test>::ChangeAlreadyScheduled
+        );
+
+        assert_eq!(
+            Aleph::scheduled_validators_changes(),
+            vec![(2, vec![1]), (5, vec![3])]
+        );
+
+        assert_eq!(AlephSessionManager::<Test>::new_session(1), None);
+        assert_eq!(AlephSessionManager::<Test>::new_session(2), Some(vec![1]));
+        assert_eq!(AlephSessionManager::<Test>::new_session(3), Some(vec![1]));
+        assert_eq!(AlephSessionManager::<Test>::new_session(5), Some(vec![3]));
+        assert_eq!(Aleph::validators_override(), Some(vec![3]));
+        assert_eq!(Aleph::scheduled_validators_changes(), vec![]);
+    });
+}
+
+#[test]
+fn test_overdue_validators_changes_are_merged() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        assert_ok!(Aleph::change_validators(Origin::root(), vec![1], 2));
+        assert_ok!(Aleph::change_validators(Origin::root(), vec![2], 3));
+
+        assert_eq!(AlephSessionManager::<Test>::new_session(4), Some(vec![2]));
+        assert_eq!(Aleph::scheduled_validators_changes(), vec![]);
+    });
+}
+
+#[test]
+fn test_cancel_validators_change() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        assert_ok!(Aleph::change_validators(Origin::root(), vec![1], 2));
+        assert_ok!(Aleph::change_validators(Origin::root(), vec![2], 3));
+
+        assert_noop!(
+            Aleph::cancel_validators_change(Origin::root(), 4),
+            Error::<Test>::NoChangeScheduled
+        );
+        assert_ok!(Aleph::cancel_validators_change(Origin::root(), 2));
+
+        assert_eq!(Aleph::scheduled_validators_changes(), vec![(3, vec![2])]);
+        assert_eq!(AlephSessionManager::<Test>::new_session(2), None);
     });
 }
 
@@ -159,3 +397,834 @@ fn test_next_session_authorities() {
         );
     })
 }
+
+#[test]
+fn test_bond_candidate_requires_session_keys() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        assert_noop!(
+            Aleph::bond_candidate(Origin::signed(7), 1_000),
+            Error::<Test>::NoSessionKeys
+        );
+        assert_noop!(
+            Aleph::bond_candidate(Origin::signed(0), 999),
+            Error::<Test>::BondTooLow
+        );
+        assert_ok!(Aleph::bond_candidate(Origin::signed(0), 1_000));
+        assert_noop!(
+            Aleph::bond_candidate(Origin::signed(0), 1_000),
+            Error::<Test>::AlreadyCandidate
+        );
+
+        assert_eq!(Aleph::candidates(0), Some(1_000));
+        assert_eq!(Balances::reserved_balance(0), 1_000);
+    });
+}
+
+#[test]
+fn test_withdraw_candidacy_unreserves_bond() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        assert_ok!(Aleph::bond_candidate(Origin::signed(0), 1_000));
+        assert_ok!(Aleph::withdraw_candidacy(Origin::signed(0)));
+
+        assert_eq!(Aleph::candidates(0), None);
+        assert_eq!(Balances::reserved_balance(0), 0);
+        assert_noop!(
+            Aleph::withdraw_candidacy(Origin::signed(0)),
+            Error::<Test>::NotCandidate
+        );
+    });
+}
+
+#[test]
+fn test_number_of_candidates_is_capped() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64), (3u64, 3u64), (4u64, 4u64)]).execute_with(|| {
+        for who in 0..3 {
+            assert_ok!(Aleph::bond_candidate(Origin::signed(who), 1_000));
+        }
+        assert_eq!(Aleph::candidate_count(), 3);
+        assert_noop!(
+            Aleph::bond_candidate(Origin::signed(3), 1_000),
+            Error::<Test>::TooManyCandidates
+        );
+
+        assert_ok!(Aleph::withdraw_candidacy(Origin::signed(0)));
+        assert_eq!(Aleph::candidate_count(), 2);
+        assert_ok!(Aleph::bond_candidate(Origin::signed(3), 1_000));
+    });
+}
+
+#[test]
+fn test_nominations_are_counted_into_backing() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        assert_ok!(Aleph::bond_candidate(Origin::signed(0), 1_000));
+        assert_noop!(
+            Aleph::nominate(Origin::signed(1), 0, 99),
+            Error::<Test>::BondTooLow
+        );
+        assert_ok!(Aleph::nominate(Origin::signed(1), 0, 500));
+        assert_ok!(Aleph::nominate(Origin::signed(1), 0, 500));
+
+        assert_eq!(Aleph::nominations(1, 0), Some(1_000));
+        assert_eq!(Aleph::total_backing(&0), Some(2_000));
+
+        assert_ok!(Aleph::withdraw_nomination(Origin::signed(1), 0));
+        assert_eq!(Aleph::total_backing(&0), Some(1_000));
+        assert_eq!(Balances::reserved_balance(1), 0);
+    });
+}
+
+#[test]
+fn test_candidacy_cannot_be_withdrawn_while_nominated() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        assert_ok!(Aleph::bond_candidate(Origin::signed(0), 1_000));
+        assert_ok!(Aleph::nominate(Origin::signed(1), 0, 500));
+        assert_noop!(
+            Aleph::withdraw_candidacy(Origin::signed(0)),
+            Error::<Test>::HasNominations
+        );
+
+        assert_ok!(Aleph::withdraw_nomination(Origin::signed(1), 0));
+        assert_ok!(Aleph::withdraw_candidacy(Origin::signed(0)));
+        assert_eq!(Aleph::nominated_total(0), 0);
+        assert_eq!(Balances::reserved_balance(0), 0);
+        assert_eq!(Balances::reserved_balance(1), 0);
+    });
+}
+
+#[test]
+fn test_election_chooses_most_backed_candidates() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64), (3u64, 3u64)]).execute_with(|| {
+        assert_ok!(Aleph::bond_candidate(Origin::signed(0), 1_000));
+        assert_eq!(AlephSessionManager::<Test>::new_session(1), None);
+
+        assert_ok!(Aleph::bond_candidate(Origin::signed(1), 1_000));
+        assert_ok!(Aleph::bond_candidate(Origin::signed(2), 2_000));
+        assert_eq!(
+            AlephSessionManager::<Test>::new_session(2),
+            Some(vec![2, 0])
+        );
+
+        assert_ok!(Aleph::nominate(Origin::signed(0), 1, 5_000));
+        assert_eq!(
+            AlephSessionManager::<Test>::new_session(3),
+            Some(vec![1, 2])
+        );
+    });
+}
+
+#[test]
+fn test_change_validators_overrides_election() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        assert_ok!(Aleph::bond_candidate(Origin::signed(0), 1_000));
+        assert_ok!(Aleph::bond_candidate(Origin::signed(1), 1_000));
+        assert_ok!(Aleph::change_validators(Origin::root(), vec![1], 2));
+
+        assert_eq!(
+            AlephSessionManager::<Test>::new_session(1),
+            Some(vec![0, 1])
+        );
+        assert_eq!(AlephSessionManager::<Test>::new_session(2), Some(vec![1]));
+        assert_eq!(AlephSessionManager::<Test>::new_session(3), Some(vec![1]));
+
+        assert_ok!(Aleph::clear_validators_override(Origin::root()));
+        assert_eq!(
+            AlephSessionManager::<Test>::new_session(4),
+            Some(vec![0, 1])
+        );
+    });
+}
+
+#[test]
+fn test_clear_validators_override() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        assert_noop!(
+            Aleph::clear_validators_override(Origin::root()),
+            Error::<Test>::NoValidatorsOverride
+        );
+        assert_ok!(Aleph::change_validators(Origin::root(), vec![1], 2));
+        assert_eq!(AlephSessionManager::<Test>::new_session(2), Some(vec![1]));
+
+        assert_noop!(
+            Aleph::clear_validators_override(Origin::signed(1)),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        assert_ok!(Aleph::clear_validators_override(Origin::root()));
+        assert_eq!(Aleph::validators_override(), None);
+        assert_eq!(AlephSessionManager::<Test>::new_session(3), None);
+    });
+}
+
+#[test]
+fn test_schedule_session_period_change_validates_input() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        initialize_session();
+        run_session(3);
+
+        assert_noop!(
+            Aleph::schedule_session_period_change(
+                Origin::root(),
+                10,
+                DEFAULT_MILLISECS_PER_BLOCK,
+                3
+            ),
+            Error::<Test>::InvalidSessionPeriodChange
+        );
+        assert_noop!(
+            Aleph::schedule_session_period_change(
+                Origin::root(),
+                0,
+                DEFAULT_MILLISECS_PER_BLOCK,
+                5
+            ),
+            Error::<Test>::InvalidSessionPeriodChange
+        );
+        assert_ok!(Aleph::schedule_session_period_change(
+            Origin::root(),
+            10,
+            DEFAULT_MILLISECS_PER_BLOCK,
+            5
+        ));
+        assert_noop!(
+            Aleph::schedule_session_period_change(
+                Origin::root(),
+                20,
+                DEFAULT_MILLISECS_PER_BLOCK,
+                4
+            ),
+            Error::<Test>::InvalidSessionPeriodChange
+        );
+    });
+}
+
+#[test]
+fn test_sessions_follow_scheduled_period() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        assert_eq!(Aleph::session_period(), DEFAULT_SESSION_PERIOD);
+        assert_ok!(Aleph::schedule_session_period_change(
+            Origin::root(),
+            10,
+            DEFAULT_MILLISECS_PER_BLOCK,
+            2
+        ));
+
+        let first_block = 2 * DEFAULT_SESSION_PERIOD as u64;
+        assert!(Aleph::should_end_session(DEFAULT_SESSION_PERIOD as u64));
+        assert!(!Aleph::should_end_session(first_block + 5));
+        assert!(Aleph::should_end_session(first_block));
+        assert!(Aleph::should_end_session(first_block + 10));
+        assert!(Aleph::should_end_session(first_block + 20));
+
+        AlephSessionManager::<Test>::start_session(2);
+        assert_eq!(Aleph::session_period(), 10);
+        assert_eq!(Aleph::millisecs_per_block(), DEFAULT_MILLISECS_PER_BLOCK);
+    });
+}
+
+#[test]
+fn test_schedule_session_period_change_rejects_block_time_change() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        initialize_session();
+
+        assert_noop!(
+            Aleph::schedule_session_period_change(
+                Origin::root(),
+                10,
+                DEFAULT_MILLISECS_PER_BLOCK / 2,
+                2
+            ),
+            Error::<Test>::BlockTimeChangeUnsupported
+        );
+    });
+}
+
+#[test]
+fn test_report_offence_disables_validator() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        initialize_session();
+
+        assert_noop!(
+            Aleph::report_offence(Origin::root(), 7),
+            Error::<Test>::NotValidator
+        );
+        assert_ok!(Aleph::report_offence(Origin::root(), 1));
+
+        assert_eq!(Aleph::disabled_authorities(), vec![1]);
+        assert_eq!(Aleph::offenders(), vec![1]);
+
+        run_session(2);
+
+        assert_eq!(Aleph::disabled_authorities(), Vec::<u32>::new());
+    });
+}
+
+#[test]
+fn test_offenders_are_excluded_from_next_committee() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        assert_ok!(Aleph::report_offence(Origin::root(), 1));
+
+        assert_eq!(AlephSessionManager::<Test>::new_session(1), Some(vec![0]));
+        assert_eq!(Aleph::offenders(), Vec::<AccountId>::new());
+        assert_eq!(AlephSessionManager::<Test>::new_session(2), None);
+    });
+}
+
+#[test]
+fn test_offenders_are_kept_while_validators_are_overridden() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        assert_ok!(Aleph::change_validators(Origin::root(), vec![0, 1], 1));
+        assert_ok!(Aleph::report_offence(Origin::root(), 1));
+
+        assert_eq!(
+            AlephSessionManager::<Test>::new_session(1),
+            Some(vec![0, 1])
+        );
+        assert_eq!(Aleph::offenders(), vec![1]);
+
+        assert_ok!(Aleph::clear_validators_override(Origin::root()));
+        assert_eq!(AlephSessionManager::<Test>::new_session(2), Some(vec![0]));
+        assert_eq!(Aleph::offenders(), Vec::<AccountId>::new());
+    });
+}
+
+#[test]
+fn test_offenders_are_not_elected() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64), (3u64, 3u64)]).execute_with(|| {
+        assert_ok!(Aleph::bond_candidate(Origin::signed(0), 1_000));
+        assert_ok!(Aleph::bond_candidate(Origin::signed(1), 2_000));
+        assert_ok!(Aleph::bond_candidate(Origin::signed(2), 3_000));
+        assert_ok!(Aleph::report_offence(Origin::root(), 2));
+
+        assert_eq!(
+            AlephSessionManager::<Test>::new_session(1),
+            Some(vec![1, 0])
+        );
+        assert_eq!(
+            AlephSessionManager::<Test>::new_session(2),
+            Some(vec![2, 1])
+        );
+    });
+}
+
+fn equivocation_proof(
+    pair: &AuthorityPair,
+    session: u32,
+    number: u64,
+) -> EquivocationProofOf<Test> {
+    equivocation_proof_in_domain(pair, session, number, None)
+}
+
+fn equivocation_proof_in_domain(
+    pair: &AuthorityPair,
+    session: u32,
+    number: u64,
+    domain: Option<&SigningDomain>,
+) -> EquivocationProofOf<Test> {
+    let sign = |header: &Header| {
+        let hash = header.hash().encode();
+        match domain {
+            Some(domain) => pair.sign(&domain.tag(&hash)),
+            None => pair.sign(&hash),
+        }
+    };
+    let header = |parent: u8| {
+        Header::new(
+            number,
+            Default::default(),
+            Default::default(),
+            H256::repeat_byte(parent),
+            Default::default(),
+        )
+    };
+    let (first_header, second_header) = (header(1), header(2));
+    EquivocationProof {
+        session,
+        offender: pair.public(),
+        first_signature: sign(&first_header),
+        first_header,
+        second_signature: sign(&second_header),
+        second_header,
+    }
+}
+
+fn authority_pairs() -> Vec<AuthorityPair> {
+    let pairs = vec![
+        AuthorityPair::from_seed(&[0; 32]),
+        AuthorityPair::from_seed(&[1; 32]),
+    ];
+    let authorities = pairs.iter().map(|pair| pair.public()).collect::<Vec<_>>();
+    pallet::Authorities::<Test>::put(authorities.clone());
+    pallet::SessionAuthorities::<Test>::insert(Session::current_index(), authorities);
+    pairs
+}
+
+fn finality_domain(session: u32) -> SigningDomain {
+    SigningDomain::new(
+        SigningPurpose::Finality,
+        System::block_hash(0).encode(),
+        SessionId(session),
+    )
+}
+
+#[test]
+fn test_report_equivocation_disables_offender() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        let pairs = authority_pairs();
+        let proof = equivocation_proof(&pairs[1], 0, 5);
+
+        assert_ok!(Aleph::report_equivocation_unsigned(
+            Origin::none(),
+            proof.clone()
+        ));
+
+        assert_eq!(Aleph::disabled_authorities(), vec![1]);
+        assert_eq!(Aleph::offenders(), vec![1]);
+        assert_eq!(
+            Aleph::reported_equivocations(0, pairs[1].public()),
+            Some(())
+        );
+        assert_noop!(
+            Aleph::report_equivocation_unsigned(Origin::none(), proof),
+            Error::<Test>::DuplicateEquivocationReport
+        );
+    });
+}
+
+#[test]
+fn test_invalid_equivocation_proofs_are_rejected() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        let pairs = authority_pairs();
+
+        let mut same_block = equivocation_proof(&pairs[0], 0, 5);
+        same_block.second_header = same_block.first_header.clone();
+        same_block.second_signature = same_block.first_signature.clone();
+        let mut different_heights = equivocation_proof(&pairs[0], 0, 5);
+        different_heights.second_header.set_number(6);
+        let mut forged = equivocation_proof(&pairs[0], 0, 5);
+        forged.offender = pairs[1].public();
+
+        for proof in vec![
+            same_block,
+            different_heights,
+            forged,
+            equivocation_proof(&pairs[0], 1, 5),
+        ] {
+            assert_noop!(
+                Aleph::report_equivocation_unsigned(Origin::none(), proof),
+                Error::<Test>::InvalidEquivocationProof
+            );
+        }
+        assert_noop!(
+            Aleph::report_equivocation_unsigned(
+                Origin::none(),
+                equivocation_proof(&AuthorityPair::from_seed(&[2; 32]), 0, 5)
+            ),
+            Error::<Test>::NotValidator
+        );
+    });
+}
+
+#[test]
+fn test_equivocations_are_proven_in_signing_domains() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        pallet::CurrentFinalityVersion::<Test>::put(FinalityVersion::DOMAIN_SEPARATED);
+        let pairs = authority_pairs();
+
+        let other_session_domain = finality_domain(1);
+        for proof in vec![
+            equivocation_proof(&pairs[1], 0, 5),
+            equivocation_proof_in_domain(&pairs[1], 0, 5, Some(&other_session_domain)),
+        ] {
+            assert_noop!(
+                Aleph::report_equivocation_unsigned(Origin::none(), proof),
+                Error::<Test>::InvalidEquivocationProof
+            );
+        }
+
+        let domain = finality_domain(0);
+        assert_ok!(Aleph::report_equivocation_unsigned(
+            Origin::none(),
+            equivocation_proof_in_domain(&pairs[1], 0, 5, Some(&domain))
+        ));
+        assert_eq!(Aleph::disabled_authorities(), vec![1]);
+    });
+}
+
+#[test]
+fn test_equivocations_of_past_sessions_are_reported() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        initialize_session();
+        run_session(3);
+        let pairs = authority_pairs();
+        let authorities = pairs.iter().map(|pair| pair.public()).collect::<Vec<_>>();
+        pallet::SessionAuthorities::<Test>::insert(1, authorities);
+        let number = DEFAULT_SESSION_PERIOD as u64 + 5;
+
+        assert_ok!(Aleph::report_equivocation_unsigned(
+            Origin::none(),
+            equivocation_proof(&pairs[1], 1, number)
+        ));
+        assert_eq!(
+            Aleph::reported_equivocations(1, pairs[1].public()),
+            Some(())
+        );
+        assert_eq!(Aleph::offenders(), vec![1]);
+
+        // Sessions out of the history cannot be proven anymore.
+        run_session(5);
+        assert_noop!(
+            Aleph::report_equivocation_unsigned(
+                Origin::none(),
+                equivocation_proof(&pairs[0], 1, number)
+            ),
+            Error::<Test>::InvalidEquivocationProof
+        );
+    });
+}
+
+#[test]
+fn test_equivocation_reports_are_only_accepted_locally() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        let pairs = authority_pairs();
+        let call = pallet::Call::report_equivocation_unsigned(equivocation_proof(&pairs[0], 0, 5));
+
+        assert!(Aleph::validate_unsigned(TransactionSource::Local, &call).is_ok());
+        assert!(Aleph::validate_unsigned(TransactionSource::External, &call).is_err());
+    });
+}
+
+fn bls_registration(seed: u8) -> BlsKeyRegistration {
+    aleph_justification::bls::generate_registration(&[seed; 32]).expect("the seed is long enough")
+}
+
+#[test]
+fn test_bls_key_registration_requires_authority_signature() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        let pairs = authority_pairs();
+        let authority = pairs[0].public();
+        let registration = bls_registration(1);
+
+        assert_noop!(
+            Aleph::register_bls_key(
+                Origin::signed(1),
+                authority.clone(),
+                registration.clone(),
+                1,
+                pairs[1].sign(&registration.payload(&authority, 1))
+            ),
+            Error::<Test>::InvalidBlsKeyRegistration
+        );
+        // Signed for another authority.
+        assert_noop!(
+            Aleph::register_bls_key(
+                Origin::signed(1),
+                authority.clone(),
+                registration.clone(),
+                1,
+                pairs[0].sign(&registration.payload(&pairs[1].public(), 1))
+            ),
+            Error::<Test>::InvalidBlsKeyRegistration
+        );
+        assert_ok!(Aleph::register_bls_key(
+            Origin::signed(1),
+            authority.clone(),
+            registration.clone(),
+            1,
+            pairs[0].sign(&registration.payload(&authority, 1))
+        ));
+        assert_eq!(Aleph::bls_keys(&authority), Some(registration));
+        assert_eq!(Aleph::bls_registration_nonce(&authority), 1);
+    });
+}
+
+#[test]
+fn test_bls_key_registration_cannot_be_replayed() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        let pairs = authority_pairs();
+        let authority = pairs[0].public();
+        let old_registration = bls_registration(1);
+        let old_signature = pairs[0].sign(&old_registration.payload(&authority, 1));
+        let new_registration = bls_registration(2);
+
+        assert_ok!(Aleph::register_bls_key(
+            Origin::signed(1),
+            authority.clone(),
+            old_registration.clone(),
+            1,
+            old_signature.clone()
+        ));
+        assert_ok!(Aleph::register_bls_key(
+            Origin::signed(1),
+            authority.clone(),
+            new_registration.clone(),
+            2,
+            pairs[0].sign(&new_registration.payload(&authority, 2))
+        ));
+
+        // Rolling the key back with the old registration.
+        assert_noop!(
+            Aleph::register_bls_key(
+                Origin::signed(2),
+                authority.clone(),
+                old_registration.clone(),
+                1,
+                old_signature.clone()
+            ),
+            Error::<Test>::StaleBlsKeyRegistration
+        );
+        // The nonce is part of what the authority signed.
+        assert_noop!(
+            Aleph::register_bls_key(
+                Origin::signed(2),
+                authority.clone(),
+                old_registration,
+                3,
+                old_signature
+            ),
+            Error::<Test>::InvalidBlsKeyRegistration
+        );
+        assert_eq!(Aleph::bls_keys(&authority), Some(new_registration));
+    });
+}
+
+#[test]
+fn test_bls_key_registration_requires_proof_of_possession() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        let pairs = authority_pairs();
+        let authority = pairs[0].public();
+        // The key of one registration with the proof of another, as in a rogue key attack.
+        let registration = BlsKeyRegistration {
+            key: bls_registration(1).key,
+            proof_of_possession: bls_registration(2).proof_of_possession,
+        };
+
+        assert_noop!(
+            Aleph::register_bls_key(
+                Origin::signed(1),
+                authority.clone(),
+                registration.clone(),
+                1,
+                pairs[0].sign(&registration.payload(&authority, 1))
+            ),
+            Error::<Test>::InvalidBlsKeyPossession
+        );
+        assert_eq!(Aleph::bls_keys(&authority), None);
+    });
+}
+
+#[test]
+fn test_bls_keys_are_recorded_for_sessions_after_switch() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        assert_noop!(
+            Aleph::schedule_bls_switch(Origin::root(), 1),
+            Error::<Test>::InvalidBlsSwitch
+        );
+        assert_ok!(Aleph::schedule_bls_switch(Origin::root(), 2));
+        for (seed, authority) in to_authorities(&[1, 2]).into_iter().enumerate() {
+            pallet::BlsKeys::<Test>::insert(authority, bls_registration(seed as u8));
+        }
+
+        initialize_session();
+        run_session(3);
+
+        assert_eq!(Aleph::session_bls_keys(1), None);
+        for session in 2..=4 {
+            assert_eq!(
+                Aleph::session_bls_keys(session),
+                Some(vec![bls_registration(0), bls_registration(1)])
+            );
+        }
+        assert_eq!(System::digest().logs().len(), 3);
+        assert_eq!(System::digest().logs(), &announcements(4, &[1, 2])[..]);
+    });
+}
+
+#[test]
+fn test_sessions_with_missing_bls_keys_keep_ed25519() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        assert_ok!(Aleph::schedule_bls_switch(Origin::root(), 2));
+        pallet::BlsKeys::<Test>::insert(to_authorities(&[1])[0].clone(), bls_registration(0));
+
+        initialize_session();
+        run_session(3);
+
+        assert_eq!(Aleph::session_bls_keys(2), None);
+        assert_eq!(Aleph::session_bls_keys(3), None);
+    });
+}
+
+fn consensus_params(unit_creation_delay: u64) -> ConsensusParams {
+    ConsensusParams {
+        unit_creation_delay,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_schedule_consensus_params_validates_input() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        initialize_session();
+        run_session(3);
+
+        assert_noop!(
+            Aleph::schedule_consensus_params(Origin::signed(1), consensus_params(500), 5),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        assert_noop!(
+            Aleph::schedule_consensus_params(Origin::root(), consensus_params(500), 3),
+            Error::<Test>::InvalidConsensusParams
+        );
+        // The parameters of the next session are settled.
+        assert_noop!(
+            Aleph::schedule_consensus_params(Origin::root(), consensus_params(500), 4),
+            Error::<Test>::InvalidConsensusParams
+        );
+        assert_noop!(
+            Aleph::schedule_consensus_params(Origin::root(), consensus_params(0), 5),
+            Error::<Test>::InvalidConsensusParams
+        );
+        assert_ok!(Aleph::schedule_consensus_params(
+            Origin::root(),
+            consensus_params(500),
+            5
+        ));
+    });
+}
+
+#[test]
+fn test_consensus_params_change_at_scheduled_sessions() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        assert_eq!(Aleph::consensus_params(0), ConsensusParams::default());
+        assert_ok!(Aleph::schedule_consensus_params(
+            Origin::root(),
+            consensus_params(100),
+            4
+        ));
+        assert_ok!(Aleph::schedule_consensus_params(
+            Origin::root(),
+            consensus_params(200),
+            2
+        ));
+
+        assert_eq!(Aleph::consensus_params(1), ConsensusParams::default());
+        assert_eq!(Aleph::consensus_params(2), consensus_params(200));
+        assert_eq!(Aleph::consensus_params(3), consensus_params(200));
+        assert_eq!(Aleph::consensus_params(4), consensus_params(100));
+
+        AlephSessionManager::<Test>::start_session(2);
+        assert_eq!(Aleph::current_consensus_params(), consensus_params(200));
+        assert_eq!(
+            Aleph::scheduled_consensus_params(),
+            vec![(4, consensus_params(100))]
+        );
+
+        AlephSessionManager::<Test>::start_session(4);
+        assert_eq!(Aleph::current_consensus_params(), consensus_params(100));
+        assert!(Aleph::scheduled_consensus_params().is_empty());
+        assert_eq!(Aleph::consensus_params(5), consensus_params(100));
+    });
+}
+
+#[test]
+fn test_schedule_finality_version_validates_input() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        initialize_session();
+        run_session(3);
+
+        assert_noop!(
+            Aleph::schedule_finality_version(
+                Origin::signed(1),
+                FinalityVersion::DOMAIN_SEPARATED,
+                5
+            ),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        assert_noop!(
+            Aleph::schedule_finality_version(Origin::root(), FinalityVersion::DOMAIN_SEPARATED, 3),
+            Error::<Test>::InvalidFinalityVersion
+        );
+        // The version of the next session is already announced.
+        assert_noop!(
+            Aleph::schedule_finality_version(Origin::root(), FinalityVersion::DOMAIN_SEPARATED, 4),
+            Error::<Test>::InvalidFinalityVersion
+        );
+        assert_noop!(
+            Aleph::schedule_finality_version(Origin::root(), FinalityVersion::LEGACY, 5),
+            Error::<Test>::InvalidFinalityVersion
+        );
+        assert_noop!(
+            Aleph::schedule_finality_version(
+                Origin::root(),
+                FinalityVersion(FinalityVersion::LATEST.0 + 1),
+                5
+            ),
+            Error::<Test>::InvalidFinalityVersion
+        );
+        assert_ok!(Aleph::schedule_finality_version(
+            Origin::root(),
+            FinalityVersion::DOMAIN_SEPARATED,
+            5
+        ));
+    });
+}
+
+#[test]
+fn test_announced_finality_version_cannot_be_replaced() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        initialize_session();
+        run_session(3);
+
+        assert_ok!(Aleph::schedule_finality_version(
+            Origin::root(),
+            FinalityVersion::DOMAIN_SEPARATED,
+            5
+        ));
+        // Not announced yet, so it can still be replaced.
+        assert_ok!(Aleph::schedule_finality_version(
+            Origin::root(),
+            FinalityVersion::DOMAIN_SEPARATED,
+            6
+        ));
+
+        run_session(5);
+        assert_noop!(
+            Aleph::schedule_finality_version(Origin::root(), FinalityVersion::NEW_NETWORK, 8),
+            Error::<Test>::FinalityVersionAnnounced
+        );
+        assert_eq!(
+            Aleph::next_finality_version(),
+            Some((6, FinalityVersion::DOMAIN_SEPARATED))
+        );
+    });
+}
+
+#[test]
+fn test_finality_version_changes_at_scheduled_session() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        assert_eq!(Aleph::finality_version(0), FinalityVersion::LEGACY);
+        assert_ok!(Aleph::schedule_finality_version(
+            Origin::root(),
+            FinalityVersion::DOMAIN_SEPARATED,
+            2
+        ));
+
+        assert_eq!(Aleph::finality_version(1), FinalityVersion::LEGACY);
+        assert_eq!(
+            Aleph::finality_version(2),
+            FinalityVersion::DOMAIN_SEPARATED
+        );
+
+        AlephSessionManager::<Test>::start_session(1);
+        assert_eq!(Aleph::current_finality_version(), FinalityVersion::LEGACY);
+
+        AlephSessionManager::<Test>::start_session(2);
+        assert_eq!(
+            Aleph::current_finality_version(),
+            FinalityVersion::DOMAIN_SEPARATED
+        );
+        assert_eq!(Aleph::next_finality_version(), None);
+        assert_noop!(
+            Aleph::schedule_finality_version(Origin::root(), FinalityVersion::DOMAIN_SEPARATED, 4),
+            Error::<Test>::InvalidFinalityVersion
+        );
+    });
+}
@@ -1,11 +1,14 @@
 #![allow(clippy::too_many_arguments, clippy::unnecessary_mut_passed)]
 #![cfg_attr(not(feature = "std"), no_std)]
-use codec::{Decode, Encode};
+use codec::{Codec, Decode, Encode};
+use sp_application_crypto::RuntimeAppPublic;
 use sp_core::crypto::KeyTypeId;
-use sp_runtime::ConsensusEngineId;
+use sp_runtime::{traits::Header as HeaderT, ConsensusEngineId};
 use sp_std::vec::Vec;
 
 pub const KEY_TYPE: KeyTypeId = KeyTypeId(*b"alp0");
+/// The keystore key type of the BLS keys of authorities.
+pub const BLS_KEY_TYPE: KeyTypeId = KeyTypeId(*b"alpb");
 
 // Same as GRANDPA_ENGINE_ID because as of right now substrate sends only
 // grandpa justifications over the network.
@@ -24,24 +27,360 @@ sp_application_crypto::with_pair! {
 pub type AuthoritySignature = app::Signature;
 pub type AuthorityId = app::Public;
 
+/// The size of a compressed BLS12-381 public key.
+pub const BLS_PUBLIC_SIZE: usize = 48;
+/// The size of a compressed BLS12-381 signature.
+pub const BLS_SIGNATURE_SIZE: usize = 96;
+/// Prefixes the message an authority signs with its `AuthorityId` to register a BLS key.
+pub const BLS_REGISTRATION_CONTEXT: &[u8] = b"aleph-bls-registration";
+
+/// A BLS public key of an authority, used for verifying aggregated justifications.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, Hash, sp_std::fmt::Debug)]
+pub struct BlsPublic(pub [u8; BLS_PUBLIC_SIZE]);
+
+/// A BLS signature, possibly aggregated from the signatures of many authorities.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, Hash, sp_std::fmt::Debug)]
+pub struct BlsSignature(pub [u8; BLS_SIGNATURE_SIZE]);
+
+/// A BLS key of an authority together with a signature of the key made with it, which proves the
+/// authority knows the secret key and prevents rogue key attacks on aggregated signatures.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, sp_std::fmt::Debug)]
+pub struct BlsKeyRegistration {
+    pub key: BlsPublic,
+    pub proof_of_possession: BlsSignature,
+}
+
+impl BlsKeyRegistration {
+    /// The message the authority signs with its `AuthorityId` to register the key. It names the
+    /// authority and carries a nonce, which has to grow with every registration of the authority,
+    /// so a signed registration cannot be replayed by anyone, e.g. to roll the key back.
+    pub fn payload<A: Encode>(&self, authority: &A, nonce: u32) -> Vec<u8> {
+        (BLS_REGISTRATION_CONTEXT, authority, nonce, self).encode()
+    }
+}
+
+/// The index of a session.
+#[derive(
+    Copy, Clone, Default, Eq, PartialEq, Hash, Ord, PartialOrd, Encode, Decode, sp_std::fmt::Debug,
+)]
+pub struct SessionId(pub u32);
+
+/// What a signed message is used for.
+#[derive(Copy, Clone, Encode, Decode, PartialEq, Eq, sp_std::fmt::Debug)]
+pub enum SigningPurpose {
+    /// Units of AlephBFT.
+    Unit,
+    /// Hashes of finalized blocks.
+    Finality,
+    /// Authentications of the network addresses of authorities.
+    Authentication,
+}
+
+/// The prefix of all the messages signed in a domain.
+const DOMAIN_PREFIX: &[u8; 8] = b"aleph-sd";
+
+/// The context a message is signed in. A message tagged with its domain before signing cannot be
+/// passed off as a message of any other purpose, chain or session.
+#[derive(Clone, PartialEq, Eq, sp_std::fmt::Debug)]
+pub struct SigningDomain {
+    purpose: SigningPurpose,
+    genesis_hash: Vec<u8>,
+    session_id: SessionId,
+}
+
+impl SigningDomain {
+    /// The domain of messages of the given purpose, signed in the given session of the chain with
+    /// the given encoded genesis hash.
+    pub fn new(purpose: SigningPurpose, genesis_hash: Vec<u8>, session_id: SessionId) -> Self {
+        SigningDomain {
+            purpose,
+            genesis_hash,
+            session_id,
+        }
+    }
+
+    pub fn purpose(&self) -> SigningPurpose {
+        self.purpose
+    }
+
+    pub fn session_id(&self) -> SessionId {
+        self.session_id
+    }
+
+    /// The message prefixed with the domain, which is what actually gets signed.
+    pub fn tag(&self, msg: &[u8]) -> Vec<u8> {
+        let mut tagged = (
+            DOMAIN_PREFIX,
+            self.purpose,
+            &self.genesis_hash,
+            self.session_id,
+        )
+            .encode();
+        tagged.extend_from_slice(msg);
+        tagged
+    }
+}
+
 pub const DEFAULT_SESSION_PERIOD: u32 = 900;
 pub const DEFAULT_MILLISECS_PER_BLOCK: u64 = 1000;
 
 pub const TOKEN_DECIMALS: u32 = 12;
 pub const ADDRESSES_ENCODING: u32 = 42;
 pub const DEFAULT_UNIT_CREATION_DELAY: u64 = 300;
+pub const DEFAULT_MAX_ROUND: u16 = 7000;
+pub const DEFAULT_UNIT_CREATION_SLOWDOWN: u32 = 5000;
+
+/// Parameters of AlephBFT that all the members of a committee have to agree on, as they decide
+/// the pace of the whole session.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, sp_std::fmt::Debug)]
+pub struct ConsensusParams {
+    /// The delay between creating subsequent units, in milliseconds.
+    pub unit_creation_delay: u64,
+    /// The last round of a session, after which no more units are created.
+    pub max_round: u16,
+    /// How much the unit creation delay grows every round once the slowdown starts, in parts per
+    /// million of its current value.
+    pub unit_creation_slowdown: u32,
+}
+
+impl ConsensusParams {
+    /// Whether AlephBFT can run with the parameters.
+    pub fn is_valid(&self) -> bool {
+        self.unit_creation_delay > 0 && self.max_round > 0
+    }
+}
+
+impl Default for ConsensusParams {
+    fn default() -> Self {
+        ConsensusParams {
+            unit_creation_delay: DEFAULT_UNIT_CREATION_DELAY,
+            max_round: DEFAULT_MAX_ROUND,
+            unit_creation_slowdown: DEFAULT_UNIT_CREATION_SLOWDOWN,
+        }
+    }
+}
+
+/// The version of the protocol run by the finality gadget in a session. Nodes running different
+/// versions cannot finalize blocks together, so all the validators have to switch at the same
+/// session.
+#[derive(
+    Copy, Clone, Default, Eq, PartialEq, Hash, Ord, PartialOrd, Encode, Decode, sp_std::fmt::Debug,
+)]
+pub struct FinalityVersion(pub u32);
+
+impl FinalityVersion {
+    /// Signing bare messages.
+    pub const LEGACY: FinalityVersion = FinalityVersion(0);
+    /// Signing messages tagged with their signing domains.
+    pub const DOMAIN_SEPARATED: FinalityVersion = FinalityVersion(1);
+    /// Talking only over the network discovering the addresses of validators, without the
+    /// legacy network kept for the transition.
+    pub const NEW_NETWORK: FinalityVersion = FinalityVersion(2);
+    /// The newest version this code knows how to run.
+    pub const LATEST: FinalityVersion = FinalityVersion::NEW_NETWORK;
+
+    /// Whether the messages are tagged with their signing domains.
+    pub fn signs_in_domains(&self) -> bool {
+        *self >= FinalityVersion::DOMAIN_SEPARATED
+    }
+
+    /// Whether the messages are also exchanged over the legacy network, so that the nodes that
+    /// do not talk over the new one yet can take part.
+    pub fn uses_legacy_network(&self) -> bool {
+        *self < FinalityVersion::NEW_NETWORK
+    }
+
+    /// Whether this code knows how to run the version.
+    pub fn is_known(&self) -> bool {
+        *self <= FinalityVersion::LATEST
+    }
+
+    /// All the versions this code knows how to run, from the oldest.
+    pub fn known() -> impl Iterator<Item = FinalityVersion> {
+        (FinalityVersion::LEGACY.0..=FinalityVersion::LATEST.0).map(FinalityVersion)
+    }
+}
 
 #[derive(Encode, Decode, PartialEq, Eq, sp_std::fmt::Debug)]
 pub enum ApiError {
     DecodeKey,
 }
 
+/// Session period and block time in effect starting from `first_session`, which begins at
+/// `first_block`.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, sp_std::fmt::Debug)]
+pub struct SessionPeriodChange {
+    pub first_session: u32,
+    pub first_block: u32,
+    pub session_period: u32,
+    pub millisecs_per_block: u64,
+}
+
+/// All the changes of session period and block time, ordered by `first_session`. The first
+/// change always starts at session 0 and block 0.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, sp_std::fmt::Debug)]
+pub struct SessionSchedule {
+    changes: Vec<SessionPeriodChange>,
+}
+
+impl SessionSchedule {
+    /// A schedule with constant session period and block time.
+    pub fn new(session_period: u32, millisecs_per_block: u64) -> Self {
+        SessionSchedule {
+            changes: sp_std::vec![SessionPeriodChange {
+                first_session: 0,
+                first_block: 0,
+                session_period,
+                millisecs_per_block,
+            }],
+        }
+    }
+
+    pub fn changes(&self) -> &[SessionPeriodChange] {
+        &self.changes
+    }
+
+    /// The most recent change, possibly not yet in effect.
+    pub fn latest(&self) -> &SessionPeriodChange {
+        self.changes.last().expect("schedule is never empty")
+    }
+
+    /// Appends a change starting at `first_session`. Returns `None` if `first_session` is not
+    /// later than the latest change or any of the values is zero.
+    pub fn with_change(
+        &self,
+        first_session: u32,
+        session_period: u32,
+        millisecs_per_block: u64,
+    ) -> Option<Self> {
+        let latest = self.latest();
+        if first_session <= latest.first_session || session_period == 0 || millisecs_per_block == 0
+        {
+            return None;
+        }
+        let first_block = self.first_block_of_session(first_session);
+        let mut changes = self.changes.clone();
+        changes.push(SessionPeriodChange {
+            first_session,
+            first_block,
+            session_period,
+            millisecs_per_block,
+        });
+        Some(SessionSchedule { changes })
+    }
+
+    /// The change in effect during the given session.
+    pub fn for_session(&self, session: u32) -> &SessionPeriodChange {
+        self.changes
+            .iter()
+            .rev()
+            .find(|change| change.first_session <= session)
+            .expect("the first change starts at session 0")
+    }
+
+    pub fn first_block_of_session(&self, session: u32) -> u32 {
+        let change = self.for_session(session);
+        change.first_block + (session - change.first_session) * change.session_period
+    }
+
+    pub fn last_block_of_session(&self, session: u32) -> u32 {
+        self.first_block_of_session(session + 1) - 1
+    }
+
+    pub fn session_of_block(&self, block: u32) -> u32 {
+        let change = self
+            .changes
+            .iter()
+            .rev()
+            .find(|change| change.first_block <= block)
+            .expect("the first change starts at block 0");
+        change.first_session + (block - change.first_block) / change.session_period
+    }
+}
+
+/// Proof that an authority signed two different blocks of the same height in one session.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, sp_std::fmt::Debug)]
+pub struct EquivocationProof<H, Id, S> {
+    pub session: u32,
+    pub offender: Id,
+    pub first_header: H,
+    pub first_signature: S,
+    pub second_header: H,
+    pub second_signature: S,
+}
+
+impl<H: HeaderT, Id: RuntimeAppPublic<Signature = S>, S> EquivocationProof<H, Id, S> {
+    /// Checks that the headers are different, have the same number and both signatures are made
+    /// by the offender. In sessions signing in domains the signatures are of the block hashes
+    /// tagged with the finality domain of the session, which has to be passed, otherwise of the
+    /// bare block hashes. Whether the offender was an authority in the session has to be checked
+    /// separately.
+    pub fn is_valid(&self, domain: Option<&SigningDomain>) -> bool {
+        if let Some(domain) = domain {
+            if domain.purpose() != SigningPurpose::Finality || domain.session_id().0 != self.session
+            {
+                return false;
+            }
+        }
+        let signed_message = |header: &H| {
+            let hash = header.hash().encode();
+            match domain {
+                Some(domain) => domain.tag(&hash),
+                None => hash,
+            }
+        };
+        self.first_header.number() == self.second_header.number()
+            && self.first_header.hash() != self.second_header.hash()
+            && self
+                .offender
+                .verify(&signed_message(&self.first_header), &self.first_signature)
+            && self
+                .offender
+                .verify(&signed_message(&self.second_header), &self.second_signature)
+    }
+}
+
+/// Logs deposited by pallet_aleph in block digests under `ALEPH_ENGINE_ID`.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, sp_std::fmt::Debug)]
+pub enum ConsensusLog<Id: Codec> {
+    /// The authorities of `session`, announced in the first and in the last block of the preceding
+    /// session. The authorities of session 0 are the genesis ones and are never announced.
+    #[codec(index = 1)]
+    NextSessionAuthorities { session: u32, authorities: Vec<Id> },
+    /// The finality version of `session`, announced together with its authorities, so that the
+    /// format of its justifications is known from the headers alone.
+    #[codec(index = 2)]
+    NextSessionFinalityVersion {
+        session: u32,
+        version: FinalityVersion,
+    },
+    /// The BLS keys of `session`, announced together with its authorities if the session is
+    /// finalized with aggregated BLS signatures, in the order of the authorities.
+    #[codec(index = 3)]
+    NextSessionBlsKeys {
+        session: u32,
+        keys: Vec<BlsKeyRegistration>,
+    },
+}
+
+pub type AlephEquivocationProof<H> = EquivocationProof<H, AuthorityId, AuthoritySignature>;
+
 sp_api::decl_runtime_apis! {
+    #[api_version(8)]
     pub trait AlephSessionApi
     {
         fn next_session_authorities() -> Result<Vec<AuthorityId>, ApiError>;
         fn authorities() -> Vec<AuthorityId>;
+        fn authorities_for_session(session_id: u32) -> Option<Vec<AuthorityId>>;
+        fn bls_keys_for_session(session_id: u32) -> Option<Vec<BlsKeyRegistration>>;
+        fn consensus_params(session_id: u32) -> ConsensusParams;
+        fn finality_version(session_id: u32) -> FinalityVersion;
         fn session_period() -> u32;
         fn millisecs_per_block() -> u64;
+        fn session_schedule() -> SessionSchedule;
+        fn disabled_authorities() -> Vec<u32>;
+        fn submit_report_equivocation_unsigned_extrinsic(
+            equivocation_proof: AlephEquivocationProof<<Block as sp_runtime::traits::Block>::Header>,
+        ) -> Option<()>;
     }
 }